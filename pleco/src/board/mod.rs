@@ -42,6 +42,7 @@ pub mod piece_locations;
 pub mod board_state;
 pub mod fen;
 pub mod perft;
+pub mod pack;
 mod pgn;
 
 /// Represents possible Errors encountered while building a `Board` from a fen string.
@@ -290,6 +291,102 @@ impl Board {
         }
     }
 
+    /// Returns a new `Board` with the colors of the position swapped. Each piece changes color
+    /// and is mirrored vertically, so a white pawn on e2 becomes a black pawn on e7. The side to
+    /// move, castling rights and en-passant square are swapped as well.
+    ///
+    /// The returned `Board` has no move history.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    ///
+    /// let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+    /// let flipped = board.flip_colors();
+    /// assert_eq!(flipped.fen(), "rnbqkbnr/pppp1ppp/8/4p3/8/8/PPPPPPPP/RNBQKBNR w KQkq e6 0 1");
+    /// assert_eq!(flipped.flip_colors().zobrist(), board.zobrist());
+    /// ```
+    pub fn flip_colors(&self) -> Board {
+        let mut castling = Castling::empty();
+        if self.state.castling.contains(Castling::WHITE_K) { castling |= Castling::BLACK_K; }
+        if self.state.castling.contains(Castling::WHITE_Q) { castling |= Castling::BLACK_Q; }
+        if self.state.castling.contains(Castling::BLACK_K) { castling |= Castling::WHITE_K; }
+        if self.state.castling.contains(Castling::BLACK_Q) { castling |= Castling::WHITE_Q; }
+        self.transformed(true, castling, |sq| sq.flip())
+    }
+
+    /// Returns a new `Board` with the position mirrored horizontally, so a piece on the a-file
+    /// moves to the h-file. As the kings and rooks no longer stand on their original squares,
+    /// all castling rights are removed.
+    ///
+    /// The returned `Board` has no move history.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    ///
+    /// let board = Board::from_fen("4k3/8/8/8/8/8/1P6/4K2R w K - 0 1").unwrap();
+    /// assert_eq!(board.mirror_horizontal().fen(), "3k4/8/8/8/8/8/6P1/R2K4 w - - 0 1");
+    /// ```
+    pub fn mirror_horizontal(&self) -> Board {
+        self.transformed(false, Castling::empty(), |sq| SQ(sq.0 ^ 0b000111))
+    }
+
+    /// Returns a new `Board` rotated by 180 degrees, so each piece is mirrored both vertically and
+    /// horizontally. Colors and the side to move are swapped, so the resulting position is
+    /// equivalent to this one. Equal to `board.flip_colors().mirror_horizontal()`.
+    ///
+    /// The returned `Board` has no move history.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    ///
+    /// let board = Board::from_fen("4k3/8/8/8/8/8/1P6/4K2R w K - 0 1").unwrap();
+    /// assert_eq!(board.rotate_180().fen(), "r2k4/6p1/8/8/8/8/8/3K4 b - - 0 1");
+    /// ```
+    pub fn rotate_180(&self) -> Board {
+        self.transformed(true, Castling::empty(), |sq| SQ(sq.0 ^ 0b111111))
+    }
+
+    // Creates a new board with each piece moved to the square given by `map_sq`, and with
+    // the colors of each piece and side to move swapped if `swap_colors`. The keys and
+    // check information are computed from scratch.
+    fn transformed<F: Fn(SQ) -> SQ>(&self, swap_colors: bool, castling: Castling, map_sq: F) -> Board {
+        let mut b = Board {
+            turn: if swap_colors {!self.turn} else {self.turn},
+            bbs: [BitBoard(0); PIECE_TYPE_CNT],
+            bbs_player: [BitBoard(0); PLAYER_CNT],
+            half_moves: 0,
+            depth: 0,
+            piece_counts: [[0; PIECE_TYPE_CNT]; PLAYER_CNT],
+            piece_locations: PieceLocations::blank(),
+            state: Arc::new(BoardState::blank()),
+            magic_helper: Helper::new(),
+        };
+
+        for sq in self.occupied() {
+            let (player, piece) = self.piece_at_sq(sq).player_piece_lossy();
+            let player = if swap_colors {!player} else {player};
+            b.put_piece_c(Piece::make_lossy(player, piece), map_sq(sq));
+        }
+
+        // keep the same move number, adjusting for the side to move
+        b.half_moves = self.half_moves - (self.turn == Player::Black) as u16
+                     + (b.turn == Player::Black) as u16;
+
+        let mut state: BoardState = BoardState::blank();
+        state.castling = castling;
+        state.rule_50 = self.rule_50();
+        state.ep_square = if self.ep_square() == NO_SQ {NO_SQ} else {map_sq(self.ep_square())};
+        state.set(&b);
+        b.state = Arc::new(state);
+        b
+    }
+
     /// Creates a `RandBoard` (Random Board Generator) for generation of `Board`s with random
     /// positions. See the `RandBoard` structure for more information.
     ///
@@ -1281,6 +1378,51 @@ impl Board {
         self.state.psq
     }
 
+    /// Recomputes the positional Score of the board from the piece-square tables in use.
+    ///
+    /// This only needs to be called after the tables have been changed with
+    /// [`psqt::set_psqt`], as the score is otherwise kept up to date as moves are applied.
+    ///
+    /// The scores of the positions before the moves played are recomputed as well, by undoing
+    /// each move and applying it again, so undoing moves afterwards restores scores computed
+    /// from the tables in use.
+    ///
+    /// [`psqt::set_psqt`]: ../helper/psqt/fn.set_psqt.html
+    pub fn recompute_psq(&mut self) {
+        // Only moves played on this board can be undone, not those played before it was cloned.
+        let depth = self.depth;
+        let mut played: Vec<BitMove> = Vec::with_capacity(depth as usize);
+        while played.len() < depth as usize {
+            played.push(self.state.prev_move);
+            if self.state.prev_move.is_null() {
+                unsafe { self.undo_null_move(); }
+            } else {
+                self.undo_move();
+            }
+        }
+
+        let mut score = Score::ZERO;
+        let mut b: BitBoard = self.occupied();
+        while let Some(sq) = b.pop_some_lsb() {
+            score += psq(self.piece_locations.piece_at(sq), sq);
+        }
+        if score != self.state.psq {
+            let mut state: BoardState = (*self.state).clone();
+            state.psq = score;
+            self.state = Arc::new(state);
+        }
+
+        for &mov in played.iter().rev() {
+            if mov.is_null() {
+                unsafe { self.apply_null_move(); }
+            } else {
+                self.apply_move(mov);
+            }
+        }
+        // Undoing a null move leaves the depth as is, unlike applying one.
+        self.depth = depth;
+    }
+
     /// Get the current square of en-passant. This is defined not as the pawn that could be
     /// captured from an en-passant move, but rather the square directly behind it.
     ///
@@ -0,0 +1,211 @@
+//! A compact, canonical binary encoding of a `Board`, for storing large numbers of positions.
+//!
+//! A packed board is 32 bytes long:
+//!
+//! | Bytes    | Contents                                                                  |
+//! |----------|---------------------------------------------------------------------------|
+//! | `0..8`   | The occupancy `BitBoard`, little-endian.                                  |
+//! | `8..24`  | The `Piece` of each occupied square as 4 bits, in order of increasing square, with the first of each pair of squares in the low nibble. Unused nibbles are zero. |
+//! | `24`     | The format version, currently `PACK_VERSION`.                             |
+//! | `25`     | The side to move in bit 0 (set for black), and the castling rights in bits 1 to 4. |
+//! | `26`     | The en-passant square, or `64` if there is none.                          |
+//! | `27`     | The fifty-move rule counter, saturating at 255.                           |
+//! | `28..30` | The number of plies played, little-endian. The full move number is half of this plus one. |
+//! | `30..32` | Reserved, always zero.                                                    |
+//!
+//! Piece codes are the discriminants of `Piece`, with white pieces from `1` to `6` and black
+//! pieces from `9` to `14`. Castling rights use the bits of `Castling`.
+//!
+//! As every field has a single valid encoding, equal positions always pack to equal bytes.
+//!
+//! # Examples
+//!
+//! ```
+//! use pleco::Board;
+//!
+//! let board = Board::start_pos();
+//! let packed: [u8; 32] = board.pack();
+//! let unpacked = Board::unpack(&packed).unwrap();
+//! assert_eq!(board.fen(), unpacked.fen());
+//! ```
+
+use std::fmt;
+
+use core::*;
+use core::sq::{SQ,NO_SQ};
+use core::bitboard::BitBoard;
+use core::masks::*;
+use tools::pleco_arc::Arc;
+use helper::Helper;
+
+use super::{Board,FenBuildError};
+use super::castle_rights::Castling;
+use super::piece_locations::PieceLocations;
+use super::board_state::BoardState;
+use super::fen;
+
+/// The number of bytes of a packed `Board`.
+pub const PACKED_LEN: usize = 32;
+
+/// The version of the packed format written by `Board::pack`.
+pub const PACK_VERSION: u8 = 1;
+
+const VERSION_BYTE: usize = 24;
+const FLAGS_BYTE: usize = 25;
+const EP_BYTE: usize = 26;
+const RULE_50_BYTE: usize = 27;
+const PLIES_BYTES: usize = 28;
+const RESERVED_BYTES: usize = 30;
+
+/// Represents possible Errors encountered while unpacking a `Board`.
+pub enum PackError {
+    TooShort {len: usize},
+    UnsupportedVersion {version: u8},
+    TooManyPieces {num: u8},
+    UnrecognizedPiece {code: u8},
+    InvalidFlags {flags: u8},
+    InvalidEPSquare {sq: u8},
+    NonZeroReserved,
+    InvalidPosition(FenBuildError),
+}
+
+impl fmt::Debug for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PackError::TooShort{len} => writeln!(f, "packed board too short: {} bytes, expected {}", len, PACKED_LEN),
+            PackError::UnsupportedVersion{version} => writeln!(f, "unsupported packed board version: {}", version),
+            PackError::TooManyPieces{num} => writeln!(f, "too many pieces: {}", num),
+            PackError::UnrecognizedPiece{code} => writeln!(f, "unrecognized piece code: {}", code),
+            PackError::InvalidFlags{flags} => writeln!(f, "invalid flags: {:#010b}", flags),
+            PackError::InvalidEPSquare{sq} => writeln!(f, "invalid En-passant square: {}", sq),
+            PackError::NonZeroReserved => writeln!(f, "reserved bytes are not zero"),
+            PackError::InvalidPosition(ref err) => write!(f, "invalid position: {:?}", err),
+        }
+    }
+}
+
+fn piece_of_code(code: u8) -> Option<Piece> {
+    match code {
+        0b0001 => Some(Piece::WhitePawn),
+        0b0010 => Some(Piece::WhiteKnight),
+        0b0011 => Some(Piece::WhiteBishop),
+        0b0100 => Some(Piece::WhiteRook),
+        0b0101 => Some(Piece::WhiteQueen),
+        0b0110 => Some(Piece::WhiteKing),
+        0b1001 => Some(Piece::BlackPawn),
+        0b1010 => Some(Piece::BlackKnight),
+        0b1011 => Some(Piece::BlackBishop),
+        0b1100 => Some(Piece::BlackRook),
+        0b1101 => Some(Piece::BlackQueen),
+        0b1110 => Some(Piece::BlackKing),
+        _ => None,
+    }
+}
+
+impl Board {
+    /// Packs the board into 32 bytes. See the [`pack`] module for the format.
+    ///
+    /// Only the position itself is packed, not the moves leading up to it.
+    ///
+    /// [`pack`]: pack/index.html
+    pub fn pack(&self) -> [u8; PACKED_LEN] {
+        let mut bytes = [0u8; PACKED_LEN];
+        let occupied = self.occupied();
+        bytes[0..8].copy_from_slice(&occupied.0.to_le_bytes());
+
+        let mut bb = occupied;
+        let mut i = 0;
+        while let Some(sq) = bb.pop_some_lsb() {
+            let code = self.piece_at_sq(sq) as u8;
+            bytes[8 + i / 2] |= code << (4 * (i % 2));
+            i += 1;
+        }
+
+        bytes[VERSION_BYTE] = PACK_VERSION;
+        bytes[FLAGS_BYTE] = (self.turn() == Player::Black) as u8 | self.castling_bits() << 1;
+        bytes[EP_BYTE] = self.ep_square().0;
+        bytes[RULE_50_BYTE] = self.rule_50().max(0).min(255) as u8;
+        bytes[PLIES_BYTES..RESERVED_BYTES].copy_from_slice(&self.moves_played().to_le_bytes());
+        bytes
+    }
+
+    /// Unpacks a board packed with [`Board::pack`], reading the first 32 bytes of `bytes`.
+    ///
+    /// # Validation
+    ///
+    /// The packed board is checked in the same way as a FEN string is by [`Board::from_fen`],
+    /// so an invalid position may still be unpacked without an error.
+    ///
+    /// [`Board::pack`]: struct.Board.html#method.pack
+    /// [`Board::from_fen`]: struct.Board.html#method.from_fen
+    pub fn unpack(bytes: &[u8]) -> Result<Board, PackError> {
+        if bytes.len() < PACKED_LEN {
+            return Err(PackError::TooShort {len: bytes.len()});
+        }
+        if bytes[VERSION_BYTE] != PACK_VERSION {
+            return Err(PackError::UnsupportedVersion {version: bytes[VERSION_BYTE]});
+        }
+        if bytes[RESERVED_BYTES..PACKED_LEN].iter().any(|b| *b != 0) {
+            return Err(PackError::NonZeroReserved);
+        }
+
+        let mut occ = [0u8; 8];
+        occ.copy_from_slice(&bytes[0..8]);
+        let mut occupied = BitBoard(u64::from_le_bytes(occ));
+        let num = occupied.count_bits();
+        if num > 32 {
+            return Err(PackError::TooManyPieces {num});
+        }
+        // Nibbles past the last piece must be zero.
+        for i in num as usize..32 {
+            if (bytes[8 + i / 2] >> (4 * (i % 2))) & 0xF != 0 {
+                return Err(PackError::TooManyPieces {num: i as u8 + 1});
+            }
+        }
+
+        let flags = bytes[FLAGS_BYTE];
+        if flags >> 5 != 0 {
+            return Err(PackError::InvalidFlags {flags});
+        }
+        let turn = if flags & 1 == 0 { Player::White } else { Player::Black };
+        let castling = Castling::from_bits(flags >> 1).ok_or(PackError::InvalidFlags {flags})?;
+
+        let ep = bytes[EP_BYTE];
+        let ep_sq = if ep == NO_SQ.0 {
+            NO_SQ
+        } else if ep < 64 && (SQ(ep).rank() == Rank::R3 || SQ(ep).rank() == Rank::R6) {
+            SQ(ep)
+        } else {
+            return Err(PackError::InvalidEPSquare {sq: ep});
+        };
+
+        let mut b = Board {
+            turn,
+            bbs: [BitBoard(0); PIECE_TYPE_CNT],
+            bbs_player: [BitBoard(0); PLAYER_CNT],
+            half_moves: u16::from_le_bytes([bytes[PLIES_BYTES], bytes[PLIES_BYTES + 1]]),
+            depth: 0,
+            piece_counts: [[0; PIECE_TYPE_CNT]; PLAYER_CNT],
+            piece_locations: PieceLocations::blank(),
+            state: Arc::new(BoardState::blank()),
+            magic_helper: Helper::new(),
+        };
+
+        let mut i = 0;
+        while let Some(sq) = occupied.pop_some_lsb() {
+            let code = (bytes[8 + i / 2] >> (4 * (i % 2))) & 0xF;
+            let piece = piece_of_code(code).ok_or(PackError::UnrecognizedPiece {code})?;
+            b.put_piece_c(piece, sq);
+            i += 1;
+        }
+
+        let mut state = BoardState::blank();
+        state.castling = castling;
+        state.rule_50 = bytes[RULE_50_BYTE] as i16;
+        state.ep_square = ep_sq;
+        state.set(&b);
+        b.state = Arc::new(state);
+
+        fen::is_valid_fen(b).map_err(PackError::InvalidPosition)
+    }
+}
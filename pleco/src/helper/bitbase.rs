@@ -0,0 +1,234 @@
+//! Bitbase for the King and Pawn vs. King endgame.
+//!
+//! The bitbase is generated by retrograde analysis when the static tables are initialized,
+//! and holds one bit per position, set if the side with the pawn wins. Only positions with
+//! white holding the pawn on files A through D are stored; other positions are mirrored
+//! before probing.
+
+use {SQ,Player};
+use core::masks::*;
+
+use super::boards::{king_moves,pawn_attacks_from};
+
+// There are 24 possible pawn squares: files A to D and ranks 2 to 7.
+const MAX_INDEX: usize = 2 * 24 * 64 * 64;
+
+static mut KPK_BITBASE: [u32; MAX_INDEX / 32] = [0; MAX_INDEX / 32];
+
+// Results of a position during generation. These are OR'd together when classifying a position
+// by the positions it can move to.
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+/// Generates the KPK bitbase.
+#[cold]
+pub fn init_kpk() {
+    let mut db: Vec<KPKPosition> = (0..MAX_INDEX).map(KPKPosition::new).collect();
+
+    // Iterate through the positions until none of the unknown positions can be changed to
+    // either wins or draws.
+    let mut repeat = true;
+    while repeat {
+        repeat = false;
+        for idx in 0..MAX_INDEX {
+            if db[idx].result == UNKNOWN {
+                let result = db[idx].classify(&db);
+                if result != UNKNOWN {
+                    db[idx].result = result;
+                    repeat = true;
+                }
+            }
+        }
+    }
+
+    unsafe {
+        for (idx, pos) in db.iter().enumerate() {
+            if pos.result == WIN {
+                KPK_BITBASE[idx / 32] |= 1 << (idx % 32);
+            }
+        }
+    }
+}
+
+/// Probes the bitbase, returning if the side with the pawn wins.
+///
+/// `strong_side` is the player with the pawn, `wksq` and `wpsq` the squares of their king and
+/// pawn, `bksq` the square of the defending king, and `stm` the side to move.
+#[inline]
+pub fn probe(strong_side: Player, wksq: SQ, wpsq: SQ, bksq: SQ, stm: Player) -> bool {
+    debug_assert!(wksq.is_okay() && wpsq.is_okay() && bksq.is_okay());
+    let flip_ranks = (strong_side as u8) * 56;
+    let flip_files = if wpsq.file_idx_of_sq() >= 4 { 7 } else { 0 };
+    let normalize = |sq: SQ| SQ(sq.0 ^ flip_ranks ^ flip_files);
+
+    let us = if stm == strong_side { Player::White } else { Player::Black };
+    let idx = index(us, normalize(bksq), normalize(wksq), normalize(wpsq));
+    unsafe {
+        (*KPK_BITBASE.get_unchecked(idx / 32) >> (idx % 32)) & 1 != 0
+    }
+}
+
+// Information is mapped in a way that minimizes the number of iterations:
+//
+// bits  0-5: white king square
+// bits 6-11: black king square
+// bit    12: side to move
+// bits 13-14: pawn file (from A to D)
+// bits 15-17: 7 minus the pawn's rank (from rank 7 to rank 2)
+#[inline(always)]
+fn index(us: Player, bksq: SQ, wksq: SQ, psq: SQ) -> usize {
+    wksq.0 as usize
+        | (bksq.0 as usize) << 6
+        | (us as usize) << 12
+        | (psq.file_idx_of_sq() as usize) << 13
+        | (6 - psq.rank_idx_of_sq() as usize) << 15
+}
+
+struct KPKPosition {
+    us: Player,
+    ksq: [SQ; PLAYER_CNT],
+    psq: SQ,
+    result: u8,
+}
+
+impl KPKPosition {
+    fn new(idx: usize) -> KPKPosition {
+        let wksq = SQ((idx & 0x3F) as u8);
+        let bksq = SQ(((idx >> 6) & 0x3F) as u8);
+        let us = if (idx >> 12) & 1 == 0 { Player::White } else { Player::Black };
+        let psq = SQ((((6 - ((idx >> 15) & 0x7)) << 3) | ((idx >> 13) & 0x3)) as u8);
+        let push_sq = SQ(psq.0 + 8);
+
+        let result = if wksq.distance(bksq) <= 1
+            || wksq == psq
+            || bksq == psq
+            || (us == Player::White && pawn_attacks_from(psq, Player::White) & bksq.to_bb().0 != 0) {
+            // Two pieces on the same square, or a king can be captured.
+            INVALID
+        } else if us == Player::White
+            && psq.rank_idx_of_sq() == 6
+            && wksq != push_sq
+            && (bksq.distance(push_sq) > 1 || king_moves(wksq) & push_sq.to_bb().0 != 0) {
+            // The pawn promotes without being captured.
+            WIN
+        } else if us == Player::Black
+            && (king_moves(bksq) & !(king_moves(wksq) | pawn_attacks_from(psq, Player::White)) == 0
+                || king_moves(bksq) & psq.to_bb().0 & !king_moves(wksq) != 0) {
+            // Stalemate, or the king captures the undefended pawn.
+            DRAW
+        } else {
+            UNKNOWN
+        };
+
+        KPKPosition {
+            us,
+            ksq: [wksq, bksq],
+            psq,
+            result,
+        }
+    }
+
+    // White to move: if one move leads to a win, the position is a win. If all moves lead to
+    // draws, the position is a draw.
+    //
+    // Black to move: if one move leads to a draw, the position is a draw. If all moves lead to
+    // wins, the position is a win.
+    //
+    // Otherwise, the position remains unknown.
+    fn classify(&self, db: &[KPKPosition]) -> u8 {
+        let us = self.us;
+        let them = !us;
+        let (good, bad) = match us {
+            Player::White => (WIN, DRAW),
+            Player::Black => (DRAW, WIN),
+        };
+
+        let mut r = INVALID;
+        let mut b = king_moves(self.ksq[us as usize]);
+        while b != 0 {
+            let to = SQ(b.trailing_zeros() as u8);
+            b &= b - 1;
+            r |= match us {
+                Player::White => db[index(them, self.ksq[Player::Black as usize], to, self.psq)].result,
+                Player::Black => db[index(them, to, self.ksq[Player::White as usize], self.psq)].result,
+            };
+        }
+
+        if us == Player::White {
+            let wksq = self.ksq[Player::White as usize];
+            let bksq = self.ksq[Player::Black as usize];
+            let push_sq = SQ(self.psq.0 + 8);
+
+            // Single push
+            if self.psq.rank_idx_of_sq() < 6 {
+                r |= db[index(them, bksq, wksq, push_sq)].result;
+            }
+
+            // Double push
+            if self.psq.rank_idx_of_sq() == 1 && push_sq != wksq && push_sq != bksq {
+                r |= db[index(them, bksq, wksq, SQ(push_sq.0 + 8))].result;
+            }
+        }
+
+        if r & good != 0 {
+            good
+        } else if r & UNKNOWN != 0 {
+            UNKNOWN
+        } else {
+            bad
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helper::Helper;
+
+    fn win(strong_side: Player, wksq: SQ, wpsq: SQ, bksq: SQ, stm: Player) -> bool {
+        Helper::new().probe_kpk(strong_side, wksq, wpsq, bksq, stm)
+    }
+
+    #[test]
+    fn kpk_positions() {
+        // Attacking king on a key square.
+        assert!(win(Player::White, SQ::D6, SQ::D4, SQ::D8, Player::White));
+        assert!(win(Player::White, SQ::D6, SQ::D4, SQ::D8, Player::Black));
+        // The opposition decides.
+        assert!(!win(Player::White, SQ::D5, SQ::D4, SQ::D7, Player::White));
+        assert!(win(Player::White, SQ::D5, SQ::D4, SQ::D7, Player::Black));
+        // The defending king blocks the pawn.
+        assert!(!win(Player::White, SQ::D3, SQ::D4, SQ::D6, Player::White));
+        // Rook pawn with the defending king in the corner.
+        assert!(!win(Player::White, SQ::B1, SQ::A2, SQ::A8, Player::White));
+        // The defending king is outside of the square of the pawn.
+        assert!(win(Player::White, SQ::A1, SQ::A4, SQ::H2, Player::Black));
+        // The undefended pawn is captured.
+        assert!(!win(Player::White, SQ::H1, SQ::D2, SQ::D3, Player::Black));
+        // Stalemate.
+        assert!(!win(Player::White, SQ::B6, SQ::A7, SQ::A8, Player::Black));
+        // The same key square position, with black holding the pawn on the king side.
+        assert!(win(Player::Black, SQ::E3, SQ::E5, SQ::E1, Player::Black));
+        assert!(win(Player::Black, SQ::E3, SQ::E5, SQ::E1, Player::White));
+    }
+
+    #[test]
+    fn kpk_symmetry() {
+        Helper::new();
+        for wksq in 0..64u8 {
+            for bksq in 0..64u8 {
+                for psq in 8..56u8 {
+                    for &stm in [Player::White, Player::Black].iter() {
+                        let w = probe(Player::White, SQ(wksq), SQ(psq), SQ(bksq), stm);
+                        let b = probe(Player::Black, SQ(wksq ^ 56), SQ(psq ^ 56), SQ(bksq ^ 56), !stm);
+                        let m = probe(Player::White, SQ(wksq ^ 7), SQ(psq ^ 7), SQ(bksq ^ 7), stm);
+                        assert_eq!(w, b);
+                        assert_eq!(w, m);
+                    }
+                }
+            }
+        }
+    }
+}
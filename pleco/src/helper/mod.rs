@@ -15,7 +15,8 @@
 mod magic;
 mod boards;
 mod zobrist;
-mod psqt;
+pub mod psqt;
+mod bitbase;
 pub mod prelude;
 
 
@@ -216,6 +217,15 @@ impl Helper {
     pub fn piece_value(self, piece: Piece, eg: bool) -> Value {
         prelude::piece_value(piece, eg)
     }
+
+    /// Probes the King and Pawn vs. King bitbase, returning if the side with the pawn wins.
+    ///
+    /// `strong_side` is the player with the pawn, `wksq` and `wpsq` the squares of their king and
+    /// pawn, `bksq` the square of the defending king, and `stm` the side to move.
+    #[inline(always)]
+    pub fn probe_kpk(self, strong_side: Player, wksq: SQ, wpsq: SQ, bksq: SQ, stm: Player) -> bool {
+        prelude::probe_kpk(strong_side, wksq, wpsq, bksq, stm)
+    }
 }
 
 
@@ -15,6 +15,7 @@ use super::psqt;
 use super::zobrist;
 use super::magic;
 use super::boards;
+use super::bitbase;
 
 use {SQ,BitBoard,Player,PieceType,File,Rank,Piece};
 use core::score::{Score,Value};
@@ -38,6 +39,7 @@ pub fn init_statics() {
         magic::init_magics();
         compiler_fence(Ordering::SeqCst);
         boards::init_boards();
+        bitbase::init_kpk();
         fence(Ordering::SeqCst);
         compiler_fence(Ordering::SeqCst);
     });
@@ -238,4 +240,9 @@ pub fn piece_value(piece: Piece, eg: bool) -> Value {
 pub fn piecetype_value(piece_type: PieceType, eg: bool) -> Value {
     let piece: Piece = unsafe {mem::transmute(piece_type)};
     psqt::piece_value(piece, eg)
-}
\ No newline at end of file
+}
+/// Probes the King and Pawn vs. King bitbase, returning if the side with the pawn wins.
+#[inline(always)]
+pub fn probe_kpk(strong_side: Player, wksq: SQ, wpsq: SQ, bksq: SQ, stm: Player) -> bool {
+    bitbase::probe(strong_side, wksq, wpsq, bksq, stm)
+}
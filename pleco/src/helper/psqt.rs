@@ -1,4 +1,38 @@
-use {Player,SQ,File,Piece};
+//! Piece-square tables, giving the `Score` of each piece on each square.
+//!
+//! Each piece's score is its material value plus a positional bonus. The bonuses are described
+//! by a [`Psqt`], which is symmetric between the a-d and e-h files, and given from white's point
+//! of view. The scores of a `Board` are kept up to date incrementally through `Board::psq()`.
+//!
+//! The bonuses in use can be replaced at runtime with [`set_psqt`], such as with a [`Psqt`]
+//! loaded from a file. Boards created before the change keep their old scores until
+//! `Board::recompute_psq()` is called on them.
+//!
+//! # Examples
+//!
+//! ```
+//! use pleco::{Board,PieceType,SQ};
+//! use pleco::core::score::Score;
+//! use pleco::helper::psqt::{self,Psqt};
+//!
+//! let mut board = Board::start_pos();
+//! let mut table = Psqt::default();
+//! table.set_bonus(PieceType::N, SQ::B1, Score(-50, -50));
+//! // No other thread is using the tables.
+//! unsafe { psqt::set_psqt(&table); }
+//! board.recompute_psq();
+//! unsafe { psqt::set_psqt(&Psqt::default()); }
+//! ```
+//!
+//! [`Psqt`]: struct.Psqt.html
+//! [`set_psqt`]: fn.set_psqt.html
+
+use std::fmt;
+use std::io;
+use std::fs::File as FsFile;
+use std::io::{Read,Write};
+
+use {Player,SQ,File,Piece,PieceType};
 use core::masks::*;
 use core::score::*;
 
@@ -88,6 +122,8 @@ const BONUS: [[[Score; (FILE_CNT / 2)]; RANK_CNT]; PIECE_TYPE_CNT] = [
 static mut PSQ: [[Score; SQ_CNT]; PIECE_CNT] =
     [[Score(0,0); SQ_CNT]; PIECE_CNT];
 
+static mut ACTIVE: Psqt = Psqt { bonus: BONUS };
+
 static PIECE_VALUE: [[Value; PHASE_CNT]; PIECE_CNT] =
     [[0, 0],                 // Empty
     [ PAWN_MG,    PAWN_EG],  // White Pawn
@@ -108,18 +144,228 @@ static PIECE_VALUE: [[Value; PHASE_CNT]; PIECE_CNT] =
     ];
 
 #[cold]
-pub fn init_psqt() {
+pub(crate) fn init_psqt() {
+    unsafe {
+        fill_psqt(&ACTIVE);
+    }
+}
+
+unsafe fn fill_psqt(psqt: &Psqt) {
     for piece in 0..PIECE_TYPE_CNT {
         let v: Score = Score(PIECE_VALUE[piece][0], PIECE_VALUE[piece][1]);
         for s in 0..SQ_CNT {
             let sq: SQ = SQ(s as u8);
             let f: File = sq.file().min(!sq.file());
-            let score = v + BONUS[piece][sq.rank() as usize][f as usize];
-            unsafe {
-                PSQ[(Player::White as usize) << 3 | piece][s] = score;
-                PSQ[(Player::Black as usize) << 3 | piece][sq.flip().0 as usize] = -score;
+            let score = v + psqt.bonus[piece][sq.rank() as usize][f as usize];
+            PSQ[(Player::White as usize) << 3 | piece][s] = score;
+            PSQ[(Player::Black as usize) << 3 | piece][sq.flip().0 as usize] = -score;
+        }
+    }
+}
+
+/// Replaces the piece-square bonuses in use.
+///
+/// The scores of existing boards aren't updated, `Board::recompute_psq()` must be called on
+/// each of them.
+///
+/// # Safety
+///
+/// Must not be called while moves are being applied to a `Board` on another thread, such as
+/// during a search, nor while another thread reads or replaces the tables.
+#[cold]
+pub unsafe fn set_psqt(psqt: &Psqt) {
+    super::prelude::init_statics();
+    ACTIVE = *psqt;
+    fill_psqt(psqt);
+}
+
+/// Returns the piece-square bonuses in use.
+pub fn current_psqt() -> Psqt {
+    super::prelude::init_statics();
+    unsafe { ACTIVE }
+}
+
+/// A set of piece-square bonuses, on top of the material value of each piece.
+///
+/// Bonuses are given for white pieces, and are mirrored across the board for black pieces. The
+/// bonus of a square on the e-h files is the same as the mirrored square on the a-d files.
+///
+/// # Text format
+///
+/// A `Psqt` is written by its `Display` implementation and read by [`Psqt::parse`] as six
+/// sections, one for each piece type. Each section begins with the piece's character
+/// (`P`, `N`, `B`, `R`, `Q` or `K`) on its own line, followed by eight lines for ranks 1 to 8.
+/// Each rank has the middle-game and end-game bonuses of the a, b, c and d files, for eight
+/// integers total. Blank lines and anything following a `#` are ignored.
+///
+/// [`Psqt::parse`]: #method.parse
+#[derive(Copy, Clone, PartialEq)]
+pub struct Psqt {
+    bonus: [[[Score; FILE_CNT / 2]; RANK_CNT]; PIECE_TYPE_CNT],
+}
+
+impl Default for Psqt {
+    /// Returns the built-in bonuses.
+    fn default() -> Self {
+        Psqt { bonus: BONUS }
+    }
+}
+
+impl Psqt {
+    /// Returns a `Psqt` with no bonuses.
+    pub fn blank() -> Self {
+        Psqt { bonus: [[[Score::ZERO; FILE_CNT / 2]; RANK_CNT]; PIECE_TYPE_CNT] }
+    }
+
+    /// Returns the bonus of a white piece at a square.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `piece` is `PieceType::None` or `PieceType::All`.
+    pub fn bonus(&self, piece: PieceType, sq: SQ) -> Score {
+        assert!(piece.is_real());
+        let f: File = sq.file().min(!sq.file());
+        self.bonus[piece as usize][sq.rank() as usize][f as usize]
+    }
+
+    /// Sets the bonus of a white piece at a square. As files are mirrored, this also sets the
+    /// bonus of the square on the opposite side of the board.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `piece` is `PieceType::None` or `PieceType::All`.
+    pub fn set_bonus(&mut self, piece: PieceType, sq: SQ, score: Score) {
+        assert!(piece.is_real());
+        let f: File = sq.file().min(!sq.file());
+        self.bonus[piece as usize][sq.rank() as usize][f as usize] = score;
+    }
+
+    /// Parses a `Psqt` from its text format.
+    pub fn parse(text: &str) -> Result<Psqt, PsqtError> {
+        let mut psqt = Psqt::blank();
+        let mut seen = [false; PIECE_TYPE_CNT];
+        let mut current: Option<(PieceType, usize)> = None;
+
+        for (i, line) in text.lines().enumerate() {
+            let line_num = i + 1;
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            match current {
+                None => {
+                    let piece = piece_of_char(line)
+                        .ok_or_else(|| PsqtError::UnrecognizedPiece {line: line_num, token: line.to_string()})?;
+                    if seen[piece as usize] {
+                        return Err(PsqtError::DuplicatePiece {piece: piece.char_upper()});
+                    }
+                    seen[piece as usize] = true;
+                    current = Some((piece, 0));
+                }
+                Some((piece, rank)) => {
+                    let values = line.split(|c: char| c.is_whitespace() || c == ',')
+                        .filter(|t| !t.is_empty())
+                        .map(|t| t.parse::<Value>())
+                        .collect::<Result<Vec<Value>, _>>()
+                        .map_err(|_| PsqtError::InvalidRank {line: line_num})?;
+                    if values.len() != FILE_CNT {
+                        return Err(PsqtError::InvalidRank {line: line_num});
+                    }
+                    for f in 0..FILE_CNT / 2 {
+                        psqt.bonus[piece as usize][rank][f] = Score(values[2 * f], values[2 * f + 1]);
+                    }
+                    current = if rank + 1 == RANK_CNT { None } else { Some((piece, rank + 1)) };
+                }
+            }
+        }
+
+        if let Some((piece, _)) = current {
+            return Err(PsqtError::MissingRanks {piece: piece.char_upper()});
+        }
+        if let Some(piece) = PIECE_CHARS.iter().find(|&&(_, p)| !seen[p as usize]) {
+            return Err(PsqtError::MissingPiece {piece: piece.0});
+        }
+        Ok(psqt)
+    }
+
+    /// Reads a `Psqt` in its text format from a file.
+    pub fn load(path: &str) -> Result<Psqt, PsqtError> {
+        let mut text = String::new();
+        FsFile::open(path)?.read_to_string(&mut text)?;
+        Psqt::parse(&text)
+    }
+
+    /// Writes the `Psqt` in its text format to a file.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut file = FsFile::create(path)?;
+        write!(file, "{}", self)
+    }
+}
+
+impl fmt::Display for Psqt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for &(c, piece) in PIECE_CHARS.iter() {
+            writeln!(f, "{}", c)?;
+            for rank in self.bonus[piece as usize].iter() {
+                for (i, score) in rank.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, "  ")?;
+                    }
+                    write!(f, "{:4} {:4}", score.0, score.1)?;
+                }
+                writeln!(f)?;
             }
         }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Psqt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Represents possible Errors encountered while reading a `Psqt`.
+pub enum PsqtError {
+    Io(io::Error),
+    UnrecognizedPiece {line: usize, token: String},
+    DuplicatePiece {piece: char},
+    MissingPiece {piece: char},
+    MissingRanks {piece: char},
+    InvalidRank {line: usize},
+}
+
+impl fmt::Debug for PsqtError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PsqtError::Io(ref e) => writeln!(f, "{}", e),
+            PsqtError::UnrecognizedPiece{line, ref token} => writeln!(f, "line {}: unrecognized piece: {}", line, token),
+            PsqtError::DuplicatePiece{piece} => writeln!(f, "duplicate table for piece: {}", piece),
+            PsqtError::MissingPiece{piece} => writeln!(f, "missing table for piece: {}", piece),
+            PsqtError::MissingRanks{piece} => writeln!(f, "table for piece {} has less than 8 ranks", piece),
+            PsqtError::InvalidRank{line} => writeln!(f, "line {}: expected 8 integers", line),
+        }
+    }
+}
+
+impl From<io::Error> for PsqtError {
+    fn from(e: io::Error) -> Self {
+        PsqtError::Io(e)
+    }
+}
+
+const PIECE_CHARS: [(char, PieceType); 6] = [
+    ('P', PieceType::P), ('N', PieceType::N), ('B', PieceType::B),
+    ('R', PieceType::R), ('Q', PieceType::Q), ('K', PieceType::K)];
+
+fn piece_of_char(token: &str) -> Option<PieceType> {
+    let mut chars = token.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => PIECE_CHARS.iter()
+            .find(|&&(p, _)| p == c.to_ascii_uppercase())
+            .map(|&(_, piece)| piece),
+        _ => None,
     }
 }
 
@@ -154,4 +400,30 @@ mod tests {
         assert_eq!(psq(Piece::BlackKnight,  SQ::B4), -psq(Piece::WhiteKnight,SQ::B5));
     }
 
+    #[test]
+    fn text_round_trip() {
+        let psqt = Psqt::default();
+        let text = psqt.to_string();
+        assert!(Psqt::parse(&text).unwrap() == psqt);
+        assert_eq!(psqt.bonus(PieceType::N, SQ::G1), psqt.bonus(PieceType::N, SQ::B1));
+
+        let mut edited = psqt;
+        edited.set_bonus(PieceType::Q, SQ::H8, Score(-1, 2));
+        assert_eq!(edited.bonus(PieceType::Q, SQ::A8), Score(-1, 2));
+        let text = format!("# edited\n\n{}", edited);
+        assert!(Psqt::parse(&text).unwrap() == edited);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let text = Psqt::default().to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(Psqt::parse(&lines[..lines.len() - 1].join("\n")).is_err());
+        assert!(Psqt::parse(&lines[..lines.len() - 9].join("\n")).is_err());
+        assert!(Psqt::parse(&text.replacen("P", "X", 1)).is_err());
+        assert!(Psqt::parse(&text.replacen("N", "P", 1)).is_err());
+        assert!(Psqt::parse(&text.replacen("-11", "a", 1)).is_err());
+        assert!(Psqt::parse(&format!("{}1", text)).is_err());
+    }
+
 }
\ No newline at end of file
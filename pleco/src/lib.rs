@@ -23,6 +23,11 @@
 //! If on nightly rust, the feature `nightly` is available. This enables some nightly
 //! optimizations and speed improvements.
 //!
+//! # Serde
+//!
+//! The feature `serde` implements `Serialize` and `Deserialize` for the core types and `Board`,
+//! using chess notation for human-readable formats and compact encodings for binary ones.
+//!
 //! # Safety
 //!
 //! While generally a safe library, pleco was built with a focus of speed in mind. Usage of methods
@@ -90,12 +95,16 @@ extern crate num_cpus;
 extern crate rand;
 extern crate rayon;
 extern crate mucow;
+#[cfg(feature = "serde")]
+extern crate serde;
 
 pub mod core;
 pub mod board;
 pub mod bots;
 pub mod helper;
 pub mod tools;
+#[cfg(feature = "serde")]
+mod serde_impls;
 
 pub use board::Board;
 pub use core::piece_move::{BitMove,ScoringMove};
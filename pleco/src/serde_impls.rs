@@ -0,0 +1,396 @@
+//! `Serialize` and `Deserialize` implementations of the core types, enabled with the `serde`
+//! feature.
+//!
+//! Human-readable formats, such as JSON, get the notation used elsewhere in chess:
+//!
+//! | Type        | Human-readable                      | Compact                   |
+//! |-------------|-------------------------------------|---------------------------|
+//! | `BitMove`   | UCI notation, `"e2e4"`              | The raw `u16`             |
+//! | `SQ`        | `"e4"`                              | The `u8` index            |
+//! | `BitBoard`  | A hex string, `"0x000000000000ff00"` | The `u64`                |
+//! | `Piece`     | The FEN character, `"N"` or `"n"`, or `"-"` for `Piece::None` | The `u8` discriminant |
+//! | `PieceType` | `"P"`, `"N"`, `"B"`, `"R"`, `"Q"`, `"K"`, `"-"` for `PieceType::None` and `"*"` for `PieceType::All` | The `u8` discriminant |
+//! | `Player`    | `"white"` or `"black"`              | The `u8` discriminant     |
+//! | `Score`     | `{"mg": 35, "eg": -12}`             | The pair of `i32`s        |
+//! | `Castling`  | As in a FEN, `"KQkq"` or `"-"`      | The `u8` bits             |
+//! | `Board`     | The FEN                             | The bytes of `Board::pack` |
+//!
+//! UCI notation doesn't hold a move's flags other than promotions, so a `BitMove` read from a
+//! human-readable format is only a quiet move or a promotion between the right squares. Such a
+//! move should be matched against the legal moves of its board, by comparing the
+//! `BitMove::stringify` of each.
+
+use std::fmt;
+
+use serde::{Serialize,Serializer,Deserialize,Deserializer};
+use serde::de::{self,Visitor,SeqAccess,MapAccess,Unexpected};
+use serde::ser::SerializeStruct;
+
+use core::*;
+use core::sq::SQ;
+use core::bitboard::BitBoard;
+use core::piece_move::BitMove;
+use core::score::{Score,Value};
+use board::Board;
+use board::castle_rights::Castling;
+
+// The width of the compact form of a type, as non-self-describing formats need to know it.
+#[derive(Copy, Clone)]
+enum Width {
+    U8,
+    U16,
+    U64,
+}
+
+// Visits either the human-readable string form of a type, or its compact unsigned integer form.
+struct StrOrInt<T> {
+    expecting: &'static str,
+    width: Width,
+    from_str: fn(&str) -> Option<T>,
+    from_int: fn(u64) -> Option<T>,
+}
+
+impl<'de, T> Visitor<'de> for StrOrInt<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.expecting)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        (self.from_str)(v).ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<T, E> {
+        (self.from_int)(v).ok_or_else(|| E::invalid_value(Unexpected::Unsigned(v), &self))
+    }
+}
+
+fn deserialize_str_or_int<'de, D, T>(d: D, visitor: StrOrInt<T>) -> Result<T, D::Error>
+    where D: Deserializer<'de>
+{
+    if d.is_human_readable() {
+        return d.deserialize_str(visitor);
+    }
+    match visitor.width {
+        Width::U8 => d.deserialize_u8(visitor),
+        Width::U16 => d.deserialize_u16(visitor),
+        Width::U64 => d.deserialize_u64(visitor),
+    }
+}
+
+fn sq_from_str(s: &str) -> Option<SQ> {
+    let bytes = s.as_bytes();
+    if bytes.len() != 2 || bytes[0] < b'a' || bytes[0] > b'h' || bytes[1] < b'1' || bytes[1] > b'8' {
+        return None;
+    }
+    Some(SQ((bytes[1] - b'1') * 8 + (bytes[0] - b'a')))
+}
+
+impl Serialize for SQ {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.collect_str(self)
+        } else {
+            s.serialize_u8(self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SQ {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        deserialize_str_or_int(d, StrOrInt {
+            expecting: "a square",
+            width: Width::U8,
+            from_str: sq_from_str,
+            from_int: |v| if v < 64 { Some(SQ(v as u8)) } else { None },
+        })
+    }
+}
+
+fn bit_move_from_str(s: &str) -> Option<BitMove> {
+    if s.len() != 4 && s.len() != 5 {
+        return None;
+    }
+    let src = sq_from_str(s.get(0..2)?)?;
+    let dst = sq_from_str(s.get(2..4)?)?;
+    let flag = match s.get(4..) {
+        Some("") => BitMove::FLAG_QUIET,
+        Some("n") => BitMove::FLAG_PROMO_N,
+        Some("b") => BitMove::FLAG_PROMO_B,
+        Some("r") => BitMove::FLAG_PROMO_R,
+        Some("q") => BitMove::FLAG_PROMO_Q,
+        _ => return None,
+    };
+    Some(BitMove::make(flag, src, dst))
+}
+
+impl Serialize for BitMove {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.collect_str(self)
+        } else {
+            s.serialize_u16(self.get_raw())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BitMove {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        deserialize_str_or_int(d, StrOrInt {
+            expecting: "a move in UCI notation",
+            width: Width::U16,
+            from_str: bit_move_from_str,
+            from_int: |v| if v <= u16::max_value() as u64 { Some(BitMove::new(v as u16)) } else { None },
+        })
+    }
+}
+
+impl Serialize for BitBoard {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.collect_str(&format_args!("{:#018x}", self.0))
+        } else {
+            s.serialize_u64(self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BitBoard {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        deserialize_str_or_int(d, StrOrInt {
+            expecting: "a hexadecimal bitboard",
+            width: Width::U64,
+            from_str: |s| {
+                let hex = s.trim_start_matches("0x");
+                if hex.len() == s.len() {
+                    return None;
+                }
+                u64::from_str_radix(hex, 16).ok().map(BitBoard)
+            },
+            from_int: |v| Some(BitBoard(v)),
+        })
+    }
+}
+
+const ALL_PIECES: [Piece; 13] = [
+    Piece::None,
+    Piece::WhitePawn, Piece::WhiteKnight, Piece::WhiteBishop,
+    Piece::WhiteRook, Piece::WhiteQueen, Piece::WhiteKing,
+    Piece::BlackPawn, Piece::BlackKnight, Piece::BlackBishop,
+    Piece::BlackRook, Piece::BlackQueen, Piece::BlackKing,
+];
+
+impl Serialize for Piece {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.serialize_char(self.character().unwrap_or('-'))
+        } else {
+            s.serialize_u8(*self as u8)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Piece {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        deserialize_str_or_int(d, StrOrInt {
+            expecting: "a piece",
+            width: Width::U8,
+            from_str: |s| ALL_PIECES.iter()
+                .find(|p| s.chars().eq(p.character().or(Some('-'))))
+                .cloned(),
+            from_int: |v| ALL_PIECES.iter().find(|p| **p as u64 == v).cloned(),
+        })
+    }
+}
+
+const ALL_PIECE_TYPES: [PieceType; 8] = [
+    PieceType::None, PieceType::P, PieceType::N, PieceType::B,
+    PieceType::R, PieceType::Q, PieceType::K, PieceType::All,
+];
+
+fn piece_type_char(piece_type: PieceType) -> char {
+    match piece_type {
+        PieceType::None => '-',
+        PieceType::All => '*',
+        _ => piece_type.char_upper(),
+    }
+}
+
+impl Serialize for PieceType {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.serialize_char(piece_type_char(*self))
+        } else {
+            s.serialize_u8(*self as u8)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PieceType {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        deserialize_str_or_int(d, StrOrInt {
+            expecting: "a piece type",
+            width: Width::U8,
+            from_str: |s| ALL_PIECE_TYPES.iter()
+                .find(|p| s.chars().eq(Some(piece_type_char(**p))))
+                .cloned(),
+            from_int: |v| ALL_PIECE_TYPES.get(v as usize).cloned(),
+        })
+    }
+}
+
+impl Serialize for Player {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.serialize_str(match *self {
+                Player::White => "white",
+                Player::Black => "black",
+            })
+        } else {
+            s.serialize_u8(*self as u8)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Player {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        deserialize_str_or_int(d, StrOrInt {
+            expecting: "a player",
+            width: Width::U8,
+            from_str: |s| match s {
+                "white" => Some(Player::White),
+                "black" => Some(Player::Black),
+                _ => None,
+            },
+            from_int: |v| match v {
+                0 => Some(Player::White),
+                1 => Some(Player::Black),
+                _ => None,
+            },
+        })
+    }
+}
+
+impl Serialize for Score {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut state = s.serialize_struct("Score", 2)?;
+        state.serialize_field("mg", &self.mg())?;
+        state.serialize_field("eg", &self.eg())?;
+        state.end()
+    }
+}
+
+struct ScoreVisitor;
+
+impl<'de> Visitor<'de> for ScoreVisitor {
+    type Value = Score;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a score with a middle game and end game value")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Score, A::Error> {
+        let mg: Value = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let eg: Value = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok(Score(mg, eg))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Score, A::Error> {
+        let mut mg: Option<Value> = None;
+        let mut eg: Option<Value> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "mg" => mg = Some(map.next_value()?),
+                "eg" => eg = Some(map.next_value()?),
+                _ => return Err(de::Error::unknown_field(&key, &["mg", "eg"])),
+            }
+        }
+        let mg = mg.ok_or_else(|| de::Error::missing_field("mg"))?;
+        let eg = eg.ok_or_else(|| de::Error::missing_field("eg"))?;
+        Ok(Score(mg, eg))
+    }
+}
+
+impl<'de> Deserialize<'de> for Score {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        d.deserialize_struct("Score", &["mg", "eg"], ScoreVisitor)
+    }
+}
+
+impl Serialize for Castling {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.serialize_str(&self.pretty_string())
+        } else {
+            s.serialize_u8(self.bits())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Castling {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        deserialize_str_or_int(d, StrOrInt {
+            expecting: "castling rights",
+            width: Width::U8,
+            from_str: |s| {
+                if s == "-" {
+                    return Some(Castling::empty());
+                }
+                if s.is_empty() || !s.chars().all(|c| "KQkq".contains(c)) {
+                    return None;
+                }
+                let mut castling = Castling::empty();
+                s.chars().for_each(|c| castling.add_castling_char(c));
+                Some(castling)
+            },
+            from_int: |v| if v <= u8::max_value() as u64 { Castling::from_bits(v as u8) } else { None },
+        })
+    }
+}
+
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.serialize_str(&self.fen())
+        } else {
+            s.serialize_bytes(&self.pack())
+        }
+    }
+}
+
+struct BoardVisitor;
+
+impl<'de> Visitor<'de> for BoardVisitor {
+    type Value = Board;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a FEN string or a packed board")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Board, E> {
+        Board::from_fen(v).map_err(|e| E::custom(format_args!("invalid FEN: {:?}", e)))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Board, E> {
+        Board::unpack(v).map_err(|e| E::custom(format_args!("invalid packed board: {:?}", e)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Board, A::Error> {
+        let mut bytes: Vec<u8> = Vec::new();
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        self.visit_bytes(&bytes)
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        if d.is_human_readable() {
+            d.deserialize_str(BoardVisitor)
+        } else {
+            d.deserialize_bytes(BoardVisitor)
+        }
+    }
+}
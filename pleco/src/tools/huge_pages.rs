@@ -0,0 +1,197 @@
+//! Allocation of large, zeroed tables, backed by huge pages where possible.
+//!
+//! Tables spanning multiple gigabytes, such as the [`TranspositionTable`], suffer from frequent
+//! TLB misses when backed by regular 4 KB pages. With the `huge_pages` feature enabled on Linux,
+//! allocations of at least [`HUGE_PAGE_SIZE`] are aligned to 2 MB and advised to use transparent
+//! huge pages through `madvise(MADV_HUGEPAGE)`. Otherwise, the global allocator is used as normal.
+//!
+//! Whether huge pages were actually obtained depends on the kernel, and can be checked with
+//! [`huge_page_bytes`].
+//!
+//! [`TranspositionTable`]: ../tt/struct.TranspositionTable.html
+//! [`HUGE_PAGE_SIZE`]: constant.HUGE_PAGE_SIZE.html
+//! [`huge_page_bytes`]: fn.huge_page_bytes.html
+
+use std::alloc::{self, Layout, handle_alloc_error};
+use std::ptr::NonNull;
+
+/// Size of a huge page, in bytes.
+pub const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// Returns if allocations are able to request huge pages, e.g. the `huge_pages` feature is
+/// enabled on a supported platform.
+#[inline]
+pub fn supported() -> bool {
+    cfg!(all(feature = "huge_pages", target_os = "linux"))
+}
+
+/// Returns the layout used to allocate `size` bytes aligned to `align`. Allocations large enough
+/// to use huge pages are rounded up to a multiple of, and aligned to, `HUGE_PAGE_SIZE`.
+///
+/// # Panics
+///
+/// `align` must be a power of two.
+pub fn layout(size: usize, align: usize) -> Layout {
+    if supported() && size >= HUGE_PAGE_SIZE {
+        let size = (size + HUGE_PAGE_SIZE - 1) / HUGE_PAGE_SIZE * HUGE_PAGE_SIZE;
+        Layout::from_size_align(size, align.max(HUGE_PAGE_SIZE)).unwrap()
+    } else {
+        Layout::from_size_align(size, align).unwrap()
+    }
+}
+
+/// Allocates `size` zeroed bytes aligned to `align`, requesting huge pages if possible.
+///
+/// # Safety
+///
+/// `size` must be greater than 0. The allocation must be freed with [`dealloc`] using the same
+/// `size` and `align`.
+///
+/// [`dealloc`]: fn.dealloc.html
+pub unsafe fn alloc_zeroed(size: usize, align: usize) -> NonNull<u8> {
+    let layout = layout(size, align);
+    if layout.align() < HUGE_PAGE_SIZE {
+        return match NonNull::new(alloc::alloc_zeroed(layout)) {
+            Some(ptr) => ptr,
+            _ => handle_alloc_error(layout),
+        };
+    }
+
+    // Advise before zeroing, so the pages are faulted in as huge pages.
+    let ptr = alloc(size, align);
+    ptr.as_ptr().write_bytes(0, layout.size());
+    ptr
+}
+
+/// Allocates `size` uninitialized bytes aligned to `align`, requesting huge pages if possible.
+///
+/// # Safety
+///
+/// `size` must be greater than 0. The allocation must be freed with [`dealloc`] using the same
+/// `size` and `align`.
+///
+/// [`dealloc`]: fn.dealloc.html
+pub unsafe fn alloc(size: usize, align: usize) -> NonNull<u8> {
+    match try_alloc(size, align) {
+        Some(ptr) => ptr,
+        None => handle_alloc_error(layout(size, align)),
+    }
+}
+
+/// Allocates `size` uninitialized bytes aligned to `align` like [`alloc`], returning `None`
+/// if the memory can't be allocated.
+///
+/// # Safety
+///
+/// `size` must be greater than 0. The allocation must be freed with [`dealloc`] using the same
+/// `size` and `align`.
+///
+/// [`alloc`]: fn.alloc.html
+/// [`dealloc`]: fn.dealloc.html
+pub unsafe fn try_alloc(size: usize, align: usize) -> Option<NonNull<u8>> {
+    let layout = layout(size, align);
+    let ptr = NonNull::new(alloc::alloc(layout))?;
+    if layout.align() >= HUGE_PAGE_SIZE {
+        sys::advise_huge(ptr.as_ptr(), layout.size());
+    }
+    Some(ptr)
+}
+
+/// De-allocates memory allocated by [`alloc_zeroed`] or [`alloc`].
+///
+/// # Safety
+///
+/// `ptr` must have been returned by `alloc_zeroed` or `alloc` with the same `size` and `align`.
+///
+/// [`alloc_zeroed`]: fn.alloc_zeroed.html
+/// [`alloc`]: fn.alloc.html
+pub unsafe fn dealloc(ptr: NonNull<u8>, size: usize, align: usize) {
+    alloc::dealloc(ptr.as_ptr(), layout(size, align));
+}
+
+/// Returns the number of bytes backed by huge pages in the mapping containing `ptr`. Always
+/// returns 0 on platforms other than Linux.
+#[cfg(target_os = "linux")]
+pub fn huge_page_bytes(ptr: *const u8) -> usize {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    let addr = ptr as usize;
+    let smaps = match File::open("/proc/self/smaps") {
+        Ok(file) => BufReader::new(file),
+        Err(_) => return 0,
+    };
+    let mut in_mapping = false;
+    for line in smaps.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return 0,
+        };
+        let mut fields = line.split_whitespace();
+        let first = fields.next().unwrap_or("");
+        // Each mapping starts with its address range, followed by its fields.
+        if let Some(dash) = first.find('-') {
+            if !first.ends_with(':') {
+                let start = usize::from_str_radix(&first[..dash], 16).unwrap_or(0);
+                let end = usize::from_str_radix(&first[dash + 1..], 16).unwrap_or(0);
+                in_mapping = start <= addr && addr < end;
+                continue;
+            }
+        }
+        if in_mapping && first == "AnonHugePages:" {
+            return fields.next().and_then(|kb| kb.parse::<usize>().ok()).unwrap_or(0) * 1024;
+        }
+    }
+    0
+}
+
+/// Returns the number of bytes backed by huge pages in the mapping containing `ptr`. Always
+/// returns 0 on platforms other than Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn huge_page_bytes(_ptr: *const u8) -> usize {
+    0
+}
+
+#[cfg(all(feature = "huge_pages", target_os = "linux"))]
+mod sys {
+    const MADV_HUGEPAGE: i32 = 14;
+
+    extern "C" {
+        fn madvise(addr: *mut u8, len: usize, advice: i32) -> i32;
+    }
+
+    // Failing to advise is harmless, the memory is simply backed by regular pages.
+    pub unsafe fn advise_huge(ptr: *mut u8, len: usize) {
+        madvise(ptr, len, MADV_HUGEPAGE);
+    }
+}
+
+#[cfg(not(all(feature = "huge_pages", target_os = "linux")))]
+mod sys {
+    pub unsafe fn advise_huge(_ptr: *mut u8, _len: usize) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alloc_zeroed_large() {
+        for &size in [64usize, HUGE_PAGE_SIZE, 3 * HUGE_PAGE_SIZE + 5].iter() {
+            let layout = layout(size, 64);
+            assert!(layout.size() >= size);
+            if supported() && size >= HUGE_PAGE_SIZE {
+                assert_eq!(layout.align(), HUGE_PAGE_SIZE);
+                assert_eq!(layout.size() % HUGE_PAGE_SIZE, 0);
+            }
+            unsafe {
+                let ptr = alloc_zeroed(size, 64);
+                assert_eq!(ptr.as_ptr() as usize % layout.align(), 0);
+                let bytes = ::std::slice::from_raw_parts_mut(ptr.as_ptr(), size);
+                assert!(bytes.iter().all(|b| *b == 0));
+                bytes[size - 1] = 1;
+                dealloc(ptr, size, 64);
+            }
+        }
+    }
+}
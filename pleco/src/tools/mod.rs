@@ -6,6 +6,7 @@
 pub mod prng;
 pub mod eval;
 pub mod tt;
+pub mod huge_pages;
 pub mod pleco_arc;
 
 
@@ -30,16 +30,29 @@
 //! assert!(found);
 //! ```
 //!
+//! The table can also hold any type implementing [`TTEntry`], with a configurable number of entries
+//! per cluster and a [`ReplacementPolicy`] deciding which entry of a full cluster is overwritten.
+//! By default, it holds [`Entry`]s in clusters of three.
+//!
+//! ```ignore
+//! let tt: TranspositionTable<Entry, TwoTier> = TranspositionTable::with_clusters(1024, 4);
+//! ```
+//!
 //! [`TranspositionTable`]: ../../tools/tt/struct.TranspositionTable.html
 //! [`Entry`]: ../../tools/tt/struct.Entry.html
-
-use std::ptr::NonNull;
-use std::mem;
-use std::alloc::{Layout, handle_alloc_error, self};
-use std::cmp::min;
+//! [`TTEntry`]: ../../tools/tt/trait.TTEntry.html
+//! [`ReplacementPolicy`]: ../../tools/tt/trait.ReplacementPolicy.html
+
+use std::ptr::{self, NonNull};
+use std::{fmt,io,mem,slice};
+use std::io::{Read,Write,BufReader,BufWriter};
+use std::fs::File;
+use std::cmp::{self, min};
+use std::marker::PhantomData;
 use std::cell::UnsafeCell;
 
 use super::PreFetchable;
+use super::huge_pages;
 use core::piece_move::BitMove;
 use super::prefetch_write;
 
@@ -57,6 +70,18 @@ pub const NODE_TYPE_MASK: u8 = 0b0000_0011;
 /// Number of Entries per Cluster.
 pub const CLUSTER_SIZE: usize = 3;
 
+/// Magic bytes starting a saved `TranspositionTable`.
+pub const TT_FILE_MAGIC: [u8; 8] = *b"PLECOTT\0";
+
+/// Version of the saved `TranspositionTable` format.
+pub const TT_FILE_VERSION: u32 = 2;
+
+// magic + version + cluster bytes + entries per cluster + cluster count + age + reserved + checksum
+const TT_FILE_HEADER_LEN: usize = 8 + 4 + 4 + 4 + 8 + 1 + 3 + 8;
+
+// Size of a cache line, in bytes.
+const CACHE_LINE: usize = 64;
+
 const BYTES_PER_KB: usize = 1000;
 const BYTES_PER_MB: usize = BYTES_PER_KB * 1000;
 const BYTES_PER_GB: usize = BYTES_PER_MB * 1000;
@@ -109,39 +134,87 @@ impl NodeTypeTimeBound {
 /// Structure defining a singular Entry in a table, containing the `BestMove` found,
 /// the score of that node, the type of Node, depth found, as well as a key uniquely defining
 /// the node.
+///
+/// The upper 16 bits of the key are stored xored with a checksum of the rest of the entry,
+/// so an entry torn by concurrent writes from multiple threads will, with high probability,
+/// no longer match its key. For this reason, the fields are private: they are read through
+/// accessors, and only written to with [`place`].
+///
+/// [`place`]: #method.place
 #[derive(Clone,PartialEq)]
 #[repr(C)]
 pub struct Entry {
-    pub partial_key: u16,
-    pub best_move: BitMove, // What was the best move found here?
-    pub score: i16, // What was the Score of this node?
-    pub eval: i16, // What is the evaluation of this node
-    pub depth: i8, // How deep was this Score Found?
-    pub time_node_bound: NodeTypeTimeBound,
+    key_check: u16, // The partial key, xored with the checksum of the entry
+    best_move: BitMove, // What was the best move found here?
+    score: i16, // What was the Score of this node?
+    eval: i16, // What is the evaluation of this node
+    depth: i8, // How deep was this Score Found?
+    time_node_bound: NodeTypeTimeBound,
 }
 
 impl Entry {
 
+    /// Returns the best move found for the position.
+    #[inline(always)]
+    pub fn best_move(&self) -> BitMove {
+        self.best_move
+    }
+
+    /// Returns the score found for the position.
+    #[inline(always)]
+    pub fn score(&self) -> i16 {
+        self.score
+    }
+
+    /// Returns the static evaluation of the position.
+    #[inline(always)]
+    pub fn eval(&self) -> i16 {
+        self.eval
+    }
+
+    /// Returns the depth the position was searched to.
+    #[inline(always)]
+    pub fn depth(&self) -> i16 {
+        self.depth as i16
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.node_type() == NodeBound::NoBound || self.partial_key == 0
+        self.node_type() == NodeBound::NoBound || self.partial_key() == 0
+    }
+
+    /// Returns the upper 16 bits of the key of the position this entry was placed for.
+    #[inline(always)]
+    pub fn partial_key(&self) -> u16 {
+        self.key_check ^ self.checksum()
+    }
+
+    /// Returns a checksum of the move, score, eval, depth and bound of the entry. The time the
+    /// entry was found is left out, as it is updated without re-writing the entry.
+    #[inline(always)]
+    pub fn checksum(&self) -> u16 {
+        let depth_bound: u16 = (self.depth as u8 as u16) | ((self.time_node_bound.data & NODE_TYPE_MASK) as u16) << 8;
+        let mut check: u16 = self.best_move.get_raw();
+        check = check.rotate_left(5) ^ self.score as u16;
+        check = check.rotate_left(5) ^ self.eval as u16;
+        check.rotate_left(5) ^ depth_bound
     }
 
     /// Rewrites over an Entry.
     pub fn place(&mut self, key: Key, best_move: BitMove, score: i16, eval: i16, depth: i16, node_type: NodeBound, gen: u8) {
         let partial_key = key.wrapping_shr(48) as u16;
+        let same_key = partial_key == self.partial_key();
 
-        if partial_key != self.partial_key {
+        if !same_key {
             self.best_move = best_move;
         }
 
-        if partial_key != self.partial_key
-            || node_type == NodeBound::Exact || depth > self.depth as i16 - 4 {
-            self.partial_key = partial_key;
+        if !same_key || node_type == NodeBound::Exact || depth > self.depth as i16 - 4 {
             self.score = score;
             self.eval = eval;
             self.depth = depth as i8;
             self.time_node_bound.update_bound(node_type, gen);
         }
+        self.key_check = partial_key ^ self.checksum();
     }
 
     /// Returns the current search time of the node.
@@ -167,25 +240,164 @@ impl Entry {
 }
 
 
-// 30 bytes + 2 = 32 Bytes
-/// Structure containing multiple Entries all mapped to by the same zobrist key.
-#[repr(C)]
-pub struct Cluster {
-    pub entry: [Entry; CLUSTER_SIZE],
-    pub padding: [u8; 2],
+/// An entry able to be stored inside a [`TranspositionTable`].
+///
+/// # Safety
+///
+/// The table is allocated zeroed and stored as raw bytes, so an entry made up entirely of zero
+/// bytes must be a valid, vacant entry. Implementors should be plain data, containing no pointers
+/// or types with a destructor.
+///
+/// [`TranspositionTable`]: struct.TranspositionTable.html
+pub unsafe trait TTEntry: Sized {
+    /// Returns if this entry holds the position of `key`. Entries able to detect being torn by
+    /// concurrent writes should only match if the entry is consistent.
+    fn matches(&self, key: Key) -> bool;
+
+    /// Returns if this entry has yet to be written to.
+    fn is_vacant(&self) -> bool;
+
+    /// Returns the depth this entry was searched to.
+    fn depth(&self) -> i16;
+
+    /// Returns the time age of the table when this entry was last written to or found.
+    fn generation(&self) -> u8;
+
+    /// Sets the time age of this entry. Time ages are always divisible by 4.
+    fn set_generation(&mut self, gen: u8);
+}
+
+unsafe impl TTEntry for Entry {
+    #[inline(always)]
+    fn matches(&self, key: Key) -> bool {
+        self.partial_key() == key.wrapping_shr(48) as u16
+    }
+
+    #[inline(always)]
+    fn is_vacant(&self) -> bool {
+        self.partial_key() == 0
+    }
+
+    #[inline(always)]
+    fn depth(&self) -> i16 {
+        self.depth as i16
+    }
+
+    #[inline(always)]
+    fn generation(&self) -> u8 {
+        self.time()
+    }
+
+    #[inline(always)]
+    fn set_generation(&mut self, gen: u8) {
+        self.time_node_bound.update_time(gen);
+    }
+}
+
+/// Decides which entry of a full cluster is to be replaced by a new position.
+pub trait ReplacementPolicy {
+    /// Returns the index of the entry in `cluster` to replace. `generation` is the current
+    /// time age of the table. Every entry of the cluster is occupied by another position.
+    fn replace<E: TTEntry>(cluster: &[E], generation: u8) -> usize;
+}
+
+/// Always replaces the first entry of a cluster.
+pub struct AlwaysReplace;
+
+/// Replaces the entry searched to the shallowest depth.
+pub struct DepthPreferred;
+
+/// Keeps the first entry of a cluster for as long as it belongs to the current search, and
+/// otherwise replaces the shallowest of the remaining entries.
+pub struct TwoTier;
+
+/// Replaces the entry with the lowest depth, with entries from previous searches counting
+/// as shallower. This is the default policy.
+pub struct DepthAge;
+
+impl ReplacementPolicy for AlwaysReplace {
+    #[inline(always)]
+    fn replace<E: TTEntry>(_cluster: &[E], _generation: u8) -> usize {
+        0
+    }
+}
+
+impl ReplacementPolicy for DepthPreferred {
+    #[inline]
+    fn replace<E: TTEntry>(cluster: &[E], _generation: u8) -> usize {
+        shallowest(cluster, 0)
+    }
+}
+
+impl ReplacementPolicy for TwoTier {
+    #[inline]
+    fn replace<E: TTEntry>(cluster: &[E], generation: u8) -> usize {
+        if cluster.len() == 1 || cluster[0].generation() != generation {
+            0
+        } else {
+            shallowest(cluster, 1)
+        }
+    }
+}
+
+impl ReplacementPolicy for DepthAge {
+    #[inline]
+    fn replace<E: TTEntry>(cluster: &[E], generation: u8) -> usize {
+        let value = |e: &E| {
+            let age: i16 = (259i16 + generation as i16 - e.generation() as i16) & 0b1111_1100;
+            e.depth().wrapping_sub(age).wrapping_mul(2)
+        };
+        let mut replacement: usize = 0;
+        let mut replacement_score: i16 = value(&cluster[0]);
+        for (i, entry) in cluster.iter().enumerate().skip(1) {
+            let entry_score = value(entry);
+            if entry_score < replacement_score {
+                replacement = i;
+                replacement_score = entry_score;
+            }
+        }
+        replacement
+    }
+}
+
+// Returns the index of the shallowest entry, starting from 'start'.
+#[inline]
+fn shallowest<E: TTEntry>(cluster: &[E], start: usize) -> usize {
+    let mut replacement: usize = start;
+    for i in (start + 1)..cluster.len() {
+        if cluster[i].depth() < cluster[replacement].depth() {
+            replacement = i;
+        }
+    }
+    replacement
 }
 
 // clusters -> Pointer to the clusters
-// cap -> n number of clusters (So n * CLUSTER_SIZE) number of entries
+// cap -> n number of clusters (So n * cluster_size) number of entries
 // time age -> documenting when an entry was placed
+// cluster_size -> number of entries in a cluster
+// stride -> bytes between the start of two clusters, padded so clusters do not straddle cache lines
 
 /// Structure for representing a `TranspositionTable`. A Transposition Table is a type
 /// of HashTable that maps Zobrist Keys to information about that position, including the best move
 /// found, score, depth the move was found at, and other information.
-pub struct TranspositionTable {
-    clusters: UnsafeCell<NonNull<Cluster>>, // pointer to the heap
-    cap: UnsafeCell<usize>, // number of clusters, so (So n * CLUSTER_SIZE) number of entries
+///
+/// The table is generic over the type of entry stored, `E`, and the [`ReplacementPolicy`] used
+/// when a cluster is full, `R`. Each key maps to a cluster of entries, the number of which is set
+/// when creating the table. By default, a table holds [`Entry`]s in clusters of [`CLUSTER_SIZE`],
+/// replaced by [`DepthAge`].
+///
+/// [`ReplacementPolicy`]: trait.ReplacementPolicy.html
+/// [`Entry`]: struct.Entry.html
+/// [`CLUSTER_SIZE`]: constant.CLUSTER_SIZE.html
+/// [`DepthAge`]: struct.DepthAge.html
+pub struct TranspositionTable<E: TTEntry = Entry, R: ReplacementPolicy = DepthAge> {
+    clusters: UnsafeCell<NonNull<u8>>, // pointer to the heap
+    cap: UnsafeCell<usize>, // number of clusters, so (So n * cluster_size) number of entries
     time_age: UnsafeCell<u8>, // documenting at which root position an entry was placed
+    cluster_size: usize,
+    stride: usize,
+    _marker: PhantomData<(E, R)>,
 }
 
 impl TranspositionTable {
@@ -198,10 +410,7 @@ impl TranspositionTable {
     ///
     /// mb_size should be > 0, or else a panic will occur
     pub fn new(mb_size: usize) -> Self {
-        assert!(mb_size > 0);
-        let mut num_clusters: usize = (mb_size * BYTES_PER_MB) / mem::size_of::<Cluster>();
-        num_clusters = num_clusters.next_power_of_two() / 2;
-        TranspositionTable::new_num_clusters(num_clusters)
+        TranspositionTable::with_size(mb_size, CLUSTER_SIZE)
     }
 
     /// Creates new TT rounded up to the nearest power of two number of entries.
@@ -219,42 +428,70 @@ impl TranspositionTable {
     ///
     /// Size should be > 0, or else a panic will occur
     pub fn new_num_clusters(num_clusters: usize) -> Self {
-        TranspositionTable::create(num_clusters.next_power_of_two())
+        TranspositionTable::with_clusters(num_clusters, CLUSTER_SIZE)
+    }
+}
+
+impl<E: TTEntry, R: ReplacementPolicy> TranspositionTable<E, R> {
+    /// Creates new with a size of around 'mb_size', with `cluster_size` entries per cluster.
+    /// Actual size is the nearest power of 2 times the size of a Cluster rounded down.
+    ///
+    /// # Panics
+    ///
+    /// mb_size and cluster_size should be > 0, or else a panic will occur
+    pub fn with_size(mb_size: usize, cluster_size: usize) -> Self {
+        assert!(mb_size > 0);
+        let num_clusters = clusters_in_megabytes(mb_size, cluster_stride::<E>(cluster_size));
+        TranspositionTable::create(num_clusters, cluster_size)
+    }
+
+    /// Creates new TT rounded up to the nearest power of two number of Clusters, with
+    /// `cluster_size` entries per cluster.
+    ///
+    /// # Panics
+    ///
+    /// num_clusters and cluster_size should be > 0, or else a panic will occur
+    pub fn with_clusters(num_clusters: usize, cluster_size: usize) -> Self {
+        TranspositionTable::create(num_clusters.next_power_of_two(), cluster_size)
     }
 
     // Creates new TT with the number of Clusters being size. size must be a power of two.
-    fn create(size: usize) -> Self {
+    fn create(size: usize, cluster_size: usize) -> Self {
         assert_eq!(size.count_ones(), 1);
         assert!(size > 0);
+        assert!(cluster_size > 0);
+        let stride = cluster_stride::<E>(cluster_size);
         TranspositionTable {
-            clusters: UnsafeCell::new(alloc_room(size)),
+            clusters: UnsafeCell::new(alloc_room(size, stride, cluster_align::<E>(stride))),
             cap: UnsafeCell::new(size),
             time_age: UnsafeCell::new(0),
+            cluster_size,
+            stride,
+            _marker: PhantomData,
         }
     }
 
     pub unsafe fn uninitialized_init(&self, mb_size: usize) {
-        let mut num_clusters: usize = (mb_size * BYTES_PER_MB) / mem::size_of::<Cluster>();
-        num_clusters = num_clusters.next_power_of_two() / 2;
+        let num_clusters = clusters_in_megabytes(mb_size, self.stride);
         self.re_alloc(num_clusters);
     }
 
     /// Returns the size of the heap allocated portion of the TT in KiloBytes.
     #[inline(always)]
     pub fn size_kilobytes(&self) -> usize {
-        (mem::size_of::<Cluster>() * self.num_clusters()) / BYTES_PER_KB
+        (self.stride * self.num_clusters()) / BYTES_PER_KB
     }
 
     /// Returns the size of the heap allocated portion of the TT in MegaBytes.
     #[inline(always)]
     pub fn size_megabytes(&self) -> usize {
-        (mem::size_of::<Cluster>() * self.num_clusters()) / BYTES_PER_MB
+        (self.stride * self.num_clusters()) / BYTES_PER_MB
     }
 
     /// Returns the size of the heap allocated portion of the TT in GigaBytes.
     #[inline(always)]
     pub fn size_gigabytes(&self) -> usize {
-        (mem::size_of::<Cluster>() * self.num_clusters()) / BYTES_PER_GB
+        (self.stride * self.num_clusters()) / BYTES_PER_GB
 
     }
 
@@ -269,7 +506,19 @@ impl TranspositionTable {
     /// Returns the number of Entries the Transposition Table holds.
     #[inline(always)]
     pub fn num_entries(&self) -> usize {
-        self.num_clusters() * CLUSTER_SIZE
+        self.num_clusters() * self.cluster_size
+    }
+
+    /// Returns the number of Entries in each cluster.
+    #[inline(always)]
+    pub fn cluster_size(&self) -> usize {
+        self.cluster_size
+    }
+
+    /// Returns the size of a cluster in bytes, including padding.
+    #[inline(always)]
+    pub fn cluster_bytes_len(&self) -> usize {
+        self.stride
     }
 
     /// Re-sizes to 'size' number of Clusters and deletes all data
@@ -301,19 +550,18 @@ impl TranspositionTable {
     /// absolutely lead to Segmentation Fault.
     pub unsafe fn resize_to_megabytes(&self, mb_size: usize) -> usize {
         assert!(mb_size > 0);
-        let mut num_clusters: usize = (mb_size * BYTES_PER_MB) / mem::size_of::<Cluster>();
-        num_clusters = num_clusters.next_power_of_two() / 2;
+        let num_clusters = clusters_in_megabytes(mb_size, self.stride);
         self.resize(num_clusters);
         self.size_megabytes()
     }
 
     // resizes the tt to a certain type
-    // TODO: Modify self.cap
     unsafe fn resize(&self, size: usize) {
         assert_eq!(size.count_ones(), 1);
         assert!(size > 0);
         self.de_alloc();
         self.re_alloc(size);
+        *self.cap.get() = size;
     }
 
     /// Clears the entire TranspositionTable
@@ -328,6 +576,60 @@ impl TranspositionTable {
         self.resize(*size);
     }
 
+    /// Returns the size in megabytes the table would have if resized to `mb_size` megabytes, as
+    /// the number of clusters is rounded down to a power of two.
+    pub fn resized_megabytes(&self, mb_size: usize) -> usize {
+        (clusters_in_megabytes(mb_size, self.stride) * self.stride) / BYTES_PER_MB
+    }
+
+    /// Re-sizes to the the mb_size number of megabytes like [`resize_to_megabytes`], but leaves
+    /// the new table uninitialized. Returns the actual size.
+    ///
+    /// This allows for clearing the table in parallel with [`clear_part`]. If the memory can't
+    /// be allocated, the table is halved in size until it can be, rather than aborting.
+    ///
+    /// # Safety
+    ///
+    /// On top of the requirements of `resize_to_megabytes`, every part of the table must be
+    /// cleared with `clear_part` before the table is accessed.
+    ///
+    /// [`resize_to_megabytes`]: #method.resize_to_megabytes
+    /// [`clear_part`]: #method.clear_part
+    pub unsafe fn resize_to_megabytes_uncleared(&self, mb_size: usize) -> usize {
+        assert!(mb_size > 0);
+        let mut num_clusters = clusters_in_megabytes(mb_size, self.stride);
+        let align = cluster_align::<E>(self.stride);
+        self.de_alloc();
+        *self.clusters.get() = loop {
+            match huge_pages::try_alloc(num_clusters * self.stride, align) {
+                Some(ptr) => break ptr,
+                None if num_clusters > 1 => num_clusters /= 2,
+                None => break huge_pages::alloc(num_clusters * self.stride, align),
+            }
+        };
+        *self.cap.get() = num_clusters;
+        self.size_megabytes()
+    }
+
+    /// Clears a single part of the TranspositionTable, the table being split into `parts`
+    /// parts of roughly equal size. Different parts may be cleared by different threads at
+    /// once.
+    ///
+    /// # Panics
+    ///
+    /// `part` must be less than `parts`.
+    ///
+    /// # Safety
+    ///
+    /// This is function is unsafe to use if the TT is currently being accessed, Or any thread of
+    /// structure contains a current reference to a `TTEntry`.
+    pub unsafe fn clear_part(&self, part: usize, parts: usize) {
+        assert!(part < parts);
+        let start = self.num_clusters() * part / parts;
+        let end = self.num_clusters() * (part + 1) / parts;
+        ptr::write_bytes(self.cluster_ptr(start), 0, (end - start) * self.stride);
+    }
+
     // Called each time a new position is searched.
     #[inline]
     pub fn new_search(&self) {
@@ -357,145 +659,325 @@ impl TranspositionTable {
     /// Probes the Transposition Table for a specified Key. Returns (true, entry) if either (1) an
     /// Entry corresponding to the current key is found, or an Open Entry slot is found for the key.
     /// In the case of an open Entry, the entry can be tested for its contents by using `Entry::is_empty()`.
-    /// If no entry is found && there are no open entries, returns the entry that is is most irrelevent to
-    /// the current search, e.g. has the shallowest depth or was found in a previous search.
+    /// If no entry is found && there are no open entries, returns the entry chosen by the
+    /// table's [`ReplacementPolicy`].
     ///
-    /// If 'true' is returned, the Entry is guaranteed to have the correct time.
-    pub fn probe(&self, key: Key) -> (bool, &mut Entry) {
-        let partial_key: u16 = (key).wrapping_shr(48) as u16;
-
+    /// If 'true' is returned, the Entry is guaranteed to have the correct time. An Entry found torn
+    /// by concurrent writes doesn't match the key, so is never returned as found. However, the Entry
+    /// may still be written to by another thread after being returned, use [`read`] for a copy of the
+    /// Entry guaranteed to be consistent.
+    ///
+    /// [`ReplacementPolicy`]: trait.ReplacementPolicy.html
+    /// [`read`]: #method.read
+    pub fn probe(&self, key: Key) -> (bool, &mut E) {
         unsafe {
-            let cluster: *mut Cluster = self.cluster(key);
-            let init_entry: *mut Entry = cluster_first_entry(cluster);
+            let cluster: &mut [E] = &mut *self.cluster(key);
 
             // for each entry
-            for i in 0..CLUSTER_SIZE {
-                // get a pointer to the specified entry
-                let entry_ptr: *mut Entry = init_entry.offset(i as isize);
-                // convert to &mut
-                let entry: &mut Entry = &mut (*entry_ptr);
-
+            for i in 0..cluster.len() {
                 // found a spot
-                if entry.partial_key == 0 || entry.partial_key == partial_key {
+                if cluster[i].is_vacant() || cluster[i].matches(key) {
+                    let entry: &mut E = &mut *(cluster.get_unchecked_mut(i) as *mut E);
+                    let vacant = entry.is_vacant();
 
                     // if age is incorrect, make it correct
-                    if entry.time() != self.time_age() && entry.partial_key != 0 {
-                        entry.time_node_bound.update_time(self.time_age());
+                    if !vacant && entry.generation() != self.time_age() {
+                        entry.set_generation(self.time_age());
                     }
 
                     // Return the spot
-                    return (entry.partial_key != 0, entry);
+                    return (!vacant, entry);
                 }
             }
 
-            let mut replacement: *mut Entry = init_entry;
-            let mut replacement_score: i16 = (&*replacement).time_value(self.time_age());
+            // Table is full, find the best replacement
+            let replacement = R::replace(cluster, self.time_age());
+            (false, &mut *(cluster.get_unchecked_mut(replacement) as *mut E))
+        }
+    }
 
-            // Table is full, find the best replacement based on depth and time placed there
-            for i in 1..CLUSTER_SIZE {
-                let entry_ptr: *mut Entry = init_entry.offset(i as isize);
-                let entry_score: i16 = (&*entry_ptr).time_value(self.time_age());
-                if entry_score < replacement_score {
-                    replacement = entry_ptr;
-                    replacement_score = entry_score;
+    /// Returns a copy of the Entry for a specified Key, if there is one. The Entry is checked
+    /// after being copied, so a copy torn by another thread writing to the Entry is never returned.
+    pub fn read(&self, key: Key) -> Option<E> {
+        unsafe {
+            let cluster: &[E] = &*self.cluster(key);
+            for entry in cluster.iter() {
+                let copy: E = ptr::read_volatile(entry as *const E);
+                if !copy.is_vacant() && copy.matches(key) {
+                    return Some(copy);
                 }
             }
-            // return the best place to replace
-            (false, &mut (*replacement))
+            None
         }
     }
 
-    /// Returns the cluster of a given key.
+    /// Returns a pointer to the cluster of a given key.
     #[inline]
-    fn cluster(&self, key: Key) -> *mut Cluster {
+    fn cluster(&self, key: Key) -> *mut [E] {
         let index: usize = ((self.num_clusters() - 1) as u64 & key) as usize;
-        unsafe {
-            (*self.clusters.get()).as_ptr().offset(index as isize)
-        }
+        unsafe { ptr::slice_from_raw_parts_mut(self.cluster_ptr(index) as *mut E, self.cluster_size) }
+    }
+
+    // Returns a pointer to the start of the cluster at 'index'.
+    #[inline(always)]
+    unsafe fn cluster_ptr(&self, index: usize) -> *mut u8 {
+        (*self.clusters.get()).as_ptr().add(index * self.stride)
     }
 
     // Re-Allocates the current TT to a specified size.
     unsafe fn re_alloc(&self, size: usize) {
         let c = self.clusters.get();
-        *c = alloc_room(size);
+        *c = alloc_room(size, self.stride, cluster_align::<E>(self.stride));
     }
 
     /// De-allocates the current heap.
     unsafe fn de_alloc(&self) {
-        let layout = Layout::from_size_align(*self.cap.get(), 2).unwrap();
-        let ptr: *mut u8 = mem::transmute(*self.clusters.get());
-//        alloc::dealloc(ptr, Layout::array::<Cluster>(*self.cap.get()).unwrap());
-        alloc::dealloc(ptr, layout);
+        huge_pages::dealloc(*self.clusters.get(), *self.cap.get() * self.stride, cluster_align::<E>(self.stride));
+    }
+
+    /// Returns the number of bytes of the table backed by huge pages. Huge pages are only
+    /// requested with the `huge_pages` feature, see [`huge_pages`].
+    ///
+    /// [`huge_pages`]: ../huge_pages/index.html
+    pub fn huge_page_bytes(&self) -> usize {
+        unsafe { huge_pages::huge_page_bytes((*self.clusters.get()).as_ptr()) }
+    }
+
+    // Returns the number of entries from the current search in 'clusters_scanned'
+    // clusters, starting from 'first'.
+    fn current_entries(&self, first: u64, clusters_scanned: u64) -> u64 {
+        let mut hits: u64 = 0;
+        for i in first..(first + clusters_scanned) {
+            let cluster: &[E] = unsafe { &*self.cluster(i) };
+            hits += cluster.iter().filter(|e| !e.is_vacant() && e.generation() == self.time_age()).count() as u64;
+        }
+        hits
     }
 
     /// Returns the % of the hash table that is full.
     pub fn hash_percent(&self) -> f64 {
+        let clusters_scanned: u64 = min((self.num_clusters() - 1) as u64, 333);
+        let hits = self.current_entries(1, clusters_scanned) as f64;
+        (hits * 100.0) / (clusters_scanned * self.cluster_size as u64) as f64
+    }
+
+    /// Returns an approximation of how full the table is, in permill, as expected by
+    /// the UCI `hashfull` info field. Only entries from the current generation are counted.
+    pub fn hashfull(&self) -> u16 {
+        let clusters_scanned: u64 = min(self.num_clusters() as u64, 1000);
+        let hits = self.current_entries(0, clusters_scanned);
+        ((hits * 1000) / (clusters_scanned * self.cluster_size as u64)) as u16
+    }
+
+    /// Saves the table to a file at `path`, to be loaded again with [`load`].
+    ///
+    /// The file starts with a header of the magic bytes `TT_FILE_MAGIC`, the format version,
+    /// the size of a cluster in bytes, the number of entries in a cluster, the number of
+    /// clusters, the time age and a checksum of the clusters. All are little-endian. The clusters
+    /// follow as they are laid out in memory, so a saved table can only be loaded on a machine
+    /// of the same endianness.
+    ///
+    /// # Safety
+    ///
+    /// The table should not be written to while being saved, or else the saved table may fail
+    /// its checksum when loaded.
+    ///
+    /// [`load`]: #method.load
+    pub fn save(&self, path: &str) -> Result<(), TTFileError> {
+        let bytes = self.cluster_bytes();
+        let mut header = [0u8; TT_FILE_HEADER_LEN];
+        header[0..8].copy_from_slice(&TT_FILE_MAGIC);
+        header[8..12].copy_from_slice(&TT_FILE_VERSION.to_le_bytes());
+        header[12..16].copy_from_slice(&(self.stride as u32).to_le_bytes());
+        header[16..20].copy_from_slice(&(self.cluster_size as u32).to_le_bytes());
+        header[20..28].copy_from_slice(&(self.num_clusters() as u64).to_le_bytes());
+        header[28] = self.time_age();
+        header[32..40].copy_from_slice(&checksum(bytes).to_le_bytes());
+
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(&header)?;
+        file.write_all(bytes)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Loads a table saved with [`save`] from the file at `path`, replacing the contents of
+    /// this table.
+    ///
+    /// Files saved with a different format version, cluster layout or number of clusters are
+    /// refused, so the table must be resized to the saved size before loading. The type
+    /// of entry isn't recorded, so a table must be loaded with the entry type it was saved with.
+    /// If the clusters fail the checksum, the table is cleared.
+    ///
+    /// # Safety
+    ///
+    /// This is function is unsafe to use if the TT is currently being accessed, Or any thread of
+    /// structure contains a current reference to a `TTEntry`. Otherwise, using this function will
+    /// absolutely lead to a Segmentation Fault.
+    ///
+    /// [`save`]: #method.save
+    pub unsafe fn load(&self, path: &str) -> Result<(), TTFileError> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut header = [0u8; TT_FILE_HEADER_LEN];
+        file.read_exact(&mut header)?;
+
+        let u32_at = |i: usize| {
+            let mut b = [0u8; 4];
+            b.copy_from_slice(&header[i..i + 4]);
+            u32::from_le_bytes(b)
+        };
+        let u64_at = |i: usize| {
+            let mut b = [0u8; 8];
+            b.copy_from_slice(&header[i..i + 8]);
+            u64::from_le_bytes(b)
+        };
+
+        if header[0..8] != TT_FILE_MAGIC {
+            return Err(TTFileError::BadMagic);
+        }
+        let version = u32_at(8);
+        if version != TT_FILE_VERSION {
+            return Err(TTFileError::UnsupportedVersion {version});
+        }
+        let cluster_bytes = u32_at(12);
+        let cluster_size = u32_at(16);
+        if cluster_bytes as usize != self.stride || cluster_size as usize != self.cluster_size {
+            return Err(TTFileError::LayoutMismatch {
+                cluster_bytes, cluster_size,
+                expected_bytes: self.stride as u32,
+                expected_size: self.cluster_size as u32,
+            });
+        }
+        let num_clusters = u64_at(20);
+        if num_clusters != self.num_clusters() as u64 {
+            return Err(TTFileError::SizeMismatch {
+                num_clusters,
+                expected: self.num_clusters() as u64,
+            });
+        }
+
+        let bytes = slice::from_raw_parts_mut((*self.clusters.get()).as_ptr(),
+                                              self.num_clusters() * self.stride);
+        if let Err(e) = file.read_exact(bytes) {
+            self.clear();
+            return Err(TTFileError::Io(e));
+        }
+        if checksum(bytes) != u64_at(32) {
+            self.clear();
+            return Err(TTFileError::BadChecksum);
+        }
+        *self.time_age.get() = header[28];
+        Ok(())
+    }
+
+    // Returns the clusters as raw bytes.
+    fn cluster_bytes(&self) -> &[u8] {
         unsafe {
-            let clusters_scanned: u64 = min((*self.cap.get() - 1) as u64, 333);
-            let mut hits: f64 = 0.0;
-
-            for i in 0..clusters_scanned {
-                let cluster = self.cluster(i + 1);
-                let init_entry: *mut Entry = cluster_first_entry(cluster);
-                for e in 0..CLUSTER_SIZE {
-                    // get a pointer to the specified entry
-                    let entry_ptr: *mut Entry = init_entry.offset(e as isize);
-                    let entry: &Entry = & (*entry_ptr);
-                    if entry.time() == self.time_age() {
-                        hits += 1.0;
-                    }
-                }
-            }
-            (hits * 100.0) / (clusters_scanned * CLUSTER_SIZE as u64) as f64
+            slice::from_raw_parts((*self.clusters.get()).as_ptr() as *const u8,
+                                  self.num_clusters() * self.stride)
         }
     }
 }
 
-unsafe impl Sync for TranspositionTable {}
+/// Represents possible Errors encountered while loading a saved `TranspositionTable`.
+pub enum TTFileError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion {version: u32},
+    LayoutMismatch {cluster_bytes: u32, cluster_size: u32, expected_bytes: u32, expected_size: u32},
+    SizeMismatch {num_clusters: u64, expected: u64},
+    BadChecksum,
+}
+
+impl fmt::Debug for TTFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TTFileError::Io(ref e) => write!(f, "{}", e),
+            TTFileError::BadMagic => write!(f, "not a transposition table file"),
+            TTFileError::UnsupportedVersion{version} =>
+                write!(f, "unsupported transposition table version: {}, expected {}", version, TT_FILE_VERSION),
+            TTFileError::LayoutMismatch{cluster_bytes, cluster_size, expected_bytes, expected_size} =>
+                write!(f, "mismatched cluster layout: {} entries in {} bytes, expected {} entries in {} bytes",
+                       cluster_size, cluster_bytes, expected_size, expected_bytes),
+            TTFileError::SizeMismatch{num_clusters, expected} =>
+                write!(f, "mismatched size: {} clusters, expected {}", num_clusters, expected),
+            TTFileError::BadChecksum => write!(f, "checksum mismatch, the file is corrupted"),
+        }
+    }
+}
 
-impl PreFetchable for TranspositionTable {
+impl From<io::Error> for TTFileError {
+    fn from(e: io::Error) -> Self {
+        TTFileError::Io(e)
+    }
+}
+
+// FNV-1a over 8 byte words. The clusters are always a multiple of 8 bytes.
+fn checksum(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for word in bytes.chunks(8) {
+        let mut b = [0u8; 8];
+        b[..word.len()].copy_from_slice(word);
+        hash = (hash ^ u64::from_le_bytes(b)).wrapping_mul(0x0000_0100_0000_01B3);
+    }
+    hash
+}
+
+unsafe impl<E: TTEntry, R: ReplacementPolicy> Sync for TranspositionTable<E, R> {}
+unsafe impl<E: TTEntry, R: ReplacementPolicy> Send for TranspositionTable<E, R> {}
+
+impl<E: TTEntry, R: ReplacementPolicy> PreFetchable for TranspositionTable<E, R> {
     /// Pre-fetches a particular key. This means bringing it into the cache for faster eventual
     /// access.
     #[inline(always)]
     fn prefetch(&self, key: u64) {
         let index: usize = ((self.num_clusters() - 1) as u64 & key) as usize;
         unsafe {
-            let ptr = (*self.clusters.get()).as_ptr().offset(index as isize);
-            prefetch_write(ptr);
+            prefetch_write(self.cluster_ptr(index));
         };
     }
 }
 
-impl Drop for TranspositionTable {
+impl<E: TTEntry, R: ReplacementPolicy> Drop for TranspositionTable<E, R> {
     fn drop(&mut self) {
         unsafe {self.de_alloc();}
     }
 }
 
-/// Returns the first entry of a cluster.
+// Returns the number of bytes taken by a cluster of 'cluster_size' entries. Clusters up to
+// a cache line are padded to a power of two, larger ones to a multiple of the cache line.
 #[inline]
-unsafe fn cluster_first_entry(cluster: *mut Cluster) -> *mut Entry {
-    (*cluster).entry.get_unchecked_mut(0) as *mut Entry
+fn cluster_stride<E: TTEntry>(cluster_size: usize) -> usize {
+    let bytes = cluster_size * mem::size_of::<E>();
+    assert!(bytes > 0);
+    if bytes <= CACHE_LINE {
+        bytes.next_power_of_two()
+    } else {
+        (bytes + CACHE_LINE - 1) / CACHE_LINE * CACHE_LINE
+    }
+}
+
+// Returns the alignment of the clusters.
+#[inline]
+fn cluster_align<E: TTEntry>(stride: usize) -> usize {
+    cmp::max(min(stride, CACHE_LINE), mem::align_of::<E>())
+}
+
+// Returns the power of two number of clusters fitting in 'mb_size' megabytes, rounded down.
+#[inline]
+fn clusters_in_megabytes(mb_size: usize, stride: usize) -> usize {
+    let num_clusters: usize = (mb_size * BYTES_PER_MB) / stride;
+    cmp::max(num_clusters.next_power_of_two() / 2, 1)
 }
 
 // Return a Heap Allocation of Size number of Clusters.
 #[inline]
-fn alloc_room(size: usize) -> NonNull<Cluster> {
+fn alloc_room(size: usize, stride: usize, align: usize) -> NonNull<u8> {
     unsafe {
-        let size = size * mem::size_of::<Cluster>();
-        let layout = Layout::from_size_align(size, 2).unwrap();
-        //let layout = Layout::array::<Cluster>(size).unwrap();
-        let ptr: *mut u8 = alloc::alloc_zeroed(layout);
-        let new_ptr: NonNull<Cluster> = match NonNull::new(ptr) {
-            Some(ptr) => ptr.cast(),
-            _ => handle_alloc_error(layout),
-        };
-        new_ptr
+        huge_pages::alloc_zeroed(size * stride, align)
     }
-
 }
 
-
 #[cfg(test)]
 mod tests {
 
@@ -505,8 +987,10 @@ mod tests {
     use std::thread::sleep;
     use std::time::Duration;
 
-    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize,Ordering};
     use std::sync::atomic::compiler_fence;
+    use std::thread;
 
     // around 0.5 GB
     const HALF_GIG: usize = 2 << 24;
@@ -526,7 +1010,7 @@ mod tests {
     #[test]
     fn tt_alloc_realloc() {
         let size: usize = 8;
-        let tt = TranspositionTable::create(size);
+        let tt = TranspositionTable::<Entry>::create(size, CLUSTER_SIZE);
         assert_eq!(tt.num_clusters(), size);
 
         let key = create_key(32, 44);
@@ -553,8 +1037,7 @@ mod tests {
             let key: u64 = rand::random::<u64>();
             {
                 let (_found, entry) = tt.probe(key);
-                entry.depth = (x % 0b1111_1111) as i8;
-                entry.partial_key = key.wrapping_shr(48) as u16;
+                entry.place(key, BitMove::null(), 0, 0, (x % 0b111_1111) as i16, NodeBound::NoBound, tt.time_age());
             }
             tt.new_search();
         }
@@ -571,13 +1054,12 @@ mod tests {
         let key_1 = create_key(partial_key_1, 0x5556);
         let (found, entry) = tt.probe(key_1);
         assert!(!found);
-        entry.partial_key = partial_key_1;
-        entry.depth = 2;
+        entry.place(key_1, BitMove::null(), 0, 0, 2, NodeBound::NoBound, tt.time_age());
 
         let (found, entry) = tt.probe(key_1);
         assert!(found);
         assert!(entry.is_empty());
-        assert_eq!(entry.partial_key,partial_key_1);
+        assert_eq!(entry.partial_key(),partial_key_1);
         assert_eq!(entry.depth,2);
 
         let partial_key_2: u16 = 8091;
@@ -588,14 +1070,12 @@ mod tests {
         let (found, entry) = tt.probe(key_2);
         assert!(!found);
         assert!(entry.is_empty());
-        entry.partial_key = partial_key_2;
-        entry.depth = 3;
+        entry.place(key_2, BitMove::null(), 0, 0, 3, NodeBound::NoBound, tt.time_age());
 
         let (found, entry) = tt.probe(key_3);
         assert!(!found);
         assert!(entry.is_empty());
-        entry.partial_key = partial_key_3;
-        entry.depth = 6;
+        entry.place(key_3, BitMove::null(), 0, 0, 6, NodeBound::NoBound, tt.time_age());
 
         // key that should find a good replacement
         let partial_key_4: u16 = 18;
@@ -605,13 +1085,290 @@ mod tests {
         assert!(!found);
 
         // most vulnerable should be key_1
-        assert_eq!(entry.partial_key, partial_key_1);
+        assert_eq!(entry.partial_key(), partial_key_1);
         assert_eq!(entry.depth, 2);
 
         compiler_fence(Ordering::Release);
         sleep(Duration::from_millis(1));
     }
 
+    #[test]
+    fn tt_hashfull_permill() {
+        let tt = TranspositionTable::new_num_clusters(1024);
+        assert_eq!(tt.hashfull(), 0);
+
+        // fill every entry of the first 500 clusters with the current generation
+        for i in 0..500u64 {
+            for p in 1..(CLUSTER_SIZE as u16 + 1) {
+                let key = create_key(p, i);
+                let (_found, entry) = tt.probe(key);
+                entry.place(key, BitMove::null(), 0, 0, 1, NodeBound::Exact, tt.time_age());
+            }
+        }
+        assert_eq!(tt.hashfull(), 500);
+
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+    }
+
+    #[test]
+    fn tt_save_load() {
+        let path = ::std::env::temp_dir().join(format!("pleco_tt_save_load_{}.bin", ::std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let tt = TranspositionTable::new_num_clusters(1024);
+        tt.new_search();
+        tt.new_search();
+        let keys: Vec<u64> = (0..500u64).map(|i| create_key(i as u16 + 1, i * 7)).collect();
+        for (i, key) in keys.iter().enumerate() {
+            let (_found, entry) = tt.probe(*key);
+            entry.place(*key, BitMove::new(i as u16 + 100), i as i16, -(i as i16), 5, NodeBound::Exact, tt.time_age());
+        }
+        tt.save(path).unwrap();
+
+        // A table of a different size is left as is.
+        let loaded = TranspositionTable::new_num_clusters(16);
+        unsafe {
+            match loaded.load(path) {
+                Err(TTFileError::SizeMismatch {num_clusters: 1024, expected: 16}) => {},
+                r => panic!("expected a size error, got {:?}", r.err()),
+            }
+        }
+        assert_eq!(loaded.num_clusters(), 16);
+
+        let loaded = TranspositionTable::new_num_clusters(1024);
+        unsafe { loaded.load(path).unwrap(); }
+        assert_eq!(loaded.time_age(), tt.time_age());
+        for (i, key) in keys.iter().enumerate() {
+            let (found, entry) = loaded.probe(*key);
+            assert!(found);
+            assert_eq!(entry.best_move, BitMove::new(i as u16 + 100));
+            assert_eq!(entry.score, i as i16);
+            assert_eq!(entry.eval, -(i as i16));
+            assert_eq!(entry.depth, 5);
+        }
+
+        // A corrupted file is refused, and leaves the table empty.
+        let mut bytes = ::std::fs::read(path).unwrap();
+        let last = bytes.len() - 20;
+        bytes[last] ^= 0xFF;
+        ::std::fs::write(path, &bytes).unwrap();
+        unsafe {
+            match loaded.load(path) {
+                Err(TTFileError::BadChecksum) => {},
+                r => panic!("expected a checksum error, got {:?}", r.err()),
+            }
+        }
+        assert!(!loaded.probe(keys[0]).0);
+
+        // As is a file of a different layout.
+        bytes[16] = CLUSTER_SIZE as u8 + 1;
+        ::std::fs::write(path, &bytes).unwrap();
+        unsafe {
+            match loaded.load(path) {
+                Err(TTFileError::LayoutMismatch {..}) => {},
+                r => panic!("expected a layout error, got {:?}", r.err()),
+            }
+        }
+        bytes[0] = b'X';
+        ::std::fs::write(path, &bytes).unwrap();
+        unsafe { assert!(loaded.load(path).is_err()); }
+        ::std::fs::remove_file(path).unwrap();
+        unsafe { assert!(loaded.load(path).is_err()); }
+    }
+
+    #[test]
+    fn tt_resize() {
+        let tt = TranspositionTable::new_num_clusters(1024);
+        unsafe {
+            tt.resize_round_up(100);
+            assert_eq!(tt.num_clusters(), 128);
+            tt.resize_round_up(4096);
+        }
+        assert_eq!(tt.num_clusters(), 4096);
+        for i in 0..4096u64 {
+            let (_found, entry) = tt.probe(create_key(1, i));
+            entry.place(create_key(1, i), BitMove::null(), 0, 0, 1, NodeBound::Exact, tt.time_age());
+        }
+    }
+
+    // The entry placed for the key at 'index' of the keys in 'tt_concurrent_consistency'.
+    fn stress_entry(index: u64) -> Entry {
+        let mut entry: Entry = unsafe { mem::zeroed() };
+        entry.place(create_key(0x0101 * (index as u16 + 1), index % 2), BitMove::new(0x0F0F ^ (index as u16 * 977)),
+                    (index as i16) * 100 + 7, -(index as i16) * 31 - 2, index as i16 + 1, NodeBound::Exact, 0);
+        entry
+    }
+
+    #[test]
+    fn tt_concurrent_consistency() {
+        const KEYS: u64 = 8;
+        const THREADS: u64 = 8;
+        let keys: Vec<u64> = (0..KEYS).map(|i| create_key(0x0101 * (i as u16 + 1), i % 2)).collect();
+        let entries: Vec<Entry> = (0..KEYS).map(stress_entry).collect();
+        let same_data = |a: &Entry, b: &Entry| a.best_move == b.best_move && a.score == b.score
+            && a.eval == b.eval && a.depth == b.depth;
+
+        // Any mix of the fields of two entries must not pass as a different entry, otherwise a torn
+        // read going undetected is expected of a 16 bit check rather than being an error.
+        for a in entries.iter() {
+            for b in entries.iter() {
+                for mask in 0..32u8 {
+                    let pick = |bit: u8| if mask & (1 << bit) == 0 {a} else {b};
+                    let mut mixed = a.clone();
+                    mixed.key_check = pick(0).key_check;
+                    mixed.best_move = pick(1).best_move;
+                    mixed.score = pick(2).score;
+                    mixed.eval = pick(3).eval;
+                    mixed.depth = pick(4).depth;
+                    for (i, key) in keys.iter().enumerate() {
+                        if mixed.matches(*key) {
+                            assert!(same_data(&mixed, &entries[i]));
+                        }
+                    }
+                }
+            }
+        }
+
+        // Two clusters of three entries, so threads are constantly overwriting each other.
+        let tt = Arc::new(TranspositionTable::new_num_clusters(2));
+        let reads = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..THREADS).map(|t| {
+            let (tt, keys, entries, reads) = (tt.clone(), keys.clone(), entries.clone(), reads.clone());
+            thread::spawn(move || {
+                let mut rng: u64 = 0x9E37_79B9_7F4A_7C15 ^ (t + 1);
+                for _ in 0..1_000_000 {
+                    rng ^= rng << 13;
+                    rng ^= rng >> 7;
+                    rng ^= rng << 17;
+                    let i = (rng % KEYS) as usize;
+                    if rng & 0x100 == 0 {
+                        if let Some(entry) = tt.read(keys[i]) {
+                            assert!(same_data(&entry, &entries[i]), "torn entry read for key {}", i);
+                            assert!(entry.node_type() == NodeBound::Exact);
+                            reads.fetch_add(1, Ordering::Relaxed);
+                        }
+                    } else {
+                        let e = &entries[i];
+                        let (_found, entry) = tt.probe(keys[i]);
+                        entry.place(keys[i], e.best_move, e.score, e.eval, e.depth as i16, NodeBound::Exact, 0);
+                    }
+                }
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(reads.load(Ordering::Relaxed) > 0);
+    }
+
+    #[test]
+    fn tt_clear_parts() {
+        let tt = TranspositionTable::new_num_clusters(1000);
+        let keys: Vec<u64> = (0..1024u64).map(|i| create_key(7, i)).collect();
+        for key in keys.iter() {
+            let (_found, entry) = tt.probe(*key);
+            entry.place(*key, BitMove::new(0x555), 1, 2, 3, NodeBound::Exact, tt.time_age());
+        }
+
+        // Clearing only some of the parts leaves the rest of the table.
+        unsafe {
+            tt.clear_part(0, 3);
+            tt.clear_part(2, 3);
+        }
+        let found = keys.iter().filter(|k| tt.probe(**k).0).count();
+        assert_eq!(found, 1024 * 2 / 3 - 1024 / 3);
+        unsafe { tt.clear_part(1, 3); }
+        assert!(keys.iter().all(|k| !tt.probe(*k).0));
+
+        unsafe {
+            tt.resize_to_megabytes_uncleared(1);
+            for part in 0..7 {
+                tt.clear_part(part, 7);
+            }
+        }
+        assert_eq!(tt.num_clusters(), 16384);
+        assert_eq!(tt.hashfull(), 0);
+        assert!(!tt.probe(keys[0]).0);
+    }
+
+    #[test]
+    fn tt_default_layout() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.cluster_size(), CLUSTER_SIZE);
+        assert_eq!(tt.cluster_bytes_len(), 32);
+        assert_eq!(tt.num_clusters(), 16384);
+    }
+
+    // A full key, with the depth and generation.
+    #[repr(C)]
+    struct Slot {
+        key: u64,
+        depth: i16,
+        gen: u8,
+        _padding: [u8; 5],
+    }
+
+    unsafe impl TTEntry for Slot {
+        fn matches(&self, key: Key) -> bool { self.key == key }
+        fn is_vacant(&self) -> bool { self.key == 0 }
+        fn depth(&self) -> i16 { self.depth }
+        fn generation(&self) -> u8 { self.gen }
+        fn set_generation(&mut self, gen: u8) { self.gen = gen; }
+    }
+
+    fn slot(key: u64, depth: i16, gen: u8) -> Slot {
+        Slot {key, depth, gen, _padding: [0; 5]}
+    }
+
+    #[test]
+    fn tt_replacement_policies() {
+        let cluster = [slot(1, 9, 4), slot(2, 3, 4), slot(3, 6, 0), slot(4, 5, 4)];
+        assert_eq!(AlwaysReplace::replace(&cluster, 4), 0);
+        assert_eq!(DepthPreferred::replace(&cluster, 4), 1);
+        // The first entry is kept while it is from the current search.
+        assert_eq!(TwoTier::replace(&cluster, 4), 1);
+        assert_eq!(TwoTier::replace(&cluster, 8), 0);
+        assert_eq!(TwoTier::replace(&cluster[..1], 4), 0);
+        // Entries of an older search are replaced before deeper ones.
+        assert_eq!(DepthAge::replace(&cluster, 4), 2);
+        assert_eq!(DepthAge::replace(&cluster[..2], 4), 1);
+    }
+
+    #[test]
+    fn tt_generic_cluster_sizes() {
+        for &cluster_size in [1usize, 2, 3, 4, 5, 8].iter() {
+            let tt: TranspositionTable<Slot, DepthPreferred> = TranspositionTable::with_clusters(16, cluster_size);
+            let stride = tt.cluster_bytes_len();
+            assert!(stride >= cluster_size * mem::size_of::<Slot>());
+            assert!(stride.is_power_of_two() || stride % CACHE_LINE == 0);
+            assert_eq!(tt.num_entries(), 16 * cluster_size);
+            tt.new_search();
+
+            // Fill up a single cluster.
+            for i in 0..cluster_size as u64 {
+                let key = ((i + 1) << 32) | 5;
+                let (found, entry) = tt.probe(key);
+                assert!(!found);
+                assert!(entry.is_vacant());
+                *entry = slot(key, 10 - i as i16, tt.time_age());
+            }
+            for i in 0..cluster_size as u64 {
+                let (found, entry) = tt.probe(((i + 1) << 32) | 5);
+                assert!(found);
+                assert_eq!(entry.depth, 10 - i as i16);
+            }
+            // Other clusters are left untouched.
+            assert!(!tt.probe(6).0);
+
+            // The shallowest entry is replaced once the cluster is full.
+            let (found, entry) = tt.probe(0xFFFF_0000_0000_0005);
+            assert!(!found);
+            assert_eq!(entry.key, ((cluster_size as u64) << 32) | 5);
+            assert_eq!(tt.hashfull(), (1000 / 16) as u16);
+        }
+    }
+
     /// Helper function to create a key of specified index / partial_key
     fn create_key(partial_key: u16, full_key: u64) -> u64 {
         (partial_key as u64).wrapping_shl(48) | (full_key & 0x0000_FFFF_FFFF_FFFF)
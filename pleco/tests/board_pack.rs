@@ -0,0 +1,93 @@
+extern crate pleco;
+extern crate rand;
+
+use pleco::board::{Board,RandBoard};
+use pleco::board::fen::ALL_FENS;
+use pleco::board::pack::{PACKED_LEN,PACK_VERSION};
+
+use rand::Rng;
+
+fn assert_round_trip(board: &Board) {
+    let packed = board.pack();
+    let unpacked = Board::unpack(&packed).unwrap();
+    assert_eq!(board.fen(), unpacked.fen());
+    assert_eq!(board.zobrist(), unpacked.zobrist());
+    assert_eq!(board.pawn_key(), unpacked.pawn_key());
+    assert_eq!(board.checkers(), unpacked.checkers());
+    assert_eq!(board.generate_moves().len(), unpacked.generate_moves().len());
+    assert_eq!(&packed[..], &unpacked.pack()[..]);
+}
+
+#[test]
+fn pack_start_pos() {
+    let board = Board::start_pos();
+    let packed = board.pack();
+    assert_eq!(packed.len(), PACKED_LEN);
+    assert_eq!(packed[24], PACK_VERSION);
+    // The first two squares are a white rook and knight.
+    assert_eq!(packed[8], 0x24);
+    assert_round_trip(&board);
+}
+
+#[test]
+fn pack_all_fens() {
+    for fen in ALL_FENS.iter() {
+        assert_round_trip(&Board::from_fen(fen).unwrap());
+    }
+    assert_round_trip(&Board::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3").unwrap());
+    assert_round_trip(&Board::from_fen("8/8/8/8/8/8/8/K6k b - - 99 214").unwrap());
+}
+
+#[test]
+fn pack_random_boards() {
+    let mut rng = rand::thread_rng();
+    for mut board in Board::random().many(20) {
+        assert_round_trip(&board);
+        for _ in 0..30 {
+            let moves = board.generate_moves();
+            if moves.is_empty() {
+                break;
+            }
+            board.apply_move(moves[rng.gen_range(0, moves.len())]);
+            assert_round_trip(&board);
+        }
+    }
+}
+
+#[test]
+fn pack_seeded_boards() {
+    for board in RandBoard::new().pseudo_random(81_912).many(50) {
+        assert_round_trip(&board);
+    }
+}
+
+#[test]
+fn unpack_errors() {
+    let packed = Board::start_pos().pack();
+    assert!(Board::unpack(&packed[..31]).is_err());
+
+    let mut version = packed;
+    version[24] = PACK_VERSION + 1;
+    assert!(Board::unpack(&version).is_err());
+
+    let mut piece = packed;
+    piece[8] = 0x27;
+    assert!(Board::unpack(&piece).is_err());
+
+    let mut ep = packed;
+    ep[26] = 20;
+    assert!(Board::unpack(&ep).is_err());
+
+    let mut reserved = packed;
+    reserved[31] = 1;
+    assert!(Board::unpack(&reserved).is_err());
+
+    let mut flags = packed;
+    flags[25] = 0xFF;
+    assert!(Board::unpack(&flags).is_err());
+
+    // A trailing nibble past the last piece.
+    let mut occupancy = packed;
+    occupancy[0] = 0;
+    assert!(Board::unpack(&occupancy).is_err());
+}
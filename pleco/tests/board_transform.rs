@@ -0,0 +1,57 @@
+extern crate pleco;
+
+use pleco::Board;
+
+fn placement(board: &Board) -> String {
+    board.fen().split_whitespace().next().unwrap().to_string()
+}
+
+fn assert_keys_match_fen(board: &Board) {
+    let fen_board = Board::from_fen(&board.fen()).unwrap();
+    assert_eq!(board.zobrist(), fen_board.zobrist());
+    assert_eq!(board.pawn_key(), fen_board.pawn_key());
+    assert_eq!(board.material_key(), fen_board.material_key());
+    assert_eq!(board.checkers(), fen_board.checkers());
+}
+
+#[test]
+fn flip_colors_transform() {
+    let boards = Board::random().pseudo_random(0x3A81_55F0).many(40);
+    for board in boards.iter() {
+        let flipped = board.flip_colors();
+        assert_keys_match_fen(&flipped);
+        assert_eq!(flipped.turn(), !board.turn());
+        assert_eq!(flipped.rule_50(), board.rule_50());
+        assert_eq!(flipped.generate_moves().len(), board.generate_moves().len());
+        assert_eq!(flipped.in_check(), board.in_check());
+
+        let back = flipped.flip_colors();
+        assert_eq!(back.fen(), board.fen());
+        assert_eq!(back.zobrist(), board.zobrist());
+    }
+}
+
+#[test]
+fn mirror_and_rotate_transform() {
+    let boards = Board::random().pseudo_random(0x11C0_9E2D).many(40);
+    for board in boards.iter() {
+        let mirrored = board.mirror_horizontal();
+        assert_keys_match_fen(&mirrored);
+        assert_eq!(mirrored.turn(), board.turn());
+        assert_eq!(mirrored.castling_bits(), 0);
+        assert_eq!(placement(&mirrored.mirror_horizontal()), placement(board));
+
+        let rotated = board.rotate_180();
+        assert_keys_match_fen(&rotated);
+        assert_eq!(rotated.fen(), board.flip_colors().mirror_horizontal().fen());
+        assert_eq!(placement(&rotated.rotate_180()), placement(board));
+    }
+}
+
+#[test]
+fn flip_colors_ep_and_castling() {
+    let board = Board::from_fen("r3k2r/8/8/8/3pP3/8/8/R3K3 b Qkq e3 0 12").unwrap();
+    let flipped = board.flip_colors();
+    assert_eq!(flipped.fen(), "r3k3/8/8/3Pp3/8/8/8/R3K2R w KQq e6 0 12");
+    assert_eq!(flipped.generate_moves().len(), board.generate_moves().len());
+}
@@ -0,0 +1,61 @@
+extern crate pleco;
+extern crate rand;
+
+use pleco::{Board,PieceType,SQ};
+use pleco::core::score::Score;
+use pleco::helper::psqt::{self,Psqt};
+
+use rand::Rng;
+
+fn assert_psq_consistent(board: &Board) {
+    let mut fresh = board.shallow_clone();
+    fresh.recompute_psq();
+    assert_eq!(board.psq(), fresh.psq());
+    assert_eq!(board.psq(), Board::from_fen(&board.fen()).unwrap().psq());
+}
+
+// The tables are global, so everything changing them is kept within a single test.
+#[test]
+fn alternate_psqt() {
+    let mut rng = rand::thread_rng();
+    let mut board = Board::from_fen("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1").unwrap();
+    let default_psq = board.psq();
+    let old_bonus = Psqt::default().bonus(PieceType::N, SQ::B1);
+
+    let mut table = Psqt::default();
+    table.set_bonus(PieceType::N, SQ::B1, Score(-100, -40));
+    table.set_bonus(PieceType::P, SQ::E4, Score(60, 10));
+    unsafe { psqt::set_psqt(&table); }
+    assert!(psqt::current_psqt() == table);
+
+    // Untouched until recomputed.
+    assert_eq!(board.psq(), default_psq);
+    board.recompute_psq();
+    assert_eq!(board.psq(), default_psq + Score(-100, -40) - old_bonus);
+
+    // Incremental updates use the new tables.
+    board = Board::start_pos();
+    for _ in 0..60 {
+        let moves = board.generate_moves();
+        if moves.is_empty() {
+            break;
+        }
+        board.apply_move(moves[rng.gen_range(0, moves.len())]);
+        assert_psq_consistent(&board);
+    }
+
+    let blank = Psqt::parse(&Psqt::blank().to_string()).unwrap();
+    unsafe { psqt::set_psqt(&blank); }
+    board.recompute_psq();
+    assert_psq_consistent(&board);
+
+    // The positions before the moves played are recomputed as well.
+    while board.ply() > 0 {
+        board.undo_move();
+        assert_psq_consistent(&board);
+    }
+
+    unsafe { psqt::set_psqt(&Psqt::default()); }
+    board.recompute_psq();
+    assert_psq_consistent(&board);
+}
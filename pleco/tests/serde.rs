@@ -0,0 +1,113 @@
+#![cfg(feature = "serde")]
+
+extern crate pleco;
+extern crate serde;
+extern crate serde_json;
+extern crate bincode;
+
+use pleco::{Board,BitMove,SQ,BitBoard,Piece,PieceType,Player};
+use pleco::core::score::Score;
+use pleco::board::castle_rights::Castling;
+use pleco::board::fen::ALL_FENS;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> (String, T, T) {
+    let json = serde_json::to_string(value).unwrap();
+    let from_json: T = serde_json::from_str(&json).unwrap();
+    let bytes = bincode::serialize(value).unwrap();
+    let from_bincode: T = bincode::deserialize(&bytes).unwrap();
+    (json, from_json, from_bincode)
+}
+
+#[test]
+fn square_and_bitboard() {
+    assert_eq!(serde_json::to_string(&SQ::E4).unwrap(), "\"e4\"");
+    assert_eq!(bincode::serialize(&SQ::E4).unwrap(), vec![28]);
+    for sq in 0..64 {
+        let (_, json, bin) = round_trip(&SQ(sq));
+        assert_eq!(json, SQ(sq));
+        assert_eq!(bin, SQ(sq));
+    }
+    assert!(serde_json::from_str::<SQ>("\"i9\"").is_err());
+
+    let bb = BitBoard::RANK_2;
+    let (json, from_json, from_bin) = round_trip(&bb);
+    assert_eq!(json, "\"0x000000000000ff00\"");
+    assert_eq!(from_json, bb);
+    assert_eq!(from_bin, bb);
+    assert_eq!(bincode::serialize(&bb).unwrap().len(), 8);
+}
+
+#[test]
+fn moves() {
+    let board = Board::start_pos();
+    let mov = board.generate_moves().iter()
+        .find(|m| m.stringify() == "e2e4")
+        .cloned()
+        .unwrap();
+    assert_eq!(serde_json::to_string(&mov).unwrap(), "\"e2e4\"");
+    assert_eq!(bincode::deserialize::<BitMove>(&bincode::serialize(&mov).unwrap()).unwrap(), mov);
+
+    let from_json: BitMove = serde_json::from_str("\"e2e4\"").unwrap();
+    assert_eq!(from_json.get_src(), SQ::E2);
+    assert_eq!(from_json.get_dest(), SQ::E4);
+    let promo: BitMove = serde_json::from_str("\"b7b8n\"").unwrap();
+    assert!(promo.is_promo());
+    assert_eq!(promo.promo_piece(), PieceType::N);
+    assert_eq!(promo.stringify(), "b7b8n");
+    assert!(serde_json::from_str::<BitMove>("\"e2e4x\"").is_err());
+}
+
+#[test]
+fn pieces_and_players() {
+    for piece in [Piece::None, Piece::WhiteKnight, Piece::BlackKing].iter() {
+        let (_, json, bin) = round_trip(piece);
+        assert_eq!(json, *piece);
+        assert_eq!(bin, *piece);
+    }
+    assert_eq!(serde_json::to_string(&Piece::BlackQueen).unwrap(), "\"q\"");
+
+    for piece_type in [PieceType::None, PieceType::P, PieceType::K, PieceType::All].iter() {
+        let (_, json, bin) = round_trip(piece_type);
+        assert_eq!(json, *piece_type);
+        assert_eq!(bin, *piece_type);
+    }
+    assert_eq!(serde_json::to_string(&PieceType::R).unwrap(), "\"R\"");
+
+    let (json, from_json, from_bin) = round_trip(&Player::Black);
+    assert_eq!(json, "\"black\"");
+    assert_eq!(from_json, Player::Black);
+    assert_eq!(from_bin, Player::Black);
+}
+
+#[test]
+fn score_and_castling() {
+    let score = Score(35, -12);
+    let (json, from_json, from_bin) = round_trip(&score);
+    assert_eq!(json, "{\"mg\":35,\"eg\":-12}");
+    assert_eq!(from_json, score);
+    assert_eq!(from_bin, score);
+
+    let castling = Castling::WHITE_K | Castling::BLACK_ALL;
+    let (json, from_json, from_bin) = round_trip(&castling);
+    assert_eq!(json, "\"Kkq\"");
+    assert_eq!(from_json, castling);
+    assert_eq!(from_bin, castling);
+    assert_eq!(serde_json::from_str::<Castling>("\"-\"").unwrap(), Castling::empty());
+    assert!(serde_json::from_str::<Castling>("\"KX\"").is_err());
+}
+
+#[test]
+fn boards() {
+    for fen in ALL_FENS.iter() {
+        let board = Board::from_fen(fen).unwrap();
+        let (json, from_json, from_bin) = round_trip(&board);
+        assert_eq!(json, format!("\"{}\"", fen));
+        assert_eq!(from_json.fen(), *fen);
+        assert_eq!(from_bin.fen(), *fen);
+    }
+    assert!(bincode::serialize(&Board::start_pos()).unwrap().len() < 48);
+    assert!(serde_json::from_str::<Board>("\"not a fen\"").is_err());
+}
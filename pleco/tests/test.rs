@@ -8,4 +8,5 @@ mod board_move_apply;
 mod basic_bots;
 mod pseudo_legal_checks;
 mod board_hash;
+mod board_transform;
 
@@ -7,6 +7,7 @@ extern crate pleco_engine;
 mod eval_benches;
 mod multimove_benches;
 mod startpos_benches;
+mod tt_benches;
 
 trait DepthLimit {
     fn depth() -> u16;
@@ -31,5 +32,6 @@ impl DepthLimit for Depth9 { fn depth() -> u16 {9} }
 criterion_main!{
     eval_benches::eval_benches,
     multimove_benches::search_multimove,
-    startpos_benches::search_singular
+    startpos_benches::search_singular,
+    tt_benches::tt_benches
 }
@@ -7,7 +7,9 @@ use pleco_engine::tables::pawn_table::{PawnEntry, PawnTable};
 use pleco_engine::tables::material::{Material, MaterialEntry};
 use pleco::core::mono_traits::WhiteType;
 
+use pleco::core::score::Score;
 use pleco_engine::search::eval::Evaluation;
+use pleco_engine::search::evaluator::{Evaluator, HandCrafted};
 
 fn bench_100_pawn_evals(b: &mut Bencher, boards: &Vec<Board>) {
 
@@ -68,6 +70,38 @@ fn bench_100_eval(b: &mut Bencher,  boards: &Vec<Board>) {
     }, BatchSize::PerIteration)
 }
 
+fn bench_100_lazy_eval(b: &mut Bencher,  boards: &Vec<Board>) {
+    b.iter_batched(|| {
+        let tp: PawnTable = black_box(PawnTable::new());
+        let tm: Material = black_box(Material::new());
+        (tp, tm)
+    }, |(mut tp, mut tm)| {
+        #[allow(unused_variables)]
+        let mut score: i64 = 0;
+        for board in boards.iter() {
+            let (value, _lazy) = black_box(Evaluation::evaluate_in_window(&board, &mut tp, &mut tm,
+                                                                          Score::ZERO, -1, 1));
+            score += value as i64;
+        }
+    }, BatchSize::PerIteration)
+}
+
+fn bench_100_cached_eval(b: &mut Bencher,  boards: &Vec<Board>) {
+    b.iter_batched(|| {
+        let mut evaluator = HandCrafted::new();
+        for board in boards.iter() {
+            evaluator.evaluate(&board);
+        }
+        evaluator
+    }, |mut evaluator| {
+        #[allow(unused_variables)]
+        let mut score: i64 = 0;
+        for board in boards.iter() {
+            score += black_box(evaluator.evaluate(&board)) as i64;
+        }
+    }, BatchSize::PerIteration)
+}
+
 fn bench_engine_evaluations(c: &mut Criterion) {
     let boards: Vec<Board> = RAND_BOARD_NON_CHECKS_100.iter()
         .map(|b| Board::from_fen(b).unwrap())
@@ -77,8 +111,10 @@ fn bench_engine_evaluations(c: &mut Criterion) {
     let pawn_king_evals = Fun::new("Pawn & King Evaluations", bench_100_pawn_king_evals);
     let material_evals = Fun::new("Material Evaluations", bench_100_material_eval);
     let full_evals =  Fun::new("Full Evaluation", bench_100_eval);
+    let lazy_evals =  Fun::new("Lazy Evaluation", bench_100_lazy_eval);
+    let cached_evals =  Fun::new("Cached Evaluation", bench_100_cached_eval);
 
-    let funcs = vec![pawn_evals, pawn_king_evals, material_evals, full_evals];
+    let funcs = vec![pawn_evals, pawn_king_evals, material_evals, full_evals, lazy_evals, cached_evals];
 
     c.bench_functions("Engine Evaluations", funcs, boards);
 }
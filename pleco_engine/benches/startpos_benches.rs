@@ -1,9 +1,11 @@
 use std::time::Duration;
-use criterion::{Criterion,black_box,Bencher,BatchSize};
+use criterion::{Criterion,black_box,Bencher,BatchSize,Benchmark,Throughput};
 
 use pleco::{Board};
+use pleco::core::score::{Score,Value};
 
 use pleco_engine::engine::PlecoSearcher;
+use pleco_engine::search::evaluator::{Evaluator,EvaluatorFactory,HandCrafted};
 use pleco_engine::time::uci_timer::PreLimits;
 use pleco_engine::threadpool::*;
 
@@ -32,10 +34,77 @@ fn bench_engine_evaluations(c: &mut Criterion) {
     c.bench_function("Search Singular Depth 9", search_singular_engine::<Depth9>);
 }
 
+// The hand-crafted evaluation without lazy evaluation, as `evaluate_in_window` defaults to a
+// full evaluation.
+struct NonLazy(HandCrafted);
+
+impl Evaluator for NonLazy {
+    fn evaluate(&mut self, board: &Board) -> Value {
+        self.0.evaluate(board)
+    }
+
+    fn trace(&mut self, board: &Board) -> String {
+        self.0.trace(board)
+    }
+
+    fn prefetch(&self, board: &Board) {
+        self.0.prefetch(board)
+    }
+
+    fn set_contempt(&mut self, contempt: Score) {
+        self.0.set_contempt(contempt)
+    }
+
+    fn clear(&mut self) {
+        self.0.clear()
+    }
+}
+
+fn non_lazy() -> Box<Evaluator> {
+    Box::new(NonLazy(HandCrafted::new()))
+}
+
+// Searches the starting position on a single thread, with the throughput being the number
+// of nodes searched, so the search speed is reported in nodes per second.
+fn search_nps<D: DepthLimit>(name: &str, evaluator: EvaluatorFactory) -> Benchmark {
+    let mut pre_limit = PreLimits::blank();
+    pre_limit.depth = Some(D::depth());
+    let limit = pre_limit.create();
+    let setup = move || {
+        let mut searcher = PlecoSearcher::init_with_evaluator(false, evaluator);
+        threadpool().set_thread_count(1);
+        threadpool().clear_all();
+        searcher.clear_tt();
+        searcher
+    };
+
+    // With a single thread and cleared tables, every search visits the same nodes.
+    let mut searcher = setup();
+    threadpool().search(&Board::start_pos(), &limit);
+    let nodes = threadpool().nodes();
+    searcher.clear_tt();
+
+    Benchmark::new(name, move |b: &mut Bencher| {
+        let mut searcher = setup();
+        b.iter_batched(|| {
+            threadpool().clear_all();
+            searcher.clear_tt();
+            Board::start_pos()
+        }, |board| {
+            black_box(threadpool().search(&board, &limit));
+        }, BatchSize::PerIteration)
+    }).throughput(Throughput::Elements(nodes as u32))
+}
+
+fn bench_lazy_eval_nps(c: &mut Criterion) {
+    c.bench("Search NPS", search_nps::<Depth8>("Depth 8 Lazy Eval", HandCrafted::boxed));
+    c.bench("Search NPS", search_nps::<Depth8>("Depth 8 Full Eval", non_lazy));
+}
+
 criterion_group!(name = search_singular;
      config = Criterion::default()
         .sample_size(35)
         .warm_up_time(Duration::from_millis(150));
-    targets = bench_engine_evaluations
+    targets = bench_engine_evaluations, bench_lazy_eval_nps
 );
 
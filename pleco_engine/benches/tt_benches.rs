@@ -0,0 +1,36 @@
+use std::time::Duration;
+use criterion::{Criterion,black_box,Bencher};
+
+use pleco::BitMove;
+use pleco::tools::prng::PRNG;
+use pleco::tools::tt::{TranspositionTable,NodeBound};
+
+// Run with and without `--features huge_pages` to compare the effect of huge pages.
+const TT_MB: usize = 512;
+
+fn bench_10000_tt_probes(b: &mut Bencher, tt: &TranspositionTable) {
+    let mut prng = PRNG::init(0x5FA7_9913);
+    let keys: Vec<u64> = (0..10_000).map(|_| prng.rand()).collect();
+    b.iter(|| {
+        for key in keys.iter() {
+            let (found, entry) = black_box(tt.probe(*key));
+            if !found {
+                entry.place(*key, BitMove::null(), 0, 0, 1, NodeBound::Exact, tt.time_age());
+            }
+        }
+    })
+}
+
+fn bench_tt(c: &mut Criterion) {
+    let tt = TranspositionTable::new(TT_MB);
+    println!("{} MB transposition table, {} MB in huge pages", tt.size_megabytes(),
+             tt.huge_page_bytes() / (1024 * 1024));
+    c.bench_function("TT Random Probes", move |b| bench_10000_tt_probes(b, &tt));
+}
+
+criterion_group!(name = tt_benches;
+     config = Criterion::default()
+        .sample_size(50)
+        .warm_up_time(Duration::from_millis(200));
+    targets = bench_tt
+);
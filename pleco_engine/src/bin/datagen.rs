@@ -0,0 +1,156 @@
+//! Generates training data from self-play games, searching each move to a fixed depth or a
+//! fixed number of nodes.
+//!
+//! See `pleco_engine::datagen` for the output formats.
+//!
+//! ```text
+//! datagen <out> [--games N] [--depth N | --nodes N] [--threads N] [--seed N]
+//!               [--hash MB] [--random-plies N] [--max-plies N] [--binary]
+//! ```
+//!
+//! Every thread searches with its own transposition table of `--hash` megabytes, so the output
+//! only depends on the seed and the search limit, and not on the number of threads.
+
+extern crate pleco_engine;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::File;
+use std::io::{BufWriter,Write};
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64,Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use pleco_engine::consts::{init_globals,USE_STDOUT};
+use pleco_engine::datagen::{self,Generator,Sample};
+use pleco_engine::time::uci_timer::LimitsType;
+
+struct Options {
+    out: String,
+    games: u64,
+    limit: LimitsType,
+    threads: usize,
+    seed: u64,
+    hash: usize,
+    random_plies: u16,
+    max_plies: u16,
+    binary: bool,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: datagen <out> [--games N] [--depth N | --nodes N] [--threads N] [--seed N]");
+    eprintln!("               [--hash MB] [--random-plies N] [--max-plies N] [--binary]");
+    process::exit(1);
+}
+
+fn parse_args() -> Options {
+    let mut opts = Options {
+        out: String::new(),
+        games: 100,
+        limit: LimitsType::Depth(6),
+        threads: 1,
+        seed: 1,
+        hash: datagen::DEFAULT_HASH_MB,
+        random_plies: 8,
+        max_plies: 400,
+        binary: false,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--games" => opts.games = value().parse().unwrap_or_else(|_| usage()),
+            "--depth" => opts.limit = LimitsType::Depth(value().parse().unwrap_or_else(|_| usage())),
+            "--nodes" => opts.limit = LimitsType::Nodes(value().parse().unwrap_or_else(|_| usage())),
+            "--threads" => opts.threads = value().parse().unwrap_or_else(|_| usage()),
+            "--seed" => opts.seed = value().parse().unwrap_or_else(|_| usage()),
+            "--hash" => opts.hash = value().parse().unwrap_or_else(|_| usage()),
+            "--random-plies" => opts.random_plies = value().parse().unwrap_or_else(|_| usage()),
+            "--max-plies" => opts.max_plies = value().parse().unwrap_or_else(|_| usage()),
+            "--binary" => opts.binary = true,
+            _ if opts.out.is_empty() && !arg.starts_with("--") => opts.out = arg,
+            _ => usage(),
+        }
+    }
+    if opts.out.is_empty() || opts.threads == 0 || opts.hash == 0 {
+        usage();
+    }
+    opts
+}
+
+fn main() {
+    let opts = parse_args();
+    init_globals();
+    USE_STDOUT.store(false, Ordering::Relaxed);
+
+    let file = File::create(&opts.out).unwrap_or_else(|e| {
+        eprintln!("unable to create {}: {}", opts.out, e);
+        process::exit(1);
+    });
+    let mut out = BufWriter::new(file);
+
+    // Workers take the next game off a shared counter, and the games are written out in order.
+    let next = Arc::new(AtomicU64::new(0));
+    let (tx, rx) = mpsc::channel::<(u64, Vec<Sample>)>();
+    let mut workers = Vec::new();
+    for _ in 0..opts.threads {
+        let next = next.clone();
+        let tx = tx.clone();
+        let (limit, seed, games, hash) = (opts.limit.clone(), opts.seed, opts.games, opts.hash);
+        let (random_plies, max_plies) = (opts.random_plies, opts.max_plies);
+        // The searcher is too large for the default stack.
+        let worker = thread::Builder::new()
+            .stack_size(64 * 1024 * 1024)
+            .spawn(move || {
+                let mut gen = Generator::new(limit)
+                    .hash_size(hash)
+                    .random_plies(random_plies)
+                    .max_plies(max_plies);
+                loop {
+                    let game = next.fetch_add(1, Ordering::Relaxed);
+                    if game >= games {
+                        return;
+                    }
+                    let samples = gen.play(datagen::game_seed(seed, game));
+                    if tx.send((game, samples)).is_err() {
+                        return;
+                    }
+                }
+            })
+            .unwrap();
+        workers.push(worker);
+    }
+    drop(tx);
+
+    let mut pending: BTreeMap<u64, Vec<Sample>> = BTreeMap::new();
+    let mut written: u64 = 0;
+    let mut positions: usize = 0;
+    for (game, samples) in rx {
+        pending.insert(game, samples);
+        while let Some(samples) = pending.remove(&written) {
+            for sample in samples.iter() {
+                let res = if opts.binary {
+                    sample.write_binary(&mut out)
+                } else {
+                    sample.write_text(&mut out)
+                };
+                res.unwrap_or_else(|e| {
+                    eprintln!("unable to write to {}: {}", opts.out, e);
+                    process::exit(1);
+                });
+            }
+            positions += samples.len();
+            written += 1;
+            if written % 10 == 0 || written == opts.games {
+                println!("{} games, {} positions", written, positions);
+            }
+        }
+    }
+    out.flush().unwrap();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    println!("wrote {} positions to {}", positions, opts.out);
+}
@@ -0,0 +1,106 @@
+//! Tunes the evaluation parameters to a file of labelled positions, and writes the tuned
+//! parameters out as Rust source for `pleco_engine/src/tune/mod.rs`.
+//!
+//! See `pleco_engine::tune::texel` for the format of the position file.
+//!
+//! ```text
+//! texel <positions> [--rounds N] [--epochs N] [--rate F] [--limit N] [--out FILE]
+//! ```
+
+extern crate pleco_engine;
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::process;
+use std::thread;
+
+use pleco_engine::consts::init_globals;
+use pleco_engine::tune;
+use pleco_engine::tune::texel::{self,Tuner};
+
+struct Options {
+    positions: String,
+    rounds: usize,
+    epochs: usize,
+    rate: f64,
+    limit: Option<usize>,
+    out: String,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: texel <positions> [--rounds N] [--epochs N] [--rate F] [--limit N] [--out FILE]");
+    process::exit(1);
+}
+
+fn parse_args() -> Options {
+    let mut opts = Options {
+        positions: String::new(),
+        rounds: 3,
+        epochs: 500,
+        rate: 1.0,
+        limit: None,
+        out: String::from("tuned_params.rs"),
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--rounds" => opts.rounds = value().parse().unwrap_or_else(|_| usage()),
+            "--epochs" => opts.epochs = value().parse().unwrap_or_else(|_| usage()),
+            "--rate" => opts.rate = value().parse().unwrap_or_else(|_| usage()),
+            "--limit" => opts.limit = Some(value().parse().unwrap_or_else(|_| usage())),
+            "--out" => opts.out = value(),
+            _ if opts.positions.is_empty() && !arg.starts_with("--") => opts.positions = arg,
+            _ => usage(),
+        }
+    }
+    if opts.positions.is_empty() {
+        usage();
+    }
+    opts
+}
+
+fn run(opts: Options) {
+    let mut positions = texel::load_positions(&opts.positions).unwrap_or_else(|e| {
+        eprintln!("unable to read {}: {}", opts.positions, e);
+        process::exit(1);
+    });
+    if let Some(limit) = opts.limit {
+        positions.truncate(limit);
+    }
+    println!("loaded {} positions", positions.len());
+
+    texel::quiesce(&mut positions);
+    println!("quiesced positions");
+
+    let mut tuner = Tuner::new();
+    for round in 1..=opts.rounds {
+        tuner.linearize(&positions);
+        if round == 1 {
+            println!("K = {:.4}", tuner.fit_k());
+        }
+        let start = tuner.error();
+        let end = tuner.train(opts.epochs, opts.rate);
+        println!("round {}: error {:.6} -> {:.6}", round, start, end);
+        tuner.apply();
+    }
+
+    let mut file = File::create(&opts.out).unwrap_or_else(|e| {
+        eprintln!("unable to create {}: {}", opts.out, e);
+        process::exit(1);
+    });
+    file.write_all(tune::rust_source().as_bytes()).unwrap();
+    println!("wrote tuned parameters to {}", opts.out);
+}
+
+fn main() {
+    let opts = parse_args();
+    init_globals();
+    // The searcher used to quiesce positions is too large for the main thread's stack.
+    let handle = thread::Builder::new()
+        .stack_size(64 * 1024 * 1024)
+        .spawn(move || run(opts))
+        .unwrap();
+    handle.join().unwrap();
+}
@@ -1,7 +1,7 @@
 //! Constant values and static structures.
 use std::mem;
 use std::ptr;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool,AtomicI32,AtomicU8};
 use std::sync::atomic::Ordering;
 use std::sync::{ONCE_INIT,Once};
 use std::sync::atomic::compiler_fence;
@@ -13,15 +13,24 @@ use time::time_management::TimeManager;
 use threadpool;
 use search;
 use tables::pawn_table;
+use search::AnalysisContempt;
 
 pub const MAX_PLY: u16 = 126;
 pub const THREAD_STACK_SIZE: usize = MAX_PLY as usize + 7;
 pub const MAX_THREADS: usize = 256;
 
 pub const DEFAULT_TT_SIZE: usize = 256;
+pub const DEFAULT_HASH_FILE: &str = "hash.bin";
 pub const PAWN_TABLE_SIZE: usize = 16384;
 pub const MATERIAL_TABLE_SIZE: usize = 8192;
 
+pub const DEFAULT_CONTEMPT: i32 = 21;
+
+pub const DEFAULT_MOVE_OVERHEAD: i32 = 100;
+pub const DEFAULT_SLOW_MOVER: i32 = 22;
+pub const DEFAULT_MIN_THINKING_TIME: i32 = 20;
+pub const DEFAULT_MOVE_HORIZON: i32 = 50;
+
 const TT_ALLOC_SIZE: usize = mem::size_of::<TranspositionTable>();
 const TIMER_ALLOC_SIZE: usize = mem::size_of::<TimeManager>();
 
@@ -31,6 +40,44 @@ type DummyTimeManager = [u8; TIMER_ALLOC_SIZE];
 
 pub static USE_STDOUT: AtomicBool = AtomicBool::new(true);
 
+/// Contempt for draws in centipawns, relative to the side to move at the root.
+///
+/// The search only scores stalemates as draws, so repetitions and the fifty-move rule
+/// are not affected by contempt.
+pub static CONTEMPT: AtomicI32 = AtomicI32::new(DEFAULT_CONTEMPT);
+
+/// Which side(s) keep their contempt while analysing, stored as an `AnalysisContempt`.
+pub static ANALYSIS_CONTEMPT: AtomicU8 = AtomicU8::new(AnalysisContempt::Off as u8);
+
+/// Whether the GUI has requested analysis mode through `UCI_AnalyseMode`.
+pub static ANALYSE_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Whether `info` lines carry win/draw/loss statistics, set through `UCI_ShowWDL`.
+pub static SHOW_WDL: AtomicBool = AtomicBool::new(false);
+
+/// Whether the search evaluates with the loaded network, set through `Use NNUE`.
+pub static USE_NNUE: AtomicBool = AtomicBool::new(false);
+
+/// Milliseconds lost to communication on each move, set through `Move Overhead`.
+pub static MOVE_OVERHEAD: AtomicI32 = AtomicI32::new(DEFAULT_MOVE_OVERHEAD);
+
+/// How much of the allocated time is used on each move, set through `Slow Mover`.
+pub static SLOW_MOVER: AtomicI32 = AtomicI32::new(DEFAULT_SLOW_MOVER);
+
+/// Minimum milliseconds to search each move for, set through `Minimum Thinking Time`.
+pub static MIN_THINKING_TIME: AtomicI32 = AtomicI32::new(DEFAULT_MIN_THINKING_TIME);
+
+/// Number of moves the remaining time is planned over when there is no `movestogo`, set
+/// through `Move Horizon`.
+pub static MOVE_HORIZON: AtomicI32 = AtomicI32::new(DEFAULT_MOVE_HORIZON);
+
+/// Nodes searched standing in for each millisecond, or zero to use the wall clock. Set
+/// through `nodestime`.
+pub static NODES_TIME: AtomicI32 = AtomicI32::new(0);
+
+/// Whether additional `info string` output is sent, toggled through the `debug` command.
+pub static DEBUG: AtomicBool = AtomicBool::new(false);
+
 static INITALIZED: Once = ONCE_INIT;
 
 /// Global Transposition Table
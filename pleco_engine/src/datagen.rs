@@ -0,0 +1,370 @@
+//! Generation of training data from self-play games.
+//!
+//! Each game starts from a few random plies played from the starting position, and is then
+//! played out with a search of a fixed depth or a fixed number of nodes for both sides. Every
+//! position reached is recorded along with the search's score and best move, and the final
+//! result of the game, except for positions that are in check or whose best move is a capture,
+//! as the static evaluation of those positions is a poor match to their score.
+//!
+//! Games are played until checkmate, stalemate, a draw by the fifty-move rule, threefold
+//! repetition or insufficient material, or until the search finds a forced mate. Games reaching
+//! the maximum number of plies are counted as draws.
+//!
+//! Each `Generator` searches with its own transposition table, which is cleared before every
+//! game, so a game is determined entirely by its seed and the search limit.
+//!
+//! # Output formats
+//!
+//! The text format holds one position per line:
+//!
+//! ```text
+//! <fen> | <score> | <best move> | <result>
+//! ```
+//!
+//! The binary format holds one record per position: the length of the FEN as a single byte,
+//! the FEN itself, the score as a little-endian `i16`, the best move as a little-endian `u16`,
+//! and the result as a single byte of `0` for a black win, `1` for a draw or `2` for a white win.
+//!
+//! In both formats, scores are in centipawns and both scores and results are from white's
+//! perspective. Mate scores are clamped to `±MATE_SCORE`.
+
+use std::collections::HashMap;
+use std::io::{self,Read,Write};
+
+use pleco::{Board,BitMove,Player,PieceType};
+use pleco::core::score::*;
+use pleco::tools::prng::PRNG;
+use pleco::tools::pleco_arc::Arc;
+use pleco::tools::tt::TranspositionTable;
+
+use search::Searcher;
+use sync::LockLatch;
+use time::uci_timer::LimitsType;
+
+/// The recorded score of a forced mate, in centipawns.
+pub const MATE_SCORE: i16 = 20000;
+
+/// The result of a game.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    WhiteWin,
+    Draw,
+    BlackWin,
+}
+
+impl GameResult {
+    /// Returns the result as the expected score of white, 1.0 for a win, 0.5 for a draw and
+    /// 0.0 for a loss.
+    pub fn white_score(self) -> f64 {
+        match self {
+            GameResult::WhiteWin => 1.0,
+            GameResult::Draw => 0.5,
+            GameResult::BlackWin => 0.0,
+        }
+    }
+
+    /// Returns the result of a game won by `player`.
+    pub fn win_for(player: Player) -> Self {
+        match player {
+            Player::White => GameResult::WhiteWin,
+            Player::Black => GameResult::BlackWin,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            GameResult::BlackWin => 0,
+            GameResult::Draw => 1,
+            GameResult::WhiteWin => 2,
+        }
+    }
+
+    fn from_u8(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(GameResult::BlackWin),
+            1 => Some(GameResult::Draw),
+            2 => Some(GameResult::WhiteWin),
+            _ => None,
+        }
+    }
+}
+
+/// A position recorded from a game.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Sample {
+    pub fen: String,
+    /// The score of the search, in centipawns from white's perspective.
+    pub score: i16,
+    pub best_move: BitMove,
+    pub result: GameResult,
+}
+
+impl Sample {
+    /// Writes the sample as a line of the text format.
+    pub fn write_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "{} | {} | {} | {:.1}", self.fen, self.score, self.best_move, self.result.white_score())
+    }
+
+    /// Writes the sample as a record of the binary format.
+    pub fn write_binary<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let fen = self.fen.as_bytes();
+        assert!(fen.len() <= u8::max_value() as usize);
+        w.write_all(&[fen.len() as u8])?;
+        w.write_all(fen)?;
+        w.write_all(&self.score.to_le_bytes())?;
+        w.write_all(&self.best_move.get_raw().to_le_bytes())?;
+        w.write_all(&[self.result.to_u8()])
+    }
+
+    /// Reads a record of the binary format, returning `None` at the end of the input.
+    pub fn read_binary<R: Read>(r: &mut R) -> io::Result<Option<Sample>> {
+        let mut len = [0u8; 1];
+        if r.read(&mut len)? == 0 {
+            return Ok(None);
+        }
+        let mut fen = vec![0u8; len[0] as usize];
+        r.read_exact(&mut fen)?;
+        let mut rest = [0u8; 5];
+        r.read_exact(&mut rest)?;
+
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let fen = String::from_utf8(fen).map_err(|_| invalid("invalid fen"))?;
+        let result = GameResult::from_u8(rest[4]).ok_or_else(|| invalid("invalid result"))?;
+        Ok(Some(Sample {
+            fen,
+            score: i16::from_le_bytes([rest[0], rest[1]]),
+            best_move: BitMove::new(u16::from_le_bytes([rest[2], rest[3]])),
+            result,
+        }))
+    }
+}
+
+/// Converts a search score from the perspective of `player` into centipawns from white's
+/// perspective.
+fn white_centipawns(score: Value, player: Player) -> i16 {
+    let cp = if score.abs() >= MATE_IN_MAX_PLY {
+        if score > 0 { MATE_SCORE as i32 } else { -MATE_SCORE as i32 }
+    } else {
+        (score * 100 / PAWN_EG).max(-MATE_SCORE as i32 + 1).min(MATE_SCORE as i32 - 1)
+    };
+    if player == Player::White { cp as i16 } else { -cp as i16 }
+}
+
+/// Returns the seed of a game, mixing in its index so that neighbouring games don't share
+/// openings.
+pub fn game_seed(seed: u64, game: u64) -> u64 {
+    let mut z = seed ^ game.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    // The PRNG never leaves zero.
+    if z == 0 { 1 } else { z }
+}
+
+/// The default size of a `Generator`'s transposition table, in megabytes.
+pub const DEFAULT_HASH_MB: usize = 16;
+
+/// Plays self-play games, one at a time.
+pub struct Generator {
+    searcher: Searcher,
+    tt: TranspositionTable,
+    limit: LimitsType,
+    random_plies: u16,
+    max_plies: u16,
+}
+
+impl Generator {
+    /// Creates a `Generator` searching each move to `limit`, which must be either a depth or
+    /// a node limit.
+    ///
+    /// The `Searcher` is large, so this must be called from a thread with a large stack, after
+    /// the engine's globals have been initialized.
+    pub fn new(limit: LimitsType) -> Self {
+        Generator {
+            searcher: Searcher::new(0, Arc::new(LockLatch::new())),
+            tt: TranspositionTable::new(DEFAULT_HASH_MB),
+            limit,
+            random_plies: 8,
+            max_plies: 400,
+        }
+    }
+
+    /// Sets the size of the transposition table, in megabytes.
+    pub fn hash_size(mut self, mb_size: usize) -> Self {
+        self.tt = TranspositionTable::new(mb_size);
+        self
+    }
+
+    /// Sets the number of random plies played at the start of each game.
+    pub fn random_plies(mut self, plies: u16) -> Self {
+        self.random_plies = plies;
+        self
+    }
+
+    /// Sets the number of plies after which a game is counted as a draw.
+    pub fn max_plies(mut self, plies: u16) -> Self {
+        self.max_plies = plies;
+        self
+    }
+
+    /// Creates the random opening of the game of a seed.
+    pub fn opening(&self, seed: u64) -> Board {
+        let mut prng = PRNG::init(seed);
+        loop {
+            let mut board = Board::start_pos();
+            for _ in 0..self.random_plies {
+                let moves = board.generate_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                board.apply_move(moves[prng.rand() as usize % moves.len()]);
+            }
+            if !board.generate_moves().is_empty() {
+                return board;
+            }
+        }
+    }
+
+    /// Plays the game of a seed, returning the recorded positions.
+    pub fn play(&mut self, seed: u64) -> Vec<Sample> {
+        let mut board = self.opening(seed);
+        self.searcher.clear();
+        // Nothing refers into the table between searches.
+        unsafe {
+            self.tt.clear();
+        }
+
+        let mut positions: Vec<(String, i16, BitMove)> = Vec::new();
+        let mut seen: HashMap<u64, u8> = HashMap::new();
+        let mut plies: u16 = 0;
+        let result = loop {
+            if board.checkmate() {
+                break GameResult::win_for(!board.turn());
+            }
+            if board.stalemate() || board.rule_50() >= 100 || insufficient_material(&board) {
+                break GameResult::Draw;
+            }
+            let count = seen.entry(board.zobrist()).or_insert(0);
+            *count += 1;
+            if *count >= 3 || plies >= self.max_plies {
+                break GameResult::Draw;
+            }
+
+            let (mov, score) = self.searcher.search_standalone(&board, self.limit.clone(), &self.tt);
+            if score.abs() >= MATE_IN_MAX_PLY {
+                let winner = if score > 0 { board.turn() } else { !board.turn() };
+                break GameResult::win_for(winner);
+            }
+            if !board.in_check() && !board.is_capture(mov) {
+                positions.push((board.fen(), white_centipawns(score, board.turn()), mov));
+            }
+
+            board.apply_move(mov);
+            plies += 1;
+        };
+
+        positions.into_iter()
+            .map(|(fen, score, best_move)| Sample { fen, score, best_move, result })
+            .collect()
+    }
+}
+
+// Returns if neither side can possibly checkmate, with only kings and at most a single minor
+// piece left.
+fn insufficient_material(board: &Board) -> bool {
+    let pieces = board.count_all_pieces();
+    pieces == 2 || (pieces == 3
+        && (board.count_piece(Player::White, PieceType::N) + board.count_piece(Player::Black, PieceType::N)
+            + board.count_piece(Player::White, PieceType::B) + board.count_piece(Player::Black, PieceType::B)) == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use consts::init_globals;
+
+    #[test]
+    fn binary_round_trip() {
+        let board = Board::start_pos();
+        let sample = Sample {
+            fen: board.fen(),
+            score: -37,
+            best_move: board.generate_moves()[3],
+            result: GameResult::BlackWin,
+        };
+        let mut bytes = Vec::new();
+        sample.write_binary(&mut bytes).unwrap();
+        sample.write_binary(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 2 * (sample.fen.len() + 6));
+
+        let mut reader = &bytes[..];
+        assert_eq!(Sample::read_binary(&mut reader).unwrap(), Some(sample.clone()));
+        assert_eq!(Sample::read_binary(&mut reader).unwrap(), Some(sample.clone()));
+        assert_eq!(Sample::read_binary(&mut reader).unwrap(), None);
+
+        let mut text = Vec::new();
+        sample.write_text(&mut text).unwrap();
+        assert_eq!(String::from_utf8(text).unwrap(), format!("{} | -37 | {} | 0.0\n", sample.fen, sample.best_move));
+    }
+
+    #[test]
+    fn score_conversion() {
+        assert_eq!(white_centipawns(PAWN_EG, Player::White), 100);
+        assert_eq!(white_centipawns(PAWN_EG, Player::Black), -100);
+        assert_eq!(white_centipawns(MATE - 3, Player::Black), -MATE_SCORE);
+        assert_eq!(white_centipawns(-MATE + 8, Player::Black), MATE_SCORE);
+    }
+
+    #[test]
+    fn insufficient() {
+        assert!(insufficient_material(&Board::from_fen("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap()));
+        assert!(!insufficient_material(&Board::from_fen("4k3/8/8/8/8/8/8/2R1K3 w - - 0 1").unwrap()));
+        assert!(!insufficient_material(&Board::start_pos()));
+    }
+
+    #[test]
+    fn self_play_game() {
+        init_globals();
+        let handle = ::std::thread::Builder::new()
+            .stack_size(16 * 1024 * 1024)
+            .spawn(|| {
+                let mut gen = Generator::new(LimitsType::Depth(2)).max_plies(40);
+                let seed = game_seed(7, 0);
+                assert_eq!(gen.opening(seed).fen(), gen.opening(seed).fen());
+                assert_eq!(gen.opening(seed).moves_played(), 8);
+                assert_ne!(gen.opening(seed).fen(), gen.opening(game_seed(7, 1)).fen());
+
+                let samples = gen.play(seed);
+                assert!(!samples.is_empty());
+                for sample in samples.iter() {
+                    let board = Board::from_fen(&sample.fen).unwrap();
+                    assert!(!board.in_check());
+                    assert!(board.generate_moves().contains(&sample.best_move));
+                    assert!(!board.is_capture(sample.best_move));
+                    assert_eq!(sample.result, samples[0].result);
+                }
+            }).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn games_are_reproducible() {
+        init_globals();
+        // The second game is played after another game by one generator, and on its own by
+        // another generator running at the same time.
+        let play = |seeds: Vec<u64>| {
+            ::std::thread::Builder::new()
+                .stack_size(16 * 1024 * 1024)
+                .spawn(move || {
+                    let mut gen = Generator::new(LimitsType::Depth(4)).max_plies(60);
+                    seeds.into_iter().map(|seed| gen.play(seed)).last().unwrap()
+                }).unwrap()
+        };
+        let after_other = play(vec![game_seed(3, 0), game_seed(3, 1)]);
+        let alone = play(vec![game_seed(3, 1)]);
+        let after_other = after_other.join().unwrap();
+        let alone = alone.join().unwrap();
+        assert!(!alone.is_empty());
+        assert_eq!(after_other, alone);
+    }
+}
@@ -3,16 +3,22 @@
 use std::io;
 use std::sync::atomic::Ordering;
 
-use pleco::Board;
+use pleco::{Board,Player};
+use pleco::core::score::PAWN_EG;
 use pleco::BitMove;
+use pleco::tools::huge_pages;
 
 use time::uci_timer::{PreLimits};
 use uci::options::{OptionsMap,OptionWork};
 use uci::parse;
+use uci::debug;
+use tune;
 use consts::*;
 use threadpool::threadpool;
 
 use search::eval::Evaluation;
+use search::nnue;
+use search::evaluator::{EvaluatorFactory,HandCrafted};
 
 use num_cpus;
 
@@ -32,18 +38,27 @@ enum SearchType {
 pub struct PlecoSearcher {
     options: OptionsMap,
     search_mode: SearchType,
-    board: Board
+    board: Board,
+    hash_file: String
 }
 
 impl PlecoSearcher {
     pub fn init(use_stdout: bool) -> Self {
+        PlecoSearcher::init_with_evaluator(use_stdout, HandCrafted::boxed)
+    }
+
+    /// Creates a `PlecoSearcher` whose threads evaluate positions with evaluators created
+    /// by `evaluator`, rather than with the hand-crafted evaluation.
+    pub fn init_with_evaluator(use_stdout: bool, evaluator: EvaluatorFactory) -> Self {
         init_globals();
         USE_STDOUT.store(use_stdout,Ordering::Relaxed);
+        threadpool().set_evaluator(evaluator);
         threadpool().set_thread_count(num_cpus::get().min(MAX_THREADS).max(1));
         PlecoSearcher {
             options: OptionsMap::new(),
             search_mode: SearchType::None,
-            board: Board::start_pos()
+            board: Board::start_pos(),
+            hash_file: DEFAULT_HASH_FILE.to_string()
         }
     }
 
@@ -82,7 +97,38 @@ impl PlecoSearcher {
                     break;
                 },
                 "stop" => self.halt(),
-                "eval" => Evaluation::trace(&self.board),
+                "eval" => {
+                    if args.get(1) == Some(&"json") {
+                        println!("{}", Evaluation::trace_json(&self.board));
+                    } else {
+                        print!("{}", (threadpool().evaluator())().trace(&self.board));
+                        if let Some(net) = nnue::network() {
+                            let mut v = net.evaluate_board(&self.board);
+                            if self.board.turn() == Player::Black {
+                                v = -v;
+                            }
+                            println!("NNUE evaluation:  {:6.3}  (white side)", v as f64 / PAWN_EG as f64);
+                        }
+                    }
+                },
+                "d" => print!("{}", debug::display_board(&self.board)),
+                "key" => print!("{}", debug::display_keys(&self.board)),
+                "moves" => print!("{}", debug::display_moves(&self.board)),
+                "flip" => self.board = self.board.flip_colors(),
+                "tune" => {
+                    if args.get(1) == Some(&"spsa") {
+                        print!("{}", tune::spsa_params());
+                    } else {
+                        print!("{}", tune::rust_source());
+                    }
+                },
+                "debug" => {
+                    match args.get(1) {
+                        Some(&"on") => DEBUG.store(true, Ordering::Relaxed),
+                        Some(&"off") => DEBUG.store(false, Ordering::Relaxed),
+                        _ => println!("debug [on | off]")
+                    }
+                },
                 _ => print!("Unknown Command: {}",full_command)
             }
             self.apply_all_options();
@@ -96,6 +142,7 @@ impl PlecoSearcher {
 
     fn uci_go(&mut self, args: &[&str]) {
         let limit = parse::parse_time(&args);
+        self.debug_info(&format!("go {} from {}", args.join(" "), self.board.fen()));
         threadpool().uci_search(&self.board, &limit.create())
     }
 
@@ -141,6 +188,7 @@ impl PlecoSearcher {
         if !self.options.apply_option(&name, &value) {
             println!("unable to apply option: '{}'", full_command);
         } else {
+            self.debug_info(&format!("option '{}' set to '{}'", name, value));
             self.apply_all_options();
         }
     }
@@ -153,7 +201,22 @@ impl PlecoSearcher {
                 match work {
                     OptionWork::ClearTT => {self.clear_tt()},
                     OptionWork::ResizeTT(mb) => {self.resize_tt(mb)},
-                    OptionWork::Threads(num) => {threadpool().set_thread_count(num)}
+                    OptionWork::Threads(num) => {threadpool().set_thread_count(num)},
+                    OptionWork::Contempt(ct) => {CONTEMPT.store(ct, Ordering::Relaxed)},
+                    OptionWork::AnalysisContempt(mode) => {ANALYSIS_CONTEMPT.store(mode as u8, Ordering::Relaxed)},
+                    OptionWork::AnalyseMode(analyse) => {ANALYSE_MODE.store(analyse, Ordering::Relaxed)},
+                    OptionWork::ShowWDL(show) => {SHOW_WDL.store(show, Ordering::Relaxed)},
+                    OptionWork::EvalFile(path) => {self.load_network(&path)},
+                    OptionWork::UseNNUE(use_nnue) => {USE_NNUE.store(use_nnue, Ordering::Relaxed)},
+                    OptionWork::HashFile(path) => {self.hash_file = path},
+                    OptionWork::SaveHash => {self.save_tt()},
+                    OptionWork::LoadHash => {self.load_tt()},
+                    OptionWork::MoveOverhead(ms) => {MOVE_OVERHEAD.store(ms, Ordering::Relaxed)},
+                    OptionWork::SlowMover(pct) => {SLOW_MOVER.store(pct, Ordering::Relaxed)},
+                    OptionWork::MinThinkingTime(ms) => {MIN_THINKING_TIME.store(ms, Ordering::Relaxed)},
+                    OptionWork::MoveHorizon(moves) => {MOVE_HORIZON.store(moves, Ordering::Relaxed)},
+                    OptionWork::NodesTime(nodes) => {NODES_TIME.store(nodes, Ordering::Relaxed)},
+                    OptionWork::Tune(param, value) => {param.set(value)}
                 }
             }
         }
@@ -163,6 +226,7 @@ impl PlecoSearcher {
         println!("id name {}",ID_NAME);
         println!("id authors {}", ID_AUTHORS);
         self.options.display_all();
+        self.report_huge_pages();
         println!("uciok");
     }
 
@@ -203,16 +267,72 @@ impl PlecoSearcher {
         true
     }
 
+    // Sends an info string to the GUI if the debug mode is on.
+    fn debug_info(&self, msg: &str) {
+        if DEBUG.load(Ordering::Relaxed) {
+            println!("info string {}", msg);
+        }
+    }
+
     pub fn hash_percent(&self) -> f64 {
         tt().hash_percent()
     }
 
     pub fn clear_tt(&mut self) {
-        unsafe {tt().clear() };
+        threadpool().clear_tt();
     }
 
     pub fn resize_tt(&mut self, mb: usize) {
-        unsafe {tt().resize_to_megabytes(mb)};
+        threadpool().wait_for_finish();
+        let size = unsafe {tt().resize_to_megabytes_uncleared(mb)};
+        threadpool().clear_tt();
+        if size < tt().resized_megabytes(mb) {
+            println!("info string unable to allocate {} MB of hash, using {} MB", mb, size);
+        }
+        self.report_huge_pages();
+    }
+
+    // Reports whether the transposition table obtained huge pages, if they are requested.
+    fn report_huge_pages(&self) {
+        if !huge_pages::supported() {
+            return;
+        }
+        let huge_mb = tt().huge_page_bytes() / (1024 * 1024);
+        if huge_mb > 0 {
+            println!("info string hash {} MB, {} MiB in huge pages", tt().size_megabytes(), huge_mb);
+        } else {
+            println!("info string hash {} MB, huge pages unavailable", tt().size_megabytes());
+        }
+    }
+
+    /// Saves the transposition table to the file set through `Hash File`.
+    pub fn save_tt(&mut self) {
+        match tt().save(&self.hash_file) {
+            Ok(()) => println!("info string saved hash to {}", self.hash_file),
+            Err(e) => println!("info string unable to save hash to {}: {:?}", self.hash_file, e),
+        }
+    }
+
+    /// Loads the transposition table from the file set through `Hash File`. Files saved with a
+    /// different `Hash` size are refused, so the size always matches the option.
+    pub fn load_tt(&mut self) {
+        threadpool().wait_for_finish();
+        match unsafe { tt().load(&self.hash_file) } {
+            Ok(()) => println!("info string loaded hash from {}, {} MB", self.hash_file, tt().size_megabytes()),
+            Err(e) => println!("info string unable to load hash from {}: {:?}", self.hash_file, e),
+        }
+    }
+
+    /// Loads the network used when `Use NNUE` is set. `<empty>` unloads the current network.
+    pub fn load_network(&mut self, path: &str) {
+        if path == "<empty>" {
+            nnue::set_network(None);
+            return;
+        }
+        match nnue::load_network(path) {
+            Ok(()) => println!("info string loaded network {}", path),
+            Err(e) => println!("info string unable to load network {}: {}", path, e),
+        }
     }
 
     pub fn use_stdout(&mut self, stdout: bool) {
@@ -30,7 +30,6 @@
 extern crate num_cpus;
 extern crate rand;
 extern crate pleco;
-extern crate chrono;
 extern crate prefetch;
 
 pub mod threadpool;
@@ -43,5 +42,7 @@ pub mod movepick;
 pub mod tables;
 pub mod engine;
 pub mod search;
+pub mod tune;
+pub mod datagen;
 
 pub use consts::*;
\ No newline at end of file
@@ -280,7 +280,11 @@ impl MovePicker {
                 while self.cur_ptr < self.end_ptr {
                     mov = self.pick_best(self.cur_ptr, self.end_ptr);
                     unsafe {self.cur_ptr = self.cur_ptr.add(1);}
-                    if mov.bit_move != self.ttm && mov.score > -128 {
+                    // The TT move has already been returned, so it isn't kept as a bad capture.
+                    if mov.bit_move == self.ttm {
+                        continue;
+                    }
+                    if mov.score > -128 {
                         let previous_val = unsafe {
                             (*self.cur_ptr.sub(1)).score as i32
                         };
@@ -690,6 +694,14 @@ mod tests {
 
 
 
+    #[test]
+    fn movepick_capture_ttm() {
+        // A capturing TT move isn't returned again alongside the bad captures.
+        let b = Board::from_fen("rnbqkbnr/p4p1p/2pp4/1p2p1p1/N1P1P2P/3P4/PP3PP1/R1BQKBNR b KQkq - 0 6").unwrap();
+        let ttm = *b.generate_moves().iter().find(|m| m.to_string() == "b5a4").unwrap();
+        movepick_main_search(b, ttm, &[BitMove::null(); 2], BitMove::null(), 5);
+    }
+
     fn movepick_rand_one(b: Board) {
         let ttm = BitMove::new(rand::random());
         let cm = BitMove::new(rand::random());
@@ -14,6 +14,7 @@ use pleco::helper::prelude::*;
 
 use tables::pawn_table::{PawnEntry, PawnTable};
 use tables::material::*;
+use tune;
 
 const CENTER: BitBoard = BitBoard((FILE_D | FILE_E) & (RANK_4 | RANK_5));
 const QUEEN_SIDE: BitBoard = BitBoard(FILE_A | FILE_B | FILE_C | FILE_D);
@@ -127,25 +128,20 @@ const TRAPPED_ROOK          : Score = Score( 92,  0);
 const WEAK_QUEEN            : Score = Score( 50, 10);
 const CLOSE_ENEMIES         : Score = Score(  7,  0);
 const PAWNLESS_FLANK        : Score = Score( 20, 80);
-const THREAT_BY_SAFE_PAWN     : Score = Score(192,175);
-const THREAT_BY_RANK         : Score = Score( 16,  3);
-const HANGING              : Score = Score( 48, 27);
 const WEAK_UNOPOSSED_PAWN    : Score = Score(  5, 25);
-const SLIDER_ON_QUEEN:          Score = Score(42, 21);
-const THREAT_BY_PAWN_PUSH     : Score = Score( 38, 22);
 const THREAT_BY_ATTACK_ON_QUEEN : Score = Score( 38, 22);
 const HINDER_PASSED_PAWN     : Score = Score(  7,  0);
 const TRAPPED_BISHOP_A1H1    : Score = Score( 50, 50);
 
-
-// Penalties for enemy's safe checks
-const QUEEN_SAFE_CHECK: i32  = 780;
-const ROOK_SAFE_CHECK: i32   = 880;
-const BISHOP_SAFE_CHECK: i32 = 435;
-const KNIGHT_SAFE_CHECK: i32 = 790;
+// The penalties for enemy's safe checks, the king danger weights, and the threat bonuses
+// THREAT_BY_SAFE_PAWN, THREAT_BY_RANK, HANGING, SLIDER_ON_QUEEN and THREAT_BY_PAWN_PUSH
+// are tunable, and found in the `tune` module.
 
 
+// The evaluation returns early with only the material, PSQT and pawn terms when they are
+// beyond LAZY_THRESHOLD, or beyond LAZY_MARGIN outside of the search window.
 const LAZY_THRESHOLD: Value = 1500;
+const LAZY_MARGIN: Value = 900;
 const SPACE_THRESHOLD: Value = 12222;
 
 #[repr(u8)]
@@ -171,7 +167,10 @@ const EVAL_PASSES_CNT: usize = 16;
 
 struct Tracer {
     a: [[Score; EVAL_PASSES_CNT]; PLAYER_CNT],
+    // If the terms were computed, rather than a specialized endgame evaluation or a lazy one.
     used: bool,
+    // If the evaluation returned early from lazy evaluation.
+    lazy: bool,
 }
 
 struct PassScore {
@@ -195,7 +194,8 @@ impl Tracer {
     pub fn new() -> Self {
         Tracer {
             a: unsafe {mem::zeroed()},
-            used: true
+            used: true,
+            lazy: false,
         }
     }
     pub fn add_piece(&mut self, piece: PieceType, player: Player, score: Score) {
@@ -225,6 +225,52 @@ impl Tracer {
 
 }
 
+impl PassScore {
+    fn json(&self) -> String {
+        let total = self.score_white - self.score_black;
+        match self.pass {
+            EvalPasses::Material | EvalPasses::Imbalance |
+            EvalPasses::Initiative | EvalPasses::Total =>
+                format!("{{\"total\":{}}}", score_json(total)),
+            _ => format!("{{\"white\":{},\"black\":{},\"total\":{}}}",
+                         score_json(self.score_white), score_json(self.score_black), score_json(total))
+        }
+    }
+}
+
+fn score_json(score: Score) -> String {
+    format!("{{\"mg\":{},\"eg\":{}}}", score.mg(), score.eg())
+}
+
+impl Tracer {
+    /// The name of each traced term, in the order they are displayed.
+    const TERMS: [(&'static str, EvalPasses); 14] = [
+        ("material", EvalPasses::Material),
+        ("imbalance", EvalPasses::Imbalance),
+        ("initiative", EvalPasses::Initiative),
+        ("pawns", EvalPasses::Pawn),
+        ("knights", EvalPasses::Knight),
+        ("bishops", EvalPasses::Bishop),
+        ("rooks", EvalPasses::Rook),
+        ("queens", EvalPasses::Queen),
+        ("mobility", EvalPasses::Mobility),
+        ("king_safety", EvalPasses::King),
+        ("threats", EvalPasses::Threat),
+        ("passed", EvalPasses::Passed),
+        ("space", EvalPasses::Space),
+        ("total", EvalPasses::Total),
+    ];
+
+    /// Creates a JSON object of each term, alongside the final evaluation (white side).
+    pub fn json(&self, final_eval: Value) -> String {
+        let terms: Vec<String> = Tracer::TERMS.iter()
+            .map(|&(name, pass)| format!("\"{}\":{}", name, self.term(pass).json()))
+            .collect();
+        format!("{{\"terms\":{{{}}},\"eval\":{},\"lazy\":{}}}",
+                terms.join(","), final_eval, self.lazy)
+    }
+}
+
 impl fmt::Display for Tracer {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "     Term    |    White    |    Black    |    Total   ")?;
@@ -286,32 +332,69 @@ pub struct Evaluation {}
 
 impl Evaluation {
     pub fn evaluate(board: &Board, pawn_table: &mut PawnTable, material: &mut Material) -> Value {
+        Evaluation::evaluate_with_contempt(board, pawn_table, material, Score::ZERO)
+    }
+
+    /// Evaluates the board with a contempt `Score` (from white's perspective) added to the
+    /// general evaluation, before the initiative and scaling are applied.
+    pub fn evaluate_with_contempt(board: &Board, pawn_table: &mut PawnTable, material: &mut Material,
+                                  contempt: Score) -> Value {
+        Evaluation::evaluate_in_window(board, pawn_table, material, contempt, NEG_INFINITE, INFINITE).0
+    }
+
+    /// Evaluates the board with a contempt `Score` (from white's perspective), returning early
+    /// with a rough evaluation when it's far outside of the `alpha`, `beta` window (from the
+    /// side to move's perspective). Also returns if the evaluation was lazy.
+    pub fn evaluate_in_window(board: &Board, pawn_table: &mut PawnTable, material: &mut Material,
+                              contempt: Score, alpha: Value, beta: Value) -> (Value, bool) {
         let pawn_entry = { pawn_table.probe(&board) };
         let material_entry = { material.probe(&board) };
         let mut no_trace = NoTrace::new();
-        let mut eval = EvaluationInner::<NoTrace>::new(board, pawn_entry, material_entry, &mut no_trace);
-        eval.value()
+        let mut eval = EvaluationInner::<NoTrace>::new(board, pawn_entry, material_entry, contempt, &mut no_trace);
+        let value = eval.value(alpha, beta);
+        (value, eval.lazy)
     }
 
+    /// Prints a table of each term of the evaluation.
     pub fn trace(board: &Board) {
+        print!("{}", Evaluation::trace_string(board));
+    }
+
+    /// Returns a table of each term of the evaluation, followed by the total.
+    pub fn trace_string(board: &Board) -> String {
+        let (trace, total) = Evaluation::traced(board);
+        let note = if trace.lazy {
+            " (lazy)"
+        } else if !trace.used {
+            " (specialized)"
+        } else {
+            ""
+        };
+        format!("{}Total evaluation: {:6.3}  (white side){}\n", trace, total as f64 / PAWN_EG as f64, note)
+    }
+
+    /// Returns each term of the evaluation as a JSON object. Scores are given in internal units,
+    /// with the final evaluation from white's side.
+    pub fn trace_json(board: &Board) -> String {
+        let (trace, total) = Evaluation::traced(board);
+        trace.json(total)
+    }
+
+    // Evaluates the board with tracing, returning the trace and the evaluation from white's side.
+    fn traced(board: &Board) -> (Tracer, Value) {
         let mut pawn_table = PawnTable::new();
         let mut material = Material::new();
         let pawn_entry = { pawn_table.probe(&board) };
         let material_entry = { material.probe(&board) };
         let mut trace = Trace::new();
         let mut total = {
-            let mut eval = EvaluationInner::<Trace>::new(board, pawn_entry, material_entry, &mut trace);
-            eval.value()
+            let mut eval = EvaluationInner::<Trace>::new(board, pawn_entry, material_entry, Score::ZERO, &mut trace);
+            eval.value(NEG_INFINITE, INFINITE)
         };
         if board.turn() == Player::Black {
             total = -total;
         }
-        print!("{}", trace.t);
-        if trace.t.used {
-            println!("Total evaluation: {:6.3}  (white side)", total as f64 / PAWN_EG as f64);
-        } else {
-            println!("Total evaluation: {:6.3}  (white side) (lazy)", total as f64 / PAWN_EG as f64);
-        }
+        (trace.t, total)
     }
 }
 
@@ -320,6 +403,7 @@ struct EvaluationInner<'a, 'b, T: 'b + Tracing> {
     board: &'a Board,
     pawn_entry: &'a mut PawnEntry,
     material_entry: &'a mut MaterialEntry,
+    contempt: Score,
     trace: &'b mut T,
     king_ring: [BitBoard; PLAYER_CNT],
     mobility_area: [BitBoard; PLAYER_CNT],
@@ -329,18 +413,21 @@ struct EvaluationInner<'a, 'b, T: 'b + Tracing> {
     king_attackers_count: [u8; PLAYER_CNT],
     king_attackers_weight: [i32; PLAYER_CNT],
     king_adjacent_zone_attacks_count: [i32; PLAYER_CNT],
+    lazy: bool,
 }
 
 impl <'a, 'b, T: Tracing> EvaluationInner<'a, 'b, T>  {
     fn new(board: &'a Board,
            pawn_entry: &'a mut PawnEntry,
            material_entry: &'a mut MaterialEntry,
+           contempt: Score,
            trace: &'b mut T
         ) -> Self {
         EvaluationInner {
             board,
             pawn_entry,
             material_entry,
+            contempt,
             trace,
             king_ring: [BitBoard(0); PLAYER_CNT],
             mobility_area: [BitBoard(0); PLAYER_CNT],
@@ -350,27 +437,38 @@ impl <'a, 'b, T: Tracing> EvaluationInner<'a, 'b, T>  {
             king_attackers_count: [0; PLAYER_CNT],
             king_attackers_weight: [0; PLAYER_CNT],
             king_adjacent_zone_attacks_count: [0; PLAYER_CNT],
+            lazy: false,
         }
     }
 
-    fn value(&mut self) -> Value {
+    // Evaluates the board from the side to move, with `alpha` and `beta` being the search
+    // window from the side to move.
+    fn value(&mut self, alpha: Value, beta: Value) -> Value {
+        // Known endgames have their own evaluation function.
+        if let Some(v) = self.material_entry.specialized_eval(self.board) {
+            if let Some(trace) = self.trace.trace() {
+                trace.used = false;
+            }
+            return v;
+        }
+
         let mut score = self.pawn_entry.pawns_score(Player::White)
             - self.pawn_entry.pawns_score(Player::Black)
             + self.material_entry.score()
-            + self.board.psq();
+            + self.board.psq()
+            + self.contempt;
 
         let mut v: i32 = (score.0 + score.1) / 2;
-        if v.abs() > LAZY_THRESHOLD {
+        let v_us: i32 = if self.board.turn() == Player::White { v } else { -v };
+        if v.abs() > LAZY_THRESHOLD
+            || v_us >= beta.saturating_add(LAZY_MARGIN)
+            || v_us <= alpha.saturating_sub(LAZY_MARGIN) {
+            self.lazy = true;
             if let Some(trace) = self.trace.trace() {
                 trace.used = false;
+                trace.lazy = true;
             }
-
-            if self.board.turn() == Player::White {
-                return v;
-            }
-            else {
-                return -v;
-            }
+            return v_us;
         }
 
         self.initialize::<WhiteType>();
@@ -457,10 +555,11 @@ impl <'a, 'b, T: Tracing> EvaluationInner<'a, 'b, T>  {
                 self.king_ring[us as usize] |= P::shift_up(b);
             }
 
+            // Extend the ring towards the center, regardless of the side.
             if ksq_us.file() == File::H {
-                self.king_ring[us as usize] |= P::shift_left(b);
+                self.king_ring[us as usize] |= WhiteType::shift_left(b);
             } else if ksq_us.file() == File::A {
-                self.king_ring[us as usize] |= P::shift_right(b);
+                self.king_ring[us as usize] |= WhiteType::shift_right(b);
             }
 
 
@@ -628,7 +727,7 @@ impl <'a, 'b, T: Tracing> EvaluationInner<'a, 'b, T>  {
             // Enemy queen safe checks
             if ((b1 | b2) & self.attacked_by[them as usize][PieceType::Q as usize] & safe_b
                 & !self.attacked_by[us as usize][PieceType::Q as usize]).is_not_empty() {
-                king_danger += QUEEN_SAFE_CHECK;
+                king_danger += tune::QUEEN_SAFE_CHECK.get();
             }
 
             b1 &= self.attacked_by[them as usize][PieceType::R as usize];
@@ -636,14 +735,14 @@ impl <'a, 'b, T: Tracing> EvaluationInner<'a, 'b, T>  {
 
             // Enemy rook checks
             if (b1 & safe_b).is_not_empty() {
-                king_danger += ROOK_SAFE_CHECK;
+                king_danger += tune::ROOK_SAFE_CHECK.get();
             } else {
                 unsafe_checks |= b1;
             }
 
             // Enemy bishops checks
             if (b2 & safe_b).is_not_empty() {
-                king_danger += BISHOP_SAFE_CHECK;
+                king_danger += tune::BISHOP_SAFE_CHECK.get();
             } else {
                 unsafe_checks |= b2;
             }
@@ -651,7 +750,7 @@ impl <'a, 'b, T: Tracing> EvaluationInner<'a, 'b, T>  {
             // Enemy knights checks
             b = knight_moves(ksq_us) & self.attacked_by[them as usize][PieceType::N as usize];
             if (b & safe_b).is_not_empty() {
-                king_danger += KNIGHT_SAFE_CHECK;
+                king_danger += tune::KNIGHT_SAFE_CHECK.get();
             } else {
                 unsafe_checks |= b;
             }
@@ -663,10 +762,10 @@ impl <'a, 'b, T: Tracing> EvaluationInner<'a, 'b, T>  {
             pinned = self.board.all_pinned_pieces(us) & self.board.get_occupied_player(us);
 
             king_danger +=        self.king_attackers_count[them as usize] as i32 * self.king_attackers_weight[them as usize];
-            king_danger += 102 *  self.king_adjacent_zone_attacks_count[them as usize];
-            king_danger += 191 * (self.king_ring[us as usize] & weak).count_bits() as i32;
-            king_danger += 848 * (pinned | unsafe_checks).count_bits() as i32;
-            king_danger -= 848 * (self.board.count_piece(them, PieceType::Q) != 0) as i32;
+            king_danger += tune::KING_ZONE_ATTACK_WEIGHT.get() * self.king_adjacent_zone_attacks_count[them as usize];
+            king_danger += tune::KING_RING_WEAK_WEIGHT.get() * (self.king_ring[us as usize] & weak).count_bits() as i32;
+            king_danger += tune::KING_PINNED_CHECKS_WEIGHT.get() * (pinned | unsafe_checks).count_bits() as i32;
+            king_danger -= tune::KING_QUEEN_WEIGHT.get() * (self.board.count_piece(them, PieceType::Q) != 0) as i32;
             king_danger -=   9 * score.mg() as i32 / 8;
             king_danger +=  40;
 
@@ -723,7 +822,7 @@ impl <'a, 'b, T: Tracing> EvaluationInner<'a, 'b, T>  {
                 & (!self.attacked_by[them as usize][PieceType::All as usize]
                     | self.attacked_by[them as usize][PieceType::P as usize]);
             safe_threats = (P::shift_up_right(b) | P::shift_up_left(b)) & weak;
-            score += THREAT_BY_SAFE_PAWN * safe_threats.count_bits();
+            score += tune::THREAT_BY_SAFE_PAWN.get() * safe_threats.count_bits();
         }
 
         // Squares strongly protected by the opponent, either because they attack the
@@ -744,7 +843,7 @@ impl <'a, 'b, T: Tracing> EvaluationInner<'a, 'b, T>  {
                 let piece = self.board.piece_at_sq(s).type_of();
                 score += THREAT_BY_MINOR[piece as usize];
                 if piece != PieceType::P {
-                    score += THREAT_BY_RANK * them.relative_rank_of_sq(s) as u8;
+                    score += tune::THREAT_BY_RANK.get() * them.relative_rank_of_sq(s) as u8;
                 }
             }
 
@@ -753,11 +852,11 @@ impl <'a, 'b, T: Tracing> EvaluationInner<'a, 'b, T>  {
                 let piece = self.board.piece_at_sq(s).type_of();
                 score += THREAT_BY_ROOK[piece as usize];
                 if piece != PieceType::P {
-                    score += THREAT_BY_RANK * them.relative_rank_of_sq(s) as u8;
+                    score += tune::THREAT_BY_RANK.get() * them.relative_rank_of_sq(s) as u8;
                 }
             }
 
-            score += HANGING * (weak & !self.attacked_by[them as usize][PieceType::All as usize]).count_bits();
+            score += tune::HANGING.get() * (weak & !self.attacked_by[them as usize][PieceType::All as usize]).count_bits();
 
             b = weak & self.attacked_by[us as usize][PieceType::K as usize];
             if b.is_not_empty() {
@@ -784,7 +883,7 @@ impl <'a, 'b, T: Tracing> EvaluationInner<'a, 'b, T>  {
             & self.board.get_occupied_player(them)
             & !self.attacked_by[us as usize][PieceType::P as usize];
 
-        score += THREAT_BY_PAWN_PUSH * b.count_bits();
+        score += tune::THREAT_BY_PAWN_PUSH.get() * b.count_bits();
 
         if self.board.count_piece(them, PieceType::Q) == 1 {
             let mut opp_quens = self.board.piece_bb(them, PieceType::Q);
@@ -795,7 +894,7 @@ impl <'a, 'b, T: Tracing> EvaluationInner<'a, 'b, T>  {
                 b = (self.attacked_by[us as usize][PieceType::B as usize] & bishop_moves(occ_all, s))
                 | (self.attacked_by[us as usize][PieceType::R as usize] & rook_moves(occ_all, s));
 
-                score += SLIDER_ON_QUEEN * (b * safe_threats & self.attacked_by2[us as usize]).count_bits();
+                score += tune::SLIDER_ON_QUEEN.get() * (b & safe_threats & self.attacked_by2[us as usize]).count_bits();
             }
         }
 
@@ -995,7 +1094,7 @@ impl <'a, 'b, T: Tracing> EvaluationInner<'a, 'b, T>  {
             Player::Black
         };
 
-        let mut sf = self.material_entry.scale_factor(strong_side);
+        let mut sf = self.material_entry.scale_factor(self.board, strong_side);
 
         // If we don't already have an unusual scale factor, check for certain
         // types of endgames, and use a lower scale for those.
@@ -1090,5 +1189,48 @@ mod tests {
         Evaluation::trace(&board);
     }
 
+    #[test]
+    fn trace_json() {
+        let board = Board::from_fen("rnbqk1nr/pppp1ppp/8/4p3/1b2P3/P7/1PPP1PPP/RNBQKBNR b KQkq - 0 3").unwrap();
+        let json = Evaluation::trace_json(&board);
+        assert!(json.starts_with("{\"terms\":{\"material\":{\"total\":{\"mg\":"));
+        assert!(json.contains("\"king_safety\":{\"white\":{\"mg\":"));
+        assert_eq!(json.matches('{').count(), json.matches('}').count());
+
+        let eval = Evaluation::evaluate(&board, &mut PawnTable::new(), &mut Material::new());
+        assert!(json.ends_with(&format!("\"eval\":{},\"lazy\":false}}", -eval)));
+    }
+
+    #[test]
+    fn lazy_trace() {
+        // Far enough ahead to be lazy with any window.
+        let board = Board::from_fen("4k3/pppp4/8/8/8/8/PPPP4/QR2K3 w - - 0 1").unwrap();
+        assert!(Evaluation::trace_json(&board).ends_with("\"lazy\":true}"));
+        assert!(Evaluation::trace_string(&board).ends_with("(white side) (lazy)\n"));
+
+        // Lazy only when outside of the window.
+        let board = Board::from_fen("4k3/pppp4/8/8/8/8/PPPP4/R3K3 b - - 0 1").unwrap();
+        let (full, lazy) = Evaluation::evaluate_in_window(&board, &mut PawnTable::new(), &mut Material::new(),
+                                                          Score::ZERO, NEG_INFINITE, INFINITE);
+        assert!(!lazy);
+        let (value, lazy) = Evaluation::evaluate_in_window(&board, &mut PawnTable::new(), &mut Material::new(),
+                                                           Score::ZERO, full - 50, full + 50);
+        assert!(!lazy);
+        assert_eq!(value, full);
+        let (value, lazy) = Evaluation::evaluate_in_window(&board, &mut PawnTable::new(), &mut Material::new(),
+                                                           Score::ZERO, 1200, 1300);
+        assert!(lazy);
+        assert!(value < 1200 - LAZY_MARGIN);
+    }
 
+    #[test]
+    fn eval_color_symmetry() {
+        let boards = Board::random().pseudo_random(0x5EED_0E7A).many(60);
+        for board in boards.iter() {
+            let flipped = board.flip_colors();
+            let eval = Evaluation::evaluate(board, &mut PawnTable::new(), &mut Material::new());
+            let flipped_eval = Evaluation::evaluate(&flipped, &mut PawnTable::new(), &mut Material::new());
+            assert_eq!(eval, flipped_eval, "\nfen: {}\nflipped: {}", board.fen(), flipped.fen());
+        }
+    }
 }
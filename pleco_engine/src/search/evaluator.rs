@@ -0,0 +1,186 @@
+//! The `Evaluator` trait, allowing the search to be run with evaluation functions other than
+//! the hand-crafted one in `search::eval`.
+//!
+//! Each search thread owns its own `Evaluator`, created through an `EvaluatorFactory` set on
+//! the thread pool, so an evaluator is free to keep per-thread caches without synchronization.
+
+use pleco::{Board,BitMove};
+use pleco::core::score::*;
+use pleco::tools::PreFetchable;
+
+use tables::pawn_table::PawnTable;
+use tables::material::Material;
+use tables::eval_cache::EvalCache;
+
+use super::eval::Evaluation;
+
+/// An evaluation function used by the search.
+pub trait Evaluator: Send {
+    /// Evaluates the board from the perspective of the side to move.
+    fn evaluate(&mut self, board: &Board) -> Value;
+
+    /// Evaluates the board from the perspective of the side to move, allowing for a rough
+    /// evaluation when it's far outside of the `alpha`, `beta` window. Also returns if the
+    /// evaluation was rough, in which case it mustn't be stored as the position's static
+    /// evaluation. Defaults to a full evaluation.
+    fn evaluate_in_window(&mut self, board: &Board, _alpha: Value, _beta: Value) -> (Value, bool) {
+        (self.evaluate(board), false)
+    }
+
+    /// Returns a human readable breakdown of the evaluation of `board`.
+    fn trace(&mut self, board: &Board) -> String;
+
+    /// Called after `mov` has been applied to `board` during the search.
+    fn on_move(&mut self, _board: &Board, _mov: BitMove) {}
+
+    /// Called after the last applied move has been undone, with `board` now at the
+    /// position before that move.
+    fn on_undo(&mut self, _board: &Board) {}
+
+    /// Hints that `board` is likely to be evaluated soon, allowing any cached entries to be
+    /// brought into the cache ahead of time.
+    fn prefetch(&self, _board: &Board) {}
+
+    /// Sets a contempt `Score` (from white's perspective) to add to the evaluation.
+    /// Ignored unless overridden.
+    fn set_contempt(&mut self, _contempt: Score) {}
+
+    /// Clears any cached information, such as when a new game starts.
+    fn clear(&mut self) {}
+}
+
+/// Creates the `Evaluator` of a single search thread.
+pub type EvaluatorFactory = fn() -> Box<Evaluator>;
+
+/// The default `Evaluator`, the hand-crafted `Evaluation` with its pawn and material tables,
+/// and a cache of full evaluations.
+pub struct HandCrafted {
+    pawns: PawnTable,
+    material: Material,
+    cache: EvalCache,
+    contempt: Score,
+    // Mixed into the key of each cached evaluation, as they depend on the contempt.
+    contempt_key: u64,
+}
+
+impl HandCrafted {
+    pub fn new() -> Self {
+        HandCrafted {
+            pawns: PawnTable::new(),
+            material: Material::new(),
+            cache: EvalCache::new(),
+            contempt: Score::ZERO,
+            contempt_key: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn cache_key(&self, board: &Board) -> u64 {
+        board.zobrist() ^ self.contempt_key
+    }
+
+    /// Creates a boxed `HandCrafted`, for use as an `EvaluatorFactory`.
+    pub fn boxed() -> Box<Evaluator> {
+        Box::new(HandCrafted::new())
+    }
+}
+
+impl Evaluator for HandCrafted {
+    fn evaluate(&mut self, board: &Board) -> Value {
+        self.evaluate_in_window(board, NEG_INFINITE, INFINITE).0
+    }
+
+    fn evaluate_in_window(&mut self, board: &Board, alpha: Value, beta: Value) -> (Value, bool) {
+        let key = self.cache_key(board);
+        if let Some(value) = self.cache.probe(key) {
+            return (value, false);
+        }
+        let (value, lazy) = Evaluation::evaluate_in_window(board, &mut self.pawns, &mut self.material,
+                                                           self.contempt, alpha, beta);
+        if !lazy {
+            self.cache.store(key, value);
+        }
+        (value, lazy)
+    }
+
+    fn trace(&mut self, board: &Board) -> String {
+        Evaluation::trace_string(board)
+    }
+
+    #[inline]
+    fn prefetch(&self, board: &Board) {
+        self.pawns.prefetch2(board.pawn_key());
+        self.material.prefetch(board.material_key());
+        self.cache.prefetch(self.cache_key(board));
+    }
+
+    fn set_contempt(&mut self, contempt: Score) {
+        self.contempt = contempt;
+        self.contempt_key = ((contempt.mg() as u32 as u64) << 32 | contempt.eg() as u32 as u64)
+            .wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    }
+
+    fn clear(&mut self) {
+        self.pawns.clear();
+        self.material.clear();
+        self.cache.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Counts non-pawn material only.
+    struct MaterialOnly;
+
+    impl Evaluator for MaterialOnly {
+        fn evaluate(&mut self, board: &Board) -> Value {
+            let us = board.turn();
+            board.non_pawn_material(us) - board.non_pawn_material(!us)
+        }
+
+        fn trace(&mut self, board: &Board) -> String {
+            format!("{}\n", self.evaluate(board))
+        }
+    }
+
+    #[test]
+    fn hand_crafted_matches_evaluation() {
+        let board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        let mut eval = HandCrafted::boxed();
+        let expected = Evaluation::evaluate(&board, &mut PawnTable::new(), &mut Material::new());
+        assert_eq!(eval.evaluate(&board), expected);
+        assert!(eval.trace(&board).contains("Total evaluation"));
+
+        // Cached, and then keyed by the contempt.
+        assert_eq!(eval.evaluate(&board), expected);
+        eval.set_contempt(Score(40, 20));
+        let with_contempt = Evaluation::evaluate_with_contempt(&board, &mut PawnTable::new(),
+                                                               &mut Material::new(), Score(40, 20));
+        assert_eq!(eval.evaluate(&board), with_contempt);
+        assert_ne!(with_contempt, expected);
+    }
+
+    #[test]
+    fn lazy_evaluations_not_cached() {
+        // White is a rook up, but not by enough to be lazy without a window.
+        let board = Board::from_fen("4k3/pppp4/8/8/8/8/PPPP4/R3K3 w - - 0 1").unwrap();
+        let full = Evaluation::evaluate(&board, &mut PawnTable::new(), &mut Material::new());
+        let mut eval = HandCrafted::new();
+        let (lazy_value, lazy) = Evaluation::evaluate_in_window(&board, &mut PawnTable::new(),
+                                                                &mut Material::new(), Score::ZERO, -2000, -1900);
+        assert!(lazy);
+        assert_eq!(eval.evaluate_in_window(&board, -2000, -1900), (lazy_value, true));
+        assert_eq!(eval.evaluate(&board), full);
+        assert_eq!(eval.evaluate_in_window(&board, -2000, -1900), (full, false));
+    }
+
+    #[test]
+    fn custom_evaluator() {
+        let mut eval: Box<Evaluator> = Box::new(MaterialOnly);
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        assert_eq!(eval.evaluate(&board), -QUEEN_MG);
+        assert_eq!(eval.trace(&board), format!("{}\n", -QUEEN_MG));
+    }
+}
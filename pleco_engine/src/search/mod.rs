@@ -1,6 +1,8 @@
 //! The main searching function.
 
 pub mod eval;
+pub mod evaluator;
+pub mod nnue;
 
 use std::cmp::{min,max};
 use std::sync::atomic::{Ordering,AtomicBool,AtomicU64};
@@ -12,6 +14,7 @@ use pleco::{Board,BitMove,SQ};
 use pleco::core::*;
 use pleco::tools::tt::*;
 use pleco::core::score::*;
+use pleco::core::masks::PLAYER_CNT;
 use pleco::tools::pleco_arc::Arc;
 use pleco::tools::PreFetchable;
 use pleco::helper::prelude::*;
@@ -22,20 +25,57 @@ use pleco::core::piece_move::MoveType;
 use {MAX_PLY,THREAD_STACK_SIZE};
 
 use threadpool::threadpool;
-use time::time_management::TimeManager;
+use time::time_management::{TimeManager,SearchStability};
 use time::uci_timer::*;
 use sync::{GuardedBool,LockLatch};
 use root_moves::RootMove;
 use root_moves::root_moves_list::RootMoveList;
-use tables::material::Material;
-use tables::pawn_table::PawnTable;
+use self::evaluator::{Evaluator,HandCrafted};
+use self::nnue::{Network,NnueState};
 use consts::*;
 use movepick::MovePicker;
 use tables::prelude::*;
+use tune::{self, TuneParam};
 
 
 const RAZORING_MARGIN: i32 = 590;
 
+/// Determines which side keeps its contempt while the engine is analysing, rather than
+/// playing a game.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(u8)]
+pub enum AnalysisContempt {
+    Off = 0,
+    White = 1,
+    Black = 2,
+    Both = 3,
+}
+
+impl AnalysisContempt {
+    /// The names of each variant, as displayed by the `Analysis Contempt` UCI option.
+    pub const NAMES: [&'static str; 4] = ["Off", "White", "Black", "Both"];
+
+    /// Parses the value of the `Analysis Contempt` UCI option.
+    pub fn from_name(name: &str) -> Option<AnalysisContempt> {
+        match name {
+            "Off" => Some(AnalysisContempt::Off),
+            "White" => Some(AnalysisContempt::White),
+            "Black" => Some(AnalysisContempt::Black),
+            "Both" => Some(AnalysisContempt::Both),
+            _ => None
+        }
+    }
+
+    pub fn from_u8(val: u8) -> AnalysisContempt {
+        match val {
+            1 => AnalysisContempt::White,
+            2 => AnalysisContempt::Black,
+            3 => AnalysisContempt::Both,
+            _ => AnalysisContempt::Off,
+        }
+    }
+}
+
 const THREAD_DIST: usize = 20;
 
 //                                      1  2  3  4  5  6  7  8  9 10 11 12 13 14 15 16 17 18 19 20
@@ -45,16 +85,17 @@ static START_PLY: [i16; THREAD_DIST] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1
 
 static mut REDUCTIONS: [[[[i16; 64]; 64]; 2]; 2] = [[[[0; 64]; 64]; 2]; 2];  // [pv][improving][depth][moveNumber]
 static mut FUTILITY_MOVE_COUNTS: [[i32; 16]; 2] = [[0; 16]; 2]; // [improving][depth]
-static RAZOR_MARGIN: [i32; 3] = [0, 590, 604];
-
-static CAPTURE_PRUNE_MARGIN: [i32; 7] = [
-    0,
-    1 * PAWN_EG * 1055 / 1000,
-    2 * PAWN_EG * 1042 / 1000,
-    3 * PAWN_EG * 963  / 1000,
-    4 * PAWN_EG * 1038 / 1000,
-    5 * PAWN_EG * 950  / 1000,
-    6 * PAWN_EG * 930  / 1000
+// Indexed by depth. Depth 0 is never razored or pruned, and so has no margin.
+static RAZOR_MARGIN: [Option<&TuneParam>; 3] = [None, Some(&tune::RAZOR_MARGIN_1), Some(&tune::RAZOR_MARGIN_2)];
+
+static CAPTURE_PRUNE_MARGIN: [Option<&TuneParam>; 7] = [
+    None,
+    Some(&tune::CAPTURE_PRUNE_MARGIN_1),
+    Some(&tune::CAPTURE_PRUNE_MARGIN_2),
+    Some(&tune::CAPTURE_PRUNE_MARGIN_3),
+    Some(&tune::CAPTURE_PRUNE_MARGIN_4),
+    Some(&tune::CAPTURE_PRUNE_MARGIN_5),
+    Some(&tune::CAPTURE_PRUNE_MARGIN_6)
 ];
 
 // used at startup to use lookup tables
@@ -96,6 +137,7 @@ pub struct Stack {
     static_eval: Value,
     stat_score: i32,
     move_count: u32,
+    nnue: NnueState,
 }
 
 impl Stack {
@@ -145,19 +187,31 @@ pub struct Searcher {
     pub kill: AtomicBool,
     pub searching: Arc<GuardedBool>,
     pub cond: Arc<LockLatch>,
+    // Set when woken up to clear the transposition table, rather than to search.
+    pub clearing_tt: bool,
 
     // search data
     pub depth_completed: i16,
     pub limit: Limits,
     pub board: Board,
     pub time_man: &'static TimeManager,
-    pub pawns: PawnTable,
-    pub material: Material,
+    pub evaluator: Box<Evaluator>,
     pub root_moves: UnsafeCell<RootMoveList>,
     pub selected_depth: i16,
     pub last_best_move: BitMove,
     pub last_best_move_depth: i16,
     pub nodes: AtomicU64,
+    // Set for a searcher running outside of the thread pool, which stops itself rather than
+    // the pool.
+    standalone: bool,
+    halted: bool,
+    // The transposition table searched, which is the global table unless given to a
+    // standalone search.
+    tt: *const TranspositionTable,
+
+    pub contempt: Score,
+    pub draw_value: [Value; PLAYER_CNT],
+    pub network: Option<&'static Network>,
 
     pub counter_moves: CounterMoveHistory,
     pub main_history: ButterflyHistory,
@@ -173,28 +227,48 @@ pub struct Searcher {
 
 }
 
+// The evaluator's tables are prefetched once the move has been applied instead.
+struct NoPrefetch;
+
+impl PreFetchable for NoPrefetch {
+    fn prefetch(&self, _key: u64) {}
+}
+
 unsafe impl Send for Searcher {}
 unsafe impl Sync for Searcher {}
 
 impl Searcher {
-    /// Creates a new `Searcher` of an ID and condition to be released by.
+    /// Creates a new `Searcher` of an ID and condition to be released by, using the
+    /// hand-crafted evaluation.
     pub fn new(id: usize, cond: Arc<LockLatch>) -> Self {
+        Searcher::with_evaluator(id, cond, HandCrafted::boxed())
+    }
+
+    /// Creates a new `Searcher` of an ID and condition to be released by, evaluating
+    /// positions with `evaluator`.
+    pub fn with_evaluator(id: usize, cond: Arc<LockLatch>, evaluator: Box<Evaluator>) -> Self {
         Searcher {
             id,
             kill: AtomicBool::new(false),
             searching: Arc::new(GuardedBool::new(true)),
             cond,
+            clearing_tt: false,
             depth_completed: 0,
             limit: Limits::blank(),
             board: Board::start_pos(),
             time_man: timer(),
-            pawns: PawnTable::new(),
-            material: Material::new(),
+            evaluator,
             root_moves: UnsafeCell::new(RootMoveList::new()),
             selected_depth: 0,
             last_best_move: BitMove::null(),
             last_best_move_depth: 0,
             nodes: AtomicU64::new(0),
+            tt: tt(),
+            standalone: false,
+            halted: false,
+            contempt: Score::ZERO,
+            draw_value: [DRAW; PLAYER_CNT],
+            network: None,
             counter_moves: CounterMoveHistory::new(),
             main_history: ButterflyHistory::new(),
             capture_history: CapturePieceToHistory::new(),
@@ -203,14 +277,13 @@ impl Searcher {
             best_move: BitMove::null(),
             failed_low: false,
             best_move_changes: 0.0,
-            previous_time_reduction: 0.0
+            previous_time_reduction: 1.0
         }
     }
 
     pub fn clear(&mut self) {
-        self.pawns.clear();
-        self.material.clear();
-        self.previous_time_reduction = 0.0;
+        self.evaluator.clear();
+        self.previous_time_reduction = 1.0;
         self.previous_score = INFINITE;
         self.counter_moves.clear();
         self.main_history.clear();
@@ -233,7 +306,9 @@ impl Searcher {
     /// Starts the searchering. Assumes the Board and Limits are set
     fn go(&mut self) {
         self.searching.set(true);
-        if self.main_thread() {
+        if self.clearing_tt {
+            self.clear_tt();
+        } else if self.main_thread() {
             // If we're main thread, wake up the other threads
             self.main_thread_go();
         } else {
@@ -244,9 +319,16 @@ impl Searcher {
         self.searching.set(false);
     }
 
+    /// Clears parts of the transposition table, alongside the other threads.
+    fn clear_tt(&mut self) {
+        threadpool().tt_clear.run(tt());
+    }
+
     /// Main thread searching function.
     fn main_thread_go(&mut self) {
-        // set the global limit
+        // Lock our own condition, as a search finishing before the pool sees us start
+        // would otherwise loop straight back into another search
+        threadpool().main_cond.lock();
 
         // Increment the TT search table.
         tt().new_search();
@@ -344,6 +426,20 @@ impl Searcher {
 
         let mut time_reduction: f64 = 1.0;
 
+        // Contempt is taken from the perspective of the side to move at the root.
+        let us: Player = self.board.turn();
+        let analysing: bool = self.limit.limits_type.is_infinite()
+            || ANALYSE_MODE.load(Ordering::Relaxed);
+        let contempt_mode = AnalysisContempt::from_u8(ANALYSIS_CONTEMPT.load(Ordering::Relaxed));
+        let ct: Value = root_contempt(CONTEMPT.load(Ordering::Relaxed), contempt_mode, analysing, us);
+        self.contempt = contempt_score(us, ct);
+        self.evaluator.set_contempt(self.contempt);
+        self.draw_value[us as usize] = DRAW - ct;
+        self.draw_value[!us as usize] = DRAW + ct;
+
+        // The network is fixed for the duration of the search.
+        self.network = if USE_NNUE.load(Ordering::Relaxed) { nnue::network() } else { None };
+
         stack.ply_zero().ply = 0;
 
         // Iterative deeping. Start at the base ply (determined by thread_id), and then increment
@@ -356,6 +452,8 @@ impl Searcher {
                 self.failed_low = false;
             }
 
+            self.selected_depth = 0;
+
             // rollback all the root moves, ala set the previous score to the current score.
             self.root_moves().rollback();
 
@@ -366,6 +464,11 @@ impl Searcher {
                 delta = 20;
                 alpha = max(prev_best_score - delta, NEG_INFINITE);
                 beta = min(prev_best_score + delta, INFINITE);
+
+                // Adjust contempt based on the root move's previous score (dynamic contempt).
+                let dct: Value = dynamic_contempt(ct, prev_best_score, contempt_mode, analysing);
+                self.contempt = contempt_score(us, dct);
+                self.evaluator.set_contempt(self.contempt);
             }
 
             // Loop until we find a value that is within the bounds of alpha, beta, and the delta margin.
@@ -443,33 +546,27 @@ impl Searcher {
             // check for time
             if self.limit.use_time_management().is_some() {
                 if !self.stop() {
-                    let score_diff: i32 = best_value - self.previous_score;
-
-                    let improving_factor: i64 = (232).max((787).min(
-                          306
-                        + 119 * self.failed_low as i64
-                        -   6 * score_diff as i64));
-
-                    time_reduction = 1.0;
+                    let stability = SearchStability {
+                        root_moves: self.root_moves().len(),
+                        depth_completed: self.depth_completed,
+                        last_best_move_depth: self.last_best_move_depth,
+                        best_move_changes: self.best_move_changes,
+                        failed_low: self.failed_low,
+                        best_value,
+                        previous_score: self.previous_score,
+                        previous_time_reduction: self.previous_time_reduction,
+                    };
+                    let elapsed = self.time_man.elapsed();
+                    let scaling = self.time_man.scale(&stability, elapsed);
+                    time_reduction = scaling.time_reduction;
 
-                    // If the bestMove is stable over several iterations, reduce time accordingly
-                    for i in 3..6 {
-                        if self.last_best_move_depth * i < self.depth_completed {
-                            time_reduction *= 1.42;
-                        }
+                    if self.use_stdout() && DEBUG.load(Ordering::Relaxed) {
+                        println!("info string depth {} {}", self.depth_completed, scaling.describe(elapsed));
                     }
 
-                    // Use part of the gained time from a previous stable move for the current move
-                    let mut unstable_factor: f64 = 1.0 + self.best_move_changes;
-                    unstable_factor *= self.previous_time_reduction.powf(0.40) / time_reduction;
-
                     // Stop the search if we have only one legal move, or if available time elapsed
-                    if self.root_moves().len() == 1
-                        || self.time_man.elapsed() >=
-                        (self.time_man.ideal_time() as f64
-                            * unstable_factor as f64
-                            * improving_factor as f64 / 600.0) as i64 {
-                        threadpool().set_stop(true);
+                    if scaling.stop.is_some() {
+                        self.halt();
                         break 'iterative_deepening;
                     }
                 }
@@ -528,11 +625,16 @@ impl Searcher {
             self.check_time();
         }
 
+        // Keep track of the maximum ply reached in a PV node.
+        if is_pv && self.selected_depth < ply as i16 + 1 {
+            self.selected_depth = ply as i16 + 1;
+        }
+
         if !at_root {
             // Check for stop conditions.
             if self.stop() || ply >= MAX_PLY {
                 if !in_check && ply >= MAX_PLY {
-                    return self.eval();
+                    return self.eval(ss);
                 } else {
                     return ZERO;
                 }
@@ -570,15 +672,19 @@ impl Searcher {
         // probe the transposition table
         excluded_move = ss.excluded_move;
         zob = self.board.zobrist() ^ (excluded_move.get_raw() as u64).wrapping_shl(16);
-        let (tt_hit, tt_entry): (bool, &mut Entry) = tt().probe(zob);
-        let tt_value: Value = if tt_hit {value_from_tt(tt_entry.score, ss.ply)} else {NONE};
+        // Other threads may write to the entry at any time, so it's only read from a copy that
+        // is checked to still hold the position. The entry itself is only written to.
+        let (tt_hit, tt_slot): (bool, &mut Entry) = self.tt().probe(zob);
+        let tt_entry: Entry = tt_slot.clone();
+        let tt_hit: bool = tt_hit && tt_entry.matches(zob);
+        let tt_value: Value = if tt_hit {value_from_tt(tt_entry.score(), ss.ply)} else {NONE};
         let tt_move: BitMove = if at_root {self.root_moves().first().bit_move}
-            else if tt_hit {tt_entry.best_move} else {BitMove::null()};
+            else if tt_hit {tt_entry.best_move()} else {BitMove::null()};
 
         // At non-PV nodes, check for a better TT value to return.
         if !is_pv
             && tt_hit
-            && tt_entry.depth as i16 >= depth as i16
+            && tt_entry.depth() >= depth as i16
             && tt_value != NONE
             && correct_bound_eq(tt_value, beta, tt_entry.node_type()) {
 
@@ -621,10 +727,10 @@ impl Searcher {
             improving = false;
         } else {
             if tt_hit {
-                pos_eval = if tt_entry.eval as i32 == NONE {
-                    self.eval()
+                pos_eval = if tt_entry.eval() as i32 == NONE {
+                    self.eval(ss)
                 } else {
-                    tt_entry.eval as i32
+                    tt_entry.eval() as i32
                 };
                 ss.static_eval = pos_eval;
 
@@ -633,13 +739,13 @@ impl Searcher {
                     pos_eval = tt_value;
                 }
             } else {
-                pos_eval = self.eval();
+                pos_eval = self.eval(ss);
                 ss.static_eval = pos_eval;
                 // Place the evaluation into the tt, as it's otherwise empty
-                tt_entry.place(zob, BitMove::null(),
-                               NONE as i16, pos_eval as i16,
-                               -6, NodeBound::NoBound,
-                               tt().time_age());
+                tt_slot.place(zob, BitMove::null(),
+                              NONE as i16, pos_eval as i16,
+                              -6, NodeBound::NoBound,
+                              self.tt().time_age());
             }
 
             improving = {
@@ -655,8 +761,8 @@ impl Searcher {
             // isn't better than alpha, go straight to qsearch.
             if !is_pv
                 && depth < 3
-                && pos_eval <= alpha - RAZOR_MARGIN[depth as usize] {
-                let r_alpha = alpha - (depth >= 2) as i32 * RAZOR_MARGIN[depth as usize];
+                && pos_eval <= alpha - tuned_margin(&RAZOR_MARGIN, depth) {
+                let r_alpha = alpha - (depth >= 2) as i32 * tuned_margin(&RAZOR_MARGIN, depth);
                 let v =  self.qsearch::<NonPV>(r_alpha, r_alpha+1, ss, 0);
                 if depth < 2 || v <= r_alpha {
                     return v;
@@ -696,11 +802,11 @@ impl Searcher {
             && tt_value != NONE
             && excluded_move == BitMove::null()
             && (tt_entry.node_type() as u8 & NodeBound::LowerBound as u8) != 0
-            && tt_entry.depth as i16 >= depth - 3;
+            && tt_entry.depth() >= depth - 3;
 
         skip_quiets = false;
         tt_capture = false;
-        pv_exact = is_pv && tt_entry.node_type() == NodeBound::Exact;
+        pv_exact = is_pv && tt_hit && tt_entry.node_type() == NodeBound::Exact;
         while let Some(mov) = move_picker.next(skip_quiets) {
             if mov == excluded_move {
                 continue;
@@ -782,13 +888,13 @@ impl Searcher {
                     }
                 } else if depth < 7
                     && extension == 0
-                    && !self.board.see_ge(mov, -CAPTURE_PRUNE_MARGIN[depth as usize]) {
+                    && !self.board.see_ge(mov, -tuned_margin(&CAPTURE_PRUNE_MARGIN, depth)) {
                     continue;
                 }
             }
 
             // speculative prefetch for the next key.
-            tt().prefetch(self.board.key_after(mov));
+            self.tt().prefetch(self.board.key_after(mov));
 
             if !self.board.legal_move(mov) {
                 ss.move_count -= 1;
@@ -796,6 +902,12 @@ impl Searcher {
                 continue;
             }
 
+            // Let the GUI know which root move is being searched during longer searches.
+            if at_root && self.main_thread() && self.use_stdout()
+                && self.time_man.elapsed() > 3000 {
+                println!("{}", currmove_info(depth, mov, moves_played));
+            }
+
             if mov == tt_move && capture_or_promotion {
                 tt_capture = true;
             }
@@ -803,10 +915,10 @@ impl Searcher {
             ss.cont_history = &mut self.cont_history[(moved_piece,mov.get_dest())] as *mut _;
 
             // do the move
-            self.apply_move(mov, gives_check);
+            self.apply_move(mov, gives_check, ss);
 
             // prefetch next TT entry
-            tt().prefetch(self.board.zobrist());
+            self.tt().prefetch(self.board.zobrist());
 
             // At higher depths, do a search of a lower ply to see if this move is
             // worth searching. We don't do this for capturing or promotion moves.
@@ -878,6 +990,7 @@ impl Searcher {
             }
 
             self.board.undo_move();
+            self.evaluator.on_undo(&self.board);
             assert!(value > NEG_INFINITE);
             assert!(value < INFINITE );
 
@@ -947,7 +1060,7 @@ impl Searcher {
             } else if in_check {
                 return mated_in(ss.ply);
             } else {
-                return DRAW as i32;
+                return self.draw_value[self.board.turn() as usize];
             }
         } else if best_move != BitMove::null() {
             // If the best move is quiet, update move heuristics
@@ -986,9 +1099,9 @@ impl Searcher {
 
 
         if excluded_move != BitMove::null() {
-            tt_entry.place(zob, best_move, value_to_tt(best_value, ss.ply),
-                           ss.static_eval as i16, depth as i16,
-                           node_bound, tt().time_age());
+            tt_slot.place(zob, best_move, value_to_tt(best_value, ss.ply),
+                          ss.static_eval as i16, depth as i16,
+                          node_bound, self.tt().time_age());
         }
 
         best_value
@@ -1021,16 +1134,22 @@ impl Searcher {
 
         let in_check: bool = self.board.in_check();
 
+        if is_pv && self.selected_depth < ply as i16 + 1 {
+            self.selected_depth = ply as i16 + 1;
+        }
+
         if ply >= MAX_PLY {
             if !in_check {
-                return self.eval();
+                return self.eval(ss);
             } else {
                 return ZERO;
             }
         }
 
-        let (tt_hit, tt_entry): (bool, &mut Entry) = tt().probe(zob);
-        let tt_value: Value = if tt_hit {value_from_tt(tt_entry.score, ss.ply)} else {NONE};
+        let (tt_hit, tt_slot): (bool, &mut Entry) = self.tt().probe(zob);
+        let tt_entry: Entry = tt_slot.clone();
+        let tt_hit: bool = tt_hit && tt_entry.matches(zob);
+        let tt_value: Value = if tt_hit {value_from_tt(tt_entry.score(), ss.ply)} else {NONE};
 
         // Determine whether or not to include checking moves.
         let tt_depth: i16 = if in_check || rev_depth >= 0 {0} else {-1};
@@ -1038,12 +1157,12 @@ impl Searcher {
         // increment the next ply
         ss.incr().ply = ply + 1;
         ss.current_move = BitMove::null();
-        let tt_move = if tt_hit {tt_entry.best_move} else {BitMove::null()};
+        let tt_move = if tt_hit {tt_entry.best_move()} else {BitMove::null()};
         let mut best_move = tt_move;
 
         if !is_pv
             && tt_hit
-            && tt_entry.depth as i16 >= tt_depth
+            && tt_entry.depth() >= tt_depth
             && tt_value != NONE
             && correct_bound_eq(tt_value, beta, tt_entry.node_type()) {
             return tt_value;
@@ -1055,31 +1174,27 @@ impl Searcher {
             best_value = NEG_INFINITE;
             futility_base = NEG_INFINITE;
         } else {
-            if tt_hit {
-                if tt_entry.eval as i32 == NONE {
-                    best_value = self.eval();
-                    pos_eval = best_value;
-                    ss.static_eval = best_value;
-                } else {
-                    best_value = tt_entry.eval as i32;
-                    pos_eval = best_value;
-                    ss.static_eval = best_value;
-                }
-
-                if tt_value != NONE && correct_bound(tt_value, best_value, tt_entry.node_type()) {
-                    best_value = tt_value;
-                }
-            } else {
-                best_value = self.eval();
+            if tt_hit && tt_entry.eval() as i32 != NONE {
+                best_value = tt_entry.eval() as i32;
                 pos_eval = best_value;
-                ss.static_eval = best_value;
+            } else {
+                let (value, lazy) = self.eval_in_window(ss, alpha, beta);
+                best_value = value;
+                // A rough evaluation only holds for this window, so it isn't kept as the
+                // static evaluation of the position.
+                pos_eval = if lazy {NONE} else {value};
+            }
+            ss.static_eval = pos_eval;
+
+            if tt_hit && tt_value != NONE && correct_bound(tt_value, best_value, tt_entry.node_type()) {
+                best_value = tt_value;
             }
 
             if best_value >= beta {
                 if !tt_hit {
-                    tt_entry.place(zob, BitMove::null(), value_to_tt(best_value, ss.ply),
-                                   pos_eval as i16, -6,
-                                   NodeBound::LowerBound, tt().time_age());
+                    tt_slot.place(zob, BitMove::null(), value_to_tt(best_value, ss.ply),
+                                  pos_eval as i16, -6,
+                                  NodeBound::LowerBound, self.tt().time_age());
                 }
                 return best_value;
             }
@@ -1131,7 +1246,7 @@ impl Searcher {
                 continue;
             }
 
-            tt().prefetch(self.board.key_after(mov));
+            self.tt().prefetch(self.board.key_after(mov));
 
             if !self.board.legal_move(mov) {
                 moves_played -= 1;
@@ -1139,16 +1254,17 @@ impl Searcher {
             }
 
             ss.current_move = mov;
-            self.apply_move(mov, gives_check);
+            self.apply_move(mov, gives_check, ss);
 
             // prefetch next TT entry
-            tt().prefetch(self.board.zobrist());
+            self.tt().prefetch(self.board.zobrist());
 
             assert_eq!(gives_check, self.board.in_check());
 
             value = -self.qsearch::<N>(-beta, -alpha, ss.incr(),rev_depth - 1);
 
             self.board.undo_move();
+            self.evaluator.on_undo(&self.board);
 
             assert!(value > NEG_INFINITE);
             assert!(value < INFINITE );
@@ -1165,9 +1281,9 @@ impl Searcher {
                         best_move = mov;
                         alpha = value;
                     } else {
-                        tt_entry.place(zob, mov, value_to_tt(best_value, ss.ply),
-                                       ss.static_eval as i16, tt_depth as i16,
-                                       NodeBound::LowerBound, tt().time_age());
+                        tt_slot.place(zob, mov, value_to_tt(best_value, ss.ply),
+                                      ss.static_eval as i16, tt_depth as i16,
+                                      NodeBound::LowerBound, self.tt().time_age());
                         return value;
                     }
                 }
@@ -1183,9 +1299,9 @@ impl Searcher {
                 else {NodeBound::UpperBound};
 
 
-        tt_entry.place(zob, best_move, value_to_tt(best_value, ss.ply),
-                       ss.static_eval as i16, tt_depth,
-                       node_bound, tt().time_age());
+        tt_slot.place(zob, best_move, value_to_tt(best_value, ss.ply),
+                      ss.static_eval as i16, tt_depth,
+                      node_bound, self.tt().time_age());
 
         assert!(best_value > NEG_INFINITE);
         assert!(best_value < INFINITE );
@@ -1254,34 +1370,143 @@ impl Searcher {
     }
 
     #[inline(always)]
-    fn apply_move(&mut self, mov: BitMove, gives_check: bool) {
+    fn apply_move(&mut self, mov: BitMove, gives_check: bool, ss: &mut Stack) {
         self.nodes.fetch_add(1, Ordering::Relaxed);
-        self.board.apply_move_pft_chk(mov, gives_check, &self.pawns, &self.material);
+        if self.network.is_some() {
+            let before = self.board.get_piece_locations();
+            let occ = self.board.occupied();
+            self.board.apply_move_pft_chk(mov, gives_check, &NoPrefetch, &NoPrefetch);
+            ss.incr().nnue.record_move(&before, occ, &self.board, mov.get_dest());
+        } else {
+            self.board.apply_move_pft_chk(mov, gives_check, &NoPrefetch, &NoPrefetch);
+            self.evaluator.prefetch(&self.board);
+        }
+        self.evaluator.on_move(&self.board, mov);
+    }
+
+    /// Evaluates the current position from the perspective of the side to move, using the
+    /// network if one is in use and the `Evaluator` otherwise. Contempt is only applied
+    /// by the `Evaluator`.
+    pub fn eval(&mut self, ss: &mut Stack) -> Value {
+        self.eval_in_window(ss, NEG_INFINITE, INFINITE).0
+    }
+
+    /// Evaluates the current position like `Searcher::eval`, allowing the `Evaluator` to return
+    /// a rough evaluation when it's far outside of the `alpha`, `beta` window. Also returns if
+    /// the evaluation was rough.
+    pub fn eval_in_window(&mut self, ss: &mut Stack, alpha: Value, beta: Value) -> (Value, bool) {
+        if let Some(net) = self.network {
+            // The previous ply always exists, as the root is not the first frame.
+            let prev: &NnueState = unsafe { &(*(ss as *const Stack).offset(-1)).nnue };
+            return (nnue::evaluate(net, &self.board, &mut ss.nnue, Some(prev)), false);
+        }
+        self.evaluator.evaluate_in_window(&self.board, alpha, beta)
+    }
+
+    /// Runs the quiescence search on the current position, returning its value from the
+    /// perspective of the side to move along with the moves leading to the quiet position at
+    /// the end of the principal variation. The principal variation is read back from the
+    /// transposition table.
+    pub fn quiesce(&mut self) -> (Value, Vec<BitMove>) {
+        let mut stack: ThreadStack = ThreadStack::new();
+        for i in [0,1,2,3,4].iter() {
+            stack.get(*i).cont_history = &mut self.cont_history[(Piece::None, SQ(0))] as *mut _;
+        }
+        stack.ply_zero().ply = 0;
+        self.network = None;
+        let value = self.qsearch::<PV>(NEG_INFINITE, INFINITE, stack.ply_zero(), 0);
+
+        let mut pv: Vec<BitMove> = Vec::new();
+        let mut board = self.board.shallow_clone();
+        while pv.len() < MAX_PLY as usize {
+            let mov = match self.tt().read(board.zobrist()) {
+                Some(entry) => entry.best_move(),
+                None => break,
+            };
+            if mov.is_null() || !board.generate_moves().contains(&mov) {
+                break;
+            }
+            board.apply_move(mov);
+            pv.push(mov);
+        }
+        (value, pv)
     }
 
-    pub fn eval(&mut self) -> Value {
-        let pawns = &mut self.pawns;
-        let material = &mut self.material;
-        eval::Evaluation::evaluate(&self.board, pawns, material)
+    // Returns the transposition table being searched.
+    #[inline(always)]
+    fn tt(&self) -> &'static TranspositionTable {
+        // The global table is never freed, and a standalone search's table outlives the search.
+        unsafe { &*self.tt }
     }
 
     #[inline(always)]
     fn main_thread(&self) -> bool {
-        self.id == 0
+        self.id == 0 || self.standalone
+    }
+
+    /// Searches `board` on the calling thread, outside of the thread pool, returning the best
+    /// move and its score from the perspective of the side to move. Only depth and node limits
+    /// are supported, and no output is printed regardless of `USE_STDOUT`.
+    ///
+    /// `tt` is searched in place of the global transposition table, and is aged by this method.
+    ///
+    /// # Panics
+    ///
+    /// If `board` has no legal moves.
+    pub fn search_standalone(&mut self, board: &Board, limits_type: LimitsType,
+                             tt: &TranspositionTable) -> (BitMove, Value) {
+        let moves = board.generate_moves();
+        assert!(!moves.is_empty(), "no legal moves to search");
+        self.board = board.shallow_clone();
+        self.limit = Limits::blank();
+        self.limit.limits_type = limits_type;
+        self.root_moves().replace(&moves);
+        self.nodes.store(0, Ordering::Relaxed);
+        self.depth_completed = 0;
+        self.previous_score = INFINITE;
+        self.standalone = true;
+        self.halted = false;
+        self.tt = tt;
+        tt.new_search();
+        self.search_root();
+        self.standalone = false;
+        self.tt = ::consts::tt();
+
+        // The last iteration may have been stopped before the best move was searched.
+        let best = self.root_moves().first();
+        let score = if best.score == NEG_INFINITE { best.prev_score } else { best.score };
+        (best.bit_move, score)
     }
 
     #[inline(always)]
     fn stop(&self) -> bool {
+        if self.standalone {
+            return self.halted;
+        }
         threadpool().stop.load(Ordering::Relaxed)
     }
 
-    fn check_time(&mut self) {
-        if self.limit.use_time_management().is_some()
-            && self.time_man.elapsed() >= self.time_man.maximum_time() {
+    // Stops the search, either of this searcher alone or of the whole thread pool.
+    fn halt(&mut self) {
+        if self.standalone {
+            self.halted = true;
+        } else {
             threadpool().set_stop(true);
+        }
+    }
+
+    fn check_time(&mut self) {
+        if self.limit.use_time_management().is_some() && self.time_man.out_of_time() {
+            self.halt();
         } else if let Some(time) = self.limit.use_movetime() {
-            if self.limit.elapsed() >= time as i64 {
-                threadpool().set_stop(true);
+            if self.time_man.elapsed() >= time as i64 {
+                self.halt();
+            }
+        } else if let LimitsType::Nodes(nodes) = self.limit.limits_type {
+            // At least one iteration is completed, so there's always a scored move.
+            if self.standalone && self.depth_completed > 0
+                && self.nodes.load(Ordering::Relaxed) >= nodes {
+                self.halt();
             }
         }
     }
@@ -1295,7 +1520,7 @@ impl Searcher {
 
     #[inline(always)]
     pub fn use_stdout(&self) -> bool {
-        USE_STDOUT.load(Ordering::Relaxed)
+        !self.standalone && USE_STDOUT.load(Ordering::Relaxed)
     }
 
     #[inline]
@@ -1307,33 +1532,34 @@ impl Searcher {
 
     /// Useful information to tell to the GUI
     fn pv(&self, depth: i16, alpha: i32, beta: i32) {
+        if let Some(info) = self.pv_info(depth, alpha, beta) {
+            println!("{}", info);
+        }
+    }
+
+    /// Creates the UCI info line for the current best root move, or `None` if no root move
+    /// has a score yet.
+    fn pv_info(&self, depth: i16, alpha: i32, beta: i32) -> Option<String> {
         let root_move: &RootMove= self.root_moves().first();
         let elapsed = self.time_man.elapsed() as u64;
         let nodes = threadpool().nodes();
-        let mut score = if root_move.score == NEG_INFINITE {
+        let score = if root_move.score == NEG_INFINITE {
             root_move.prev_score
             } else {
             root_move.score
         };
 
         if score == NEG_INFINITE {
-            return;
+            return None;
         }
 
         let mut s = String::from("info");
         s.push_str(&format!(" depth {}", depth));
-        if score.abs() < MATE - MAX_PLY as i32 {
-            score *= 100;
-            score /= PAWN_EG;
-            s.push_str(&format!(" score cp {}", score));
-        } else {
-            let mut mate_in = if score > 0 {
-                MATE - score + 1
-            } else {
-                -MATE - score
-            };
-            mate_in /= 2;
-            s.push_str(&format!(" score mate {}", mate_in));
+        s.push_str(&format!(" seldepth {}", self.selected_depth.max(depth)));
+        s.push_str(&format!(" score {}", uci_score(score)));
+        if SHOW_WDL.load(Ordering::Relaxed) {
+            let (win, draw, loss) = win_draw_loss(score, self.board.moves_played());
+            s.push_str(&format!(" wdl {} {} {}", win, draw, loss));
         }
         if root_move.score >= beta {
             s.push_str(" lowerbound");
@@ -1343,11 +1569,11 @@ impl Searcher {
         s.push_str(&format!(" nodes {}", nodes));
         if elapsed > 1000 {
             s.push_str(&format!(" nps {}", (nodes * 1000) / elapsed));
-            s.push_str(&format!(" hashfull {:.2}", tt().hash_percent()));
+            s.push_str(&format!(" hashfull {}", self.tt().hashfull()));
         }
         s.push_str(&format!(" time {}", elapsed));
         s.push_str(&format!(" pv {}", root_move.bit_move.to_string()));
-        println!("{}",s);
+        Some(s)
     }
 }
 
@@ -1374,6 +1600,89 @@ fn correct_bound(tt_value: i32, val: i32, bound: NodeBound) -> bool {
     }
 }
 
+/// Converts a score into the UCI format, either "cp <x>" or "mate <y>", where y is given in moves
+/// rather than plies.
+fn uci_score(score: Value) -> String {
+    if score.abs() < MATE - MAX_PLY as i32 {
+        format!("cp {}", score * 100 / PAWN_EG)
+    } else {
+        let mate_in = if score > 0 {
+            MATE - score + 1
+        } else {
+            -MATE - score
+        };
+        format!("mate {}", mate_in / 2)
+    }
+}
+
+/// Coefficients of the polynomials (in the game ply) giving the centre and the spread of the
+/// logistic win rate model. Fitted by maximum likelihood on the 897909 positions of
+/// `datagen --games 12000 --depth 6 --seed 2028 --binary`, first for each bucket of four plies
+/// and then as cubics through the buckets' centres and spreads.
+const WDL_AS: [f64; 4] = [16.70708251, 233.46237025, -373.38584820, 234.14759254];
+const WDL_BS: [f64; 4] = [-66.81080283, 481.67770056, -671.59049487, 386.68405949];
+
+/// Returns the expected number of wins per thousand games for the side with the given score,
+/// at the given game ply.
+pub fn win_rate_model(score: Value, ply: u16) -> u16 {
+    // The model is fitted up to move 100, and is constant afterwards.
+    let m: f64 = f64::from(ply.min(200)) / 64.0;
+    let a: f64 = ((WDL_AS[0] * m + WDL_AS[1]) * m + WDL_AS[2]) * m + WDL_AS[3];
+    let b: f64 = ((WDL_BS[0] * m + WDL_BS[1]) * m + WDL_BS[2]) * m + WDL_BS[3];
+
+    // The model is fitted in centipawns, with a limited range.
+    let x: f64 = (f64::from(score) * 100.0 / f64::from(PAWN_EG)).max(-2000.0).min(2000.0);
+    (0.5 + 1000.0 / (1.0 + ((a - x) / b).exp())) as u16
+}
+
+/// Converts a score from the perspective of the side to move into the expected
+/// (win, draw, loss) outcome per thousand games, at the given game ply.
+pub fn win_draw_loss(score: Value, ply: u16) -> (u16, u16, u16) {
+    let win = win_rate_model(score, ply);
+    let loss = win_rate_model(-score, ply);
+    (win, 1000 - win - loss, loss)
+}
+
+/// Creates the info line announcing the root move currently being searched.
+fn currmove_info(depth: i16, mov: BitMove, move_number: u32) -> String {
+    format!("info depth {} currmove {} currmovenumber {}", depth, mov, move_number)
+}
+
+/// Returns the contempt of the side to move at the root, converted from centipawns. When
+/// analysing, `mode` determines which side(s) keep their contempt.
+fn root_contempt(contempt_cp: i32, mode: AnalysisContempt, analysing: bool, us: Player) -> Value {
+    let ct: Value = contempt_cp * PAWN_EG / 100;
+    if !analysing {
+        return ct;
+    }
+    match mode {
+        AnalysisContempt::Off => 0,
+        AnalysisContempt::Both => ct,
+        AnalysisContempt::White if us == Player::Black => -ct,
+        AnalysisContempt::Black if us == Player::White => -ct,
+        _ => ct
+    }
+}
+
+/// Returns the root contempt adjusted by the previous iteration's best score. Analysing with
+/// contempt turned off keeps the evaluation symmetric, so no adjustment is made.
+fn dynamic_contempt(ct: Value, prev_score: Value, mode: AnalysisContempt, analysing: bool) -> Value {
+    if analysing && mode == AnalysisContempt::Off {
+        return ct;
+    }
+    ct + 88 * prev_score / (prev_score.abs() + 200)
+}
+
+/// Converts a root contempt into a `Score` from white's perspective, as used by the evaluation.
+fn contempt_score(us: Player, ct: Value) -> Score {
+    let score = Score(ct, ct / 2);
+    if us == Player::White {
+        score
+    } else {
+        -score
+    }
+}
+
 fn mate_in(ply: u16) -> i32 {
     MATE - ply as i32
 }
@@ -1404,9 +1713,14 @@ fn value_from_tt(value: i16, ply: u16) -> i32 {
     }
 }
 
+#[inline]
+fn tuned_margin(margins: &[Option<&TuneParam>], depth: i16) -> i32 {
+    margins[depth as usize].map_or(0, |p| p.get())
+}
+
 #[inline]
 fn futility_margin(depth: i16, improving: bool) -> i32 {
-    depth as i32 * (175 - 50 * improving as i32)
+    depth as i32 * (tune::FUTILITY_MARGIN.get() - tune::FUTILITY_MARGIN_IMPROVING.get() * improving as i32)
 }
 
 fn reduction<PV: PVNode>(i: bool, depth: i16, mn: u32) -> i16 {
@@ -1429,3 +1743,157 @@ fn how_big() {
     let x = mem::size_of::<Searcher>() / 1000;
     println!("size of searcher: {} KB",x);
 }
+
+#[test]
+fn contempt_analysis_modes() {
+    let ct = 20 * PAWN_EG / 100;
+    assert_eq!(root_contempt(20, AnalysisContempt::Off, false, Player::Black), ct);
+    assert_eq!(root_contempt(20, AnalysisContempt::Off, true, Player::White), 0);
+    assert_eq!(root_contempt(20, AnalysisContempt::Off, true, Player::Black), 0);
+    assert_eq!(root_contempt(20, AnalysisContempt::Both, true, Player::Black), ct);
+    assert_eq!(root_contempt(20, AnalysisContempt::White, true, Player::White), ct);
+    assert_eq!(root_contempt(20, AnalysisContempt::White, true, Player::Black), -ct);
+    assert_eq!(root_contempt(20, AnalysisContempt::Black, true, Player::White), -ct);
+    assert_eq!(root_contempt(20, AnalysisContempt::Black, true, Player::Black), ct);
+
+    assert_eq!(dynamic_contempt(0, 300, AnalysisContempt::Off, true), 0);
+    assert_eq!(dynamic_contempt(0, -300, AnalysisContempt::Off, true), 0);
+    assert_eq!(dynamic_contempt(ct, 300, AnalysisContempt::Off, false), ct + 88 * 300 / 500);
+    assert_eq!(dynamic_contempt(ct, -300, AnalysisContempt::Both, true), ct - 88 * 300 / 500);
+
+    assert_eq!(contempt_score(Player::White, 40), Score(40, 20));
+    assert_eq!(contempt_score(Player::Black, 40), Score(-40, -20));
+}
+
+// Returns the value following `key` in a UCI info line.
+#[cfg(test)]
+fn info_field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let mut tokens = line.split_whitespace();
+    assert_eq!(tokens.next(), Some("info"));
+    while let Some(token) = tokens.next() {
+        if token == key {
+            return tokens.next();
+        }
+    }
+    None
+}
+
+#[test]
+fn uci_score_format() {
+    assert_eq!(uci_score(PAWN_EG), "cp 100");
+    assert_eq!(uci_score(-PAWN_EG / 2), "cp -50");
+    assert_eq!(uci_score(mate_in(1)), "mate 1");
+    assert_eq!(uci_score(mate_in(3)), "mate 2");
+    assert_eq!(uci_score(mated_in(2)), "mate -1");
+}
+
+#[test]
+fn currmove_info_line() {
+    let board = Board::start_pos();
+    let mov = board.generate_moves()[0];
+    let line = currmove_info(7, mov, 3);
+    assert_eq!(info_field(&line, "depth"), Some("7"));
+    assert_eq!(info_field(&line, "currmove"), Some(mov.to_string().as_str()));
+    assert_eq!(info_field(&line, "currmovenumber"), Some("3"));
+}
+
+#[test]
+fn pv_info_line() {
+    init_globals();
+    // The searcher is too large for the default test stack.
+    let handle = ::std::thread::Builder::new()
+        .stack_size(16 * 1024 * 1024)
+        .spawn(|| {
+            let mut searcher = Searcher::new(0, Arc::new(LockLatch::new()));
+            searcher.root_moves().replace(&searcher.board.generate_moves());
+            assert!(searcher.pv_info(1, NEG_INFINITE, INFINITE).is_none());
+
+            searcher.root_moves().first().score = PAWN_EG;
+            searcher.selected_depth = 9;
+
+            let line = searcher.pv_info(5, NEG_INFINITE, INFINITE).unwrap();
+            let best = searcher.root_moves().first().bit_move.to_string();
+            assert_eq!(info_field(&line, "depth"), Some("5"));
+            assert_eq!(info_field(&line, "seldepth"), Some("9"));
+            assert_eq!(info_field(&line, "cp"), Some("100"));
+            assert_eq!(info_field(&line, "pv"), Some(best.as_str()));
+            assert!(info_field(&line, "nodes").unwrap().parse::<u64>().is_ok());
+            assert!(info_field(&line, "time").unwrap().parse::<u64>().is_ok());
+            if let Some(hashfull) = info_field(&line, "hashfull") {
+                assert!(hashfull.parse::<u16>().unwrap() <= 1000);
+            }
+            assert!(!line.contains("lowerbound") && !line.contains("upperbound"));
+
+            let line = searcher.pv_info(5, NEG_INFINITE, PAWN_EG).unwrap();
+            assert!(line.contains(" lowerbound"));
+
+            SHOW_WDL.store(true, Ordering::Relaxed);
+            let line = searcher.pv_info(5, NEG_INFINITE, INFINITE).unwrap();
+            SHOW_WDL.store(false, Ordering::Relaxed);
+            let wdl: Vec<u16> = line.split_whitespace()
+                .skip_while(|t| *t != "wdl")
+                .skip(1)
+                .take(3)
+                .map(|t| t.parse().unwrap())
+                .collect();
+            assert_eq!(wdl.len(), 3);
+            assert_eq!(wdl.iter().sum::<u16>(), 1000);
+            assert!(wdl[0] > wdl[2]);
+        }).unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn wdl_model() {
+    for ply in [0, 40, 120, 300].iter() {
+        let (w, d, l) = win_draw_loss(0, *ply);
+        assert_eq!(w, l);
+        assert_eq!(w + d + l, 1000);
+
+        let mut last_win = 0;
+        for score in (-10..11).map(|i| i * PAWN_EG / 2) {
+            let (w, d, l) = win_draw_loss(score, *ply);
+            assert_eq!(w + d + l, 1000);
+            assert_eq!((w, l), {let (w2, _, l2) = win_draw_loss(-score, *ply); (l2, w2)});
+            assert!(w >= last_win);
+            last_win = w;
+        }
+    }
+    // A pawn up at move 32 wins about half its games, and loses far fewer.
+    assert_eq!(win_draw_loss(PAWN_EG, 64), (479, 356, 165));
+    assert_eq!(win_draw_loss(mate_in(3), 60), (1000, 0, 0));
+    assert_eq!(win_draw_loss(mated_in(2), 60), (0, 0, 1000));
+}
+
+#[test]
+fn lazy_qsearch_eval_not_stored() {
+    init_globals();
+    let handle = ::std::thread::Builder::new()
+        .stack_size(16 * 1024 * 1024)
+        .spawn(|| {
+            // A rook up, which is only lazily evaluated with a window far below it.
+            let board = Board::from_fen("4k3/pppp4/8/8/8/8/PPPP4/R3K3 w - - 0 1").unwrap();
+            let table = TranspositionTable::new(1);
+            let mut searcher = Searcher::new(0, Arc::new(LockLatch::new()));
+            searcher.board = board.shallow_clone();
+            searcher.tt = &table;
+            let mut stack: ThreadStack = ThreadStack::new();
+            for i in [0,1,2,3,4].iter() {
+                stack.get(*i).cont_history = &mut searcher.cont_history[(Piece::None, SQ(0))] as *mut _;
+            }
+            stack.ply_zero().ply = 0;
+
+            let value = searcher.qsearch::<NonPV>(-1901, -1900, stack.ply_zero(), 0);
+            assert!(value >= -1900);
+            assert_eq!(stack.ply_zero().static_eval, NONE);
+            let entry = table.read(board.zobrist()).unwrap();
+            assert_eq!(entry.eval() as i32, NONE);
+
+            // A full evaluation is stored once the window is around it.
+            searcher.qsearch::<PV>(NEG_INFINITE, INFINITE, stack.ply_zero(), 0);
+            let full = searcher.evaluator.evaluate(&board);
+            assert_eq!(stack.ply_zero().static_eval, full);
+            assert_eq!(table.read(board.zobrist()).unwrap().eval() as i32, full);
+        }).unwrap();
+    handle.join().unwrap();
+}
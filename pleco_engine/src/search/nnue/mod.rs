@@ -0,0 +1,526 @@
+//! An efficiently updatable neural network (NNUE) evaluation, used as an alternative to
+//! the hand-crafted evaluation in `search::eval`.
+//!
+//! The network is a single hidden layer with HalfKA input features: every piece on the board
+//! (kings included) is a feature, seen from the perspective of each player and optionally
+//! bucketed by that player's king square. The hidden layer is computed separately for each
+//! perspective into an `Accumulator`, which is kept in the searcher's per-ply `Stack` and
+//! updated incrementally as moves are applied.
+//!
+//! # File format
+//!
+//! All values are little-endian.
+//!
+//! | Field             | Type  | Count                              |
+//! |-------------------|-------|------------------------------------|
+//! | magic             | bytes | 4, `b"PLNN"`                       |
+//! | version           | u32   | 1                                  |
+//! | king buckets      | u32   | 1, either 1 or 64                  |
+//! | hidden size       | u32   | 1, at most `MAX_HIDDEN`            |
+//! | feature biases    | i16   | hidden                             |
+//! | feature weights   | i16   | king buckets * 768 * hidden        |
+//! | output weights    | i16   | 2 * hidden, side to move first     |
+//! | output bias       | i32   | 1                                  |
+//!
+//! Feature weights are stored feature-major, so the weights of a single feature are
+//! contiguous.
+
+mod simd;
+
+use self::simd::Simd;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self,Read};
+use std::ptr;
+use std::sync::atomic::{AtomicPtr,Ordering};
+
+use pleco::{Board,Player,Piece,PieceType,SQ};
+use pleco::board::piece_locations::PieceLocations;
+use pleco::core::masks::PLAYER_CNT;
+use pleco::core::score::*;
+use pleco::core::bitboard::BitBoard;
+
+/// The largest supported hidden layer size.
+pub const MAX_HIDDEN: usize = 512;
+
+/// The number of features for a single king bucket: each of the 12 pieces on each of the 64 squares.
+pub const FEATURES_PER_BUCKET: usize = 12 * 64;
+
+const MAGIC: &[u8; 4] = b"PLNN";
+const VERSION: u32 = 1;
+
+// Quantization of the hidden layer activation and the output weights.
+const QA: i32 = 255;
+const QB: i32 = 64;
+
+// Scales the network output into centipawns.
+const EVAL_SCALE: i32 = 400;
+
+static NETWORK: AtomicPtr<Network> = AtomicPtr::new(ptr::null_mut());
+
+/// Returns the currently loaded network, if any.
+#[inline]
+pub fn network() -> Option<&'static Network> {
+    unsafe { NETWORK.load(Ordering::Acquire).as_ref() }
+}
+
+/// Loads a network from the file at `path`, replacing the current network.
+///
+/// Must not be called while a search is in progress.
+pub fn load_network(path: &str) -> Result<(), NetworkError> {
+    let net = Network::load(path)?;
+    set_network(Some(net));
+    Ok(())
+}
+
+/// Replaces the currently loaded network, or unloads it when given `None`.
+///
+/// Must not be called while a search is in progress.
+pub fn set_network(net: Option<Network>) {
+    let new = net.map_or(ptr::null_mut(), |n| Box::into_raw(Box::new(n)));
+    let old = NETWORK.swap(new, Ordering::AcqRel);
+    if !old.is_null() {
+        unsafe { drop(Box::from_raw(old)); }
+    }
+}
+
+/// Errors that can occur while loading a network.
+#[derive(Debug)]
+pub enum NetworkError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u32),
+    BadDimensions { king_buckets: u32, hidden: u32 },
+    BadLength { expected: usize, found: usize },
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NetworkError::Io(ref e) => write!(f, "{}", e),
+            NetworkError::BadMagic => write!(f, "not a network file"),
+            NetworkError::UnsupportedVersion(v) => write!(f, "unsupported network version {}", v),
+            NetworkError::BadDimensions { king_buckets, hidden } =>
+                write!(f, "unsupported dimensions: {} king buckets, {} hidden", king_buckets, hidden),
+            NetworkError::BadLength { expected, found } =>
+                write!(f, "expected {} bytes, found {}", expected, found),
+        }
+    }
+}
+
+impl From<io::Error> for NetworkError {
+    fn from(e: io::Error) -> Self {
+        NetworkError::Io(e)
+    }
+}
+
+/// The weights of a network.
+pub struct Network {
+    king_buckets: usize,
+    hidden: usize,
+    feature_biases: Vec<i16>,
+    feature_weights: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32,
+    simd: Simd,
+}
+
+impl Network {
+    /// Reads a network from a file.
+    pub fn load(path: &str) -> Result<Network, NetworkError> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        Network::from_bytes(&bytes)
+    }
+
+    /// Parses a network from its file representation.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Network, NetworkError> {
+        if bytes.len() < 16 || &bytes[0..4] != MAGIC {
+            return Err(NetworkError::BadMagic);
+        }
+        let version = read_u32(&bytes[4..]);
+        if version != VERSION {
+            return Err(NetworkError::UnsupportedVersion(version));
+        }
+        let king_buckets = read_u32(&bytes[8..]);
+        let hidden = read_u32(&bytes[12..]);
+        if (king_buckets != 1 && king_buckets != 64) || hidden == 0 || hidden as usize > MAX_HIDDEN {
+            return Err(NetworkError::BadDimensions { king_buckets, hidden });
+        }
+
+        let buckets = king_buckets as usize;
+        let hidden = hidden as usize;
+        let num_weights = buckets * FEATURES_PER_BUCKET * hidden;
+        let expected = 16 + 2 * (hidden + num_weights + 2 * hidden) + 4;
+        if bytes.len() != expected {
+            return Err(NetworkError::BadLength { expected, found: bytes.len() });
+        }
+
+        let mut offset = 16;
+        let feature_biases = read_i16s(bytes, &mut offset, hidden);
+        let feature_weights = read_i16s(bytes, &mut offset, num_weights);
+        let output_weights = read_i16s(bytes, &mut offset, 2 * hidden);
+        let output_bias = read_u32(&bytes[offset..]) as i32;
+
+        Ok(Network {
+            king_buckets: buckets,
+            hidden,
+            feature_biases,
+            feature_weights,
+            output_weights,
+            output_bias,
+            simd: Simd::detect(),
+        })
+    }
+
+    /// Returns the file representation of the network.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.king_buckets as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.hidden as u32).to_le_bytes());
+        for w in self.feature_biases.iter()
+            .chain(self.feature_weights.iter())
+            .chain(self.output_weights.iter()) {
+            bytes.extend_from_slice(&w.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.output_bias.to_le_bytes());
+        bytes
+    }
+
+    /// Returns the size of the hidden layer.
+    #[inline(always)]
+    pub fn hidden(&self) -> usize {
+        self.hidden
+    }
+
+    /// Returns the number of king buckets, either 1 or 64.
+    #[inline(always)]
+    pub fn king_buckets(&self) -> usize {
+        self.king_buckets
+    }
+
+    /// Evaluates a position from scratch, from the perspective of the side to move.
+    pub fn evaluate_board(&self, board: &Board) -> Value {
+        let mut acc = Accumulator::new();
+        for &player in [Player::White, Player::Black].iter() {
+            self.refresh(&mut acc, board, player);
+        }
+        self.evaluate(&acc, board.turn())
+    }
+
+    /// Evaluates a computed accumulator from the perspective of `stm`.
+    pub fn evaluate(&self, acc: &Accumulator, stm: Player) -> Value {
+        let h = self.hidden;
+        let us = &acc.values[stm as usize][..h];
+        let them = &acc.values[!stm as usize][..h];
+        let out = i64::from(self.output_bias)
+            + self.simd.crelu_dot(us, &self.output_weights[..h], QA as i16)
+            + self.simd.crelu_dot(them, &self.output_weights[h..], QA as i16);
+        let cp = out * i64::from(EVAL_SCALE) / i64::from(QA * QB);
+        // Never let a network claim a mate.
+        let bound = i64::from(MATE_IN_MAX_PLY - 1);
+        (cp * i64::from(PAWN_EG) / 100).max(-bound).min(bound) as Value
+    }
+
+    /// Computes the accumulator of a perspective from scratch.
+    pub fn refresh(&self, acc: &mut Accumulator, board: &Board, persp: Player) {
+        let h = self.hidden;
+        let ksq = board.king_sq(persp);
+        let values = &mut acc.values[persp as usize][..h];
+        values.copy_from_slice(&self.feature_biases);
+        let mut occ: BitBoard = board.occupied();
+        while let Some(sq) = occ.pop_some_lsb() {
+            let idx = self.feature_index(persp, ksq, board.piece_at_sq(sq), sq);
+            self.simd.add_assign(values, self.weights(idx));
+        }
+        acc.computed[persp as usize] = true;
+    }
+
+    /// Updates the accumulator of `persp` from its value at the previous ply, applying the
+    /// changed pieces. Returns false if the update can't be done incrementally, as the
+    /// perspective's king changed buckets.
+    pub fn update(&self, acc: &mut Accumulator, prev: &Accumulator, dirty: &DirtyPieces,
+                  board: &Board, persp: Player) -> bool {
+        if !prev.computed[persp as usize] {
+            return false;
+        }
+        let king = Piece::make_lossy(persp, PieceType::K);
+        if self.king_buckets > 1 && dirty.changes().iter().any(|c| c.new == king) {
+            return false;
+        }
+
+        let h = self.hidden;
+        let ksq = board.king_sq(persp);
+        let values = &mut acc.values[persp as usize][..h];
+        values.copy_from_slice(&prev.values[persp as usize][..h]);
+        for change in dirty.changes() {
+            if change.old != Piece::None {
+                let idx = self.feature_index(persp, ksq, change.old, change.sq);
+                self.simd.sub_assign(values, self.weights(idx));
+            }
+            if change.new != Piece::None {
+                let idx = self.feature_index(persp, ksq, change.new, change.sq);
+                self.simd.add_assign(values, self.weights(idx));
+            }
+        }
+        acc.computed[persp as usize] = true;
+        true
+    }
+
+    #[inline(always)]
+    fn weights(&self, idx: usize) -> &[i16] {
+        &self.feature_weights[idx * self.hidden..(idx + 1) * self.hidden]
+    }
+
+    #[inline(always)]
+    fn feature_index(&self, persp: Player, ksq: SQ, piece: Piece, sq: SQ) -> usize {
+        let bucket = if self.king_buckets > 1 { orient(persp, ksq) } else { 0 };
+        let side = (piece.player_lossy() != persp) as usize;
+        let pt = piece.type_of() as usize - 1;
+        bucket * FEATURES_PER_BUCKET + (side * 6 + pt) * 64 + orient(persp, sq)
+    }
+}
+
+// Squares are seen from each player's side of the board.
+#[inline(always)]
+fn orient(persp: Player, sq: SQ) -> usize {
+    (sq.0 ^ (persp as u8 * 56)) as usize
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn read_i16s(bytes: &[u8], offset: &mut usize, count: usize) -> Vec<i16> {
+    let start = *offset;
+    *offset += count * 2;
+    bytes[start..*offset].chunks(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect()
+}
+
+/// The hidden layer values of both perspectives.
+#[repr(align(32))]
+pub struct Accumulator {
+    values: [[i16; MAX_HIDDEN]; PLAYER_CNT],
+    computed: [bool; PLAYER_CNT],
+}
+
+impl Accumulator {
+    pub fn new() -> Self {
+        Accumulator {
+            values: [[0; MAX_HIDDEN]; PLAYER_CNT],
+            computed: [false; PLAYER_CNT],
+        }
+    }
+
+    /// Returns if both perspectives are computed.
+    #[inline(always)]
+    pub fn is_computed(&self) -> bool {
+        self.computed[0] && self.computed[1]
+    }
+}
+
+/// A square whose piece changed during a move.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PieceChange {
+    pub sq: SQ,
+    pub old: Piece,
+    pub new: Piece,
+}
+
+/// The pieces changed by the last move, at most four for castling.
+#[derive(Copy, Clone)]
+pub struct DirtyPieces {
+    changes: [PieceChange; 4],
+    count: usize,
+}
+
+impl DirtyPieces {
+    /// Records the squares changed by a move, given the pieces and occupancy from before the
+    /// move and the board after the move, moving square `dest`.
+    pub fn record(&mut self, before: &PieceLocations, occ_before: BitBoard, board: &Board, dest: SQ) {
+        self.count = 0;
+        let mut changed = (occ_before ^ board.occupied()) | dest.to_bb();
+        while let Some(sq) = changed.pop_some_lsb() {
+            let old = before.piece_at(sq);
+            let new = board.piece_at_sq(sq);
+            if old != new {
+                self.changes[self.count] = PieceChange { sq, old, new };
+                self.count += 1;
+            }
+        }
+    }
+
+    /// Returns the changed pieces.
+    #[inline(always)]
+    pub fn changes(&self) -> &[PieceChange] {
+        &self.changes[..self.count]
+    }
+}
+
+/// The NNUE state kept for each ply of the search.
+pub struct NnueState {
+    pub accumulator: Accumulator,
+    pub dirty: DirtyPieces,
+}
+
+impl NnueState {
+    /// Records the move that led to this ply, marking the accumulator as out of date.
+    #[inline]
+    pub fn record_move(&mut self, before: &PieceLocations, occ_before: BitBoard, board: &Board, dest: SQ) {
+        self.dirty.record(before, occ_before, board, dest);
+        self.accumulator.computed = [false; PLAYER_CNT];
+    }
+
+    /// Marks the accumulator as out of date, so it will be computed from scratch.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.dirty.count = 0;
+        self.accumulator.computed = [false; PLAYER_CNT];
+    }
+}
+
+/// Evaluates `board` from the perspective of the side to move, bringing the accumulator of
+/// `state` up to date, incrementally from `prev` where possible.
+pub fn evaluate(net: &Network, board: &Board, state: &mut NnueState, prev: Option<&NnueState>) -> Value {
+    if !state.accumulator.is_computed() {
+        for &player in [Player::White, Player::Black].iter() {
+            if state.accumulator.computed[player as usize] {
+                continue;
+            }
+            let updated = match prev {
+                Some(p) => net.update(&mut state.accumulator, &p.accumulator, &state.dirty, board, player),
+                None => false
+            };
+            if !updated {
+                net.refresh(&mut state.accumulator, board, player);
+            }
+        }
+    }
+    net.evaluate(&state.accumulator, board.turn())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pleco::BitMove;
+    use rand::{self,Rng};
+
+    const TINY_NET: &[u8] = include_bytes!("../../../tests/fixtures/tiny.nnue");
+
+    fn tiny() -> Network {
+        Network::from_bytes(TINY_NET).unwrap()
+    }
+
+    fn zeroed_state() -> NnueState {
+        NnueState {
+            accumulator: Accumulator::new(),
+            dirty: DirtyPieces {
+                changes: [PieceChange { sq: SQ(0), old: Piece::None, new: Piece::None }; 4],
+                count: 0
+            }
+        }
+    }
+
+    #[test]
+    fn load_tiny() {
+        let net = tiny();
+        assert_eq!(net.king_buckets(), 1);
+        assert_eq!(net.hidden(), 4);
+        assert_eq!(net.to_bytes(), TINY_NET);
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tiny.nnue");
+        assert!(Network::load(path).is_ok());
+        assert!(Network::load("does/not/exist.nnue").is_err());
+    }
+
+    #[test]
+    fn bad_files() {
+        assert!(Network::from_bytes(b"nope").is_err());
+        assert!(Network::from_bytes(&TINY_NET[..TINY_NET.len() - 1]).is_err());
+        let mut version = TINY_NET.to_vec();
+        version[4] = 2;
+        assert!(Network::from_bytes(&version).is_err());
+    }
+
+    #[test]
+    fn tiny_material() {
+        let net = tiny();
+        let board = Board::start_pos();
+        assert_eq!(net.evaluate_board(&board), 0);
+
+        // The tiny network counts material: white is up a queen.
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let white = net.evaluate_board(&board);
+        assert!(white > 4 * PAWN_EG);
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        assert_eq!(net.evaluate_board(&board), -white);
+    }
+
+    #[test]
+    fn evaluation_is_clamped() {
+        let mut net = tiny();
+        net.output_bias = i32::max_value();
+        assert_eq!(net.evaluate_board(&Board::start_pos()), MATE_IN_MAX_PLY - 1);
+        net.output_bias = i32::min_value();
+        assert_eq!(net.evaluate_board(&Board::start_pos()), -(MATE_IN_MAX_PLY - 1));
+    }
+
+    fn random_bucketed() -> Network {
+        let mut rng = rand::thread_rng();
+        let hidden = 24;
+        let mut random = |n: usize| (0..n).map(|_| rng.gen_range(-64, 64)).collect::<Vec<i16>>();
+        Network {
+            king_buckets: 64,
+            hidden,
+            feature_biases: random(hidden),
+            feature_weights: random(64 * FEATURES_PER_BUCKET * hidden),
+            output_weights: random(2 * hidden),
+            output_bias: 37,
+            simd: Simd::detect(),
+        }
+    }
+
+    #[test]
+    fn round_trip_bucketed() {
+        let net = random_bucketed();
+        let read = Network::from_bytes(&net.to_bytes()).unwrap();
+        assert_eq!(read.king_buckets(), 64);
+        assert_eq!(read.to_bytes(), net.to_bytes());
+    }
+
+    #[test]
+    fn incremental_matches_refresh() {
+        play_random_games(&tiny());
+        play_random_games(&random_bucketed());
+    }
+
+    fn play_random_games(net: &Network) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..10 {
+            let mut board = Board::start_pos();
+            let mut states: Vec<NnueState> = vec![zeroed_state()];
+            evaluate(net, &board, &mut states[0], None);
+            for _ in 0..80 {
+                let moves = board.generate_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                let mov: BitMove = moves[rng.gen_range(0, moves.len())];
+                let before = board.get_piece_locations();
+                let occ = board.occupied();
+                board.apply_move(mov);
+
+                let mut state = zeroed_state();
+                state.record_move(&before, occ, &board, mov.get_dest());
+                let value = evaluate(net, &board, &mut state, states.last());
+                assert_eq!(value, net.evaluate_board(&board), "{}", board.fen());
+                states.push(state);
+            }
+        }
+    }
+}
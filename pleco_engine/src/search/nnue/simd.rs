@@ -0,0 +1,181 @@
+//! Vectorized accumulator operations, using AVX2 when the CPU supports it and plain loops
+//! otherwise.
+
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+// The number of i16 lanes in an AVX2 register.
+#[cfg(target_arch = "x86_64")]
+const LANES: usize = 16;
+
+/// The instruction set used by the accumulator operations. Detected once when a network is
+/// loaded, rather than on every operation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Simd {
+    // Only ever true if the CPU supports AVX2, as the AVX2 functions are called on it.
+    avx2: bool,
+}
+
+impl Simd {
+    /// Detects the best instruction set supported by the CPU.
+    pub fn detect() -> Simd {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                return Simd { avx2: true };
+            }
+        }
+        Simd::scalar()
+    }
+
+    /// Returns plain loops, supported by every CPU.
+    pub fn scalar() -> Simd {
+        Simd { avx2: false }
+    }
+
+    /// Adds `w` to `acc`, element-wise.
+    #[inline]
+    pub fn add_assign(self, acc: &mut [i16], w: &[i16]) {
+        debug_assert_eq!(acc.len(), w.len());
+        #[cfg(target_arch = "x86_64")]
+        {
+            if self.avx2 {
+                return unsafe { add_assign_avx2(acc, w) };
+            }
+        }
+        add_assign_scalar(acc, w)
+    }
+
+    /// Subtracts `w` from `acc`, element-wise.
+    #[inline]
+    pub fn sub_assign(self, acc: &mut [i16], w: &[i16]) {
+        debug_assert_eq!(acc.len(), w.len());
+        #[cfg(target_arch = "x86_64")]
+        {
+            if self.avx2 {
+                return unsafe { sub_assign_avx2(acc, w) };
+            }
+        }
+        sub_assign_scalar(acc, w)
+    }
+
+    /// Returns the dot product of `acc`, clamped to `[0, max]`, and `w`.
+    #[inline]
+    pub fn crelu_dot(self, acc: &[i16], w: &[i16], max: i16) -> i64 {
+        debug_assert_eq!(acc.len(), w.len());
+        #[cfg(target_arch = "x86_64")]
+        {
+            if self.avx2 {
+                return unsafe { crelu_dot_avx2(acc, w, max) };
+            }
+        }
+        crelu_dot_scalar(acc, w, max)
+    }
+}
+
+fn add_assign_scalar(acc: &mut [i16], w: &[i16]) {
+    for (a, b) in acc.iter_mut().zip(w.iter()) {
+        *a = a.wrapping_add(*b);
+    }
+}
+
+fn sub_assign_scalar(acc: &mut [i16], w: &[i16]) {
+    for (a, b) in acc.iter_mut().zip(w.iter()) {
+        *a = a.wrapping_sub(*b);
+    }
+}
+
+fn crelu_dot_scalar(acc: &[i16], w: &[i16], max: i16) -> i64 {
+    acc.iter().zip(w.iter())
+        .map(|(a, b)| i64::from((*a).max(0).min(max)) * i64::from(*b))
+        .sum()
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn add_assign_avx2(acc: &mut [i16], w: &[i16]) {
+    let chunks = acc.len() / LANES;
+    for i in 0..chunks {
+        let a = acc.as_mut_ptr().add(i * LANES) as *mut __m256i;
+        let b = w.as_ptr().add(i * LANES) as *const __m256i;
+        _mm256_storeu_si256(a, _mm256_add_epi16(_mm256_loadu_si256(a), _mm256_loadu_si256(b)));
+    }
+    let rem = chunks * LANES;
+    add_assign_scalar(&mut acc[rem..], &w[rem..]);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn sub_assign_avx2(acc: &mut [i16], w: &[i16]) {
+    let chunks = acc.len() / LANES;
+    for i in 0..chunks {
+        let a = acc.as_mut_ptr().add(i * LANES) as *mut __m256i;
+        let b = w.as_ptr().add(i * LANES) as *const __m256i;
+        _mm256_storeu_si256(a, _mm256_sub_epi16(_mm256_loadu_si256(a), _mm256_loadu_si256(b)));
+    }
+    let rem = chunks * LANES;
+    sub_assign_scalar(&mut acc[rem..], &w[rem..]);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn crelu_dot_avx2(acc: &[i16], w: &[i16], max: i16) -> i64 {
+    let chunks = acc.len() / LANES;
+    let zero = _mm256_setzero_si256();
+    let max_v = _mm256_set1_epi16(max);
+    let mut sum = _mm256_setzero_si256();
+    for i in 0..chunks {
+        let a = _mm256_loadu_si256(acc.as_ptr().add(i * LANES) as *const __m256i);
+        let b = _mm256_loadu_si256(w.as_ptr().add(i * LANES) as *const __m256i);
+        let clamped = _mm256_min_epi16(_mm256_max_epi16(a, zero), max_v);
+        // Multiplies the i16 lanes and adds adjacent pairs into i32 lanes, which are then
+        // widened into i64 lanes so long accumulators can't overflow.
+        let pairs = _mm256_madd_epi16(clamped, b);
+        sum = _mm256_add_epi64(sum, _mm256_cvtepi32_epi64(_mm256_castsi256_si128(pairs)));
+        sum = _mm256_add_epi64(sum, _mm256_cvtepi32_epi64(_mm256_extracti128_si256(pairs, 1)));
+    }
+
+    let mut lanes = [0i64; 4];
+    _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sum);
+    let rem = chunks * LANES;
+    lanes.iter().sum::<i64>() + crelu_dot_scalar(&acc[rem..], &w[rem..], max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{self,Rng};
+
+    #[test]
+    fn simd_matches_scalar() {
+        let simd = Simd::detect();
+        let mut rng = rand::thread_rng();
+        for &len in [1usize, 15, 16, 33, 256, 512].iter() {
+            let acc: Vec<i16> = (0..len).map(|_| rng.gen_range(-400, 400)).collect();
+            let w: Vec<i16> = (0..len).map(|_| rng.gen_range(-400, 400)).collect();
+
+            let mut added = acc.clone();
+            simd.add_assign(&mut added, &w);
+            let mut added_scalar = acc.clone();
+            add_assign_scalar(&mut added_scalar, &w);
+            assert_eq!(added, added_scalar);
+
+            let mut subbed = acc.clone();
+            simd.sub_assign(&mut subbed, &w);
+            let mut subbed_scalar = acc.clone();
+            sub_assign_scalar(&mut subbed_scalar, &w);
+            assert_eq!(subbed, subbed_scalar);
+
+            assert_eq!(simd.crelu_dot(&acc, &w, 255), crelu_dot_scalar(&acc, &w, 255));
+        }
+    }
+
+    #[test]
+    fn crelu_dot_no_overflow() {
+        let acc = vec![255i16; 512];
+        let w = vec![i16::max_value(); 512];
+        let expected = 512 * 255 * i64::from(i16::max_value());
+        assert_eq!(Simd::detect().crelu_dot(&acc, &w, 255), expected);
+        assert_eq!(Simd::scalar().crelu_dot(&acc, &w, 255), expected);
+    }
+}
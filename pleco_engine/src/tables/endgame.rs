@@ -0,0 +1,681 @@
+//! Specialized evaluation and scaling functions for known endgames.
+//!
+//! Endgames are registered by their material configuration, such as "KBNK", and looked up
+//! by the material key of a position. An evaluation function replaces the general evaluation
+//! entirely, while a scaling function only adjusts the end-game scale factor of the side
+//! holding the extra material.
+
+use std::sync::{Once, ONCE_INIT};
+use std::ptr;
+
+use pleco::{Board, Player, PieceType, SQ, BitBoard};
+use pleco::core::score::*;
+use pleco::core::ALL_PLAYERS;
+use pleco::helper::prelude::*;
+
+use super::material::{SCALE_FACTOR_DRAW, SCALE_FACTOR_NONE};
+
+/// Value of a position which is known to be won, but without a mate in sight.
+pub const KNOWN_WIN: Value = 10000;
+
+// Bonus for driving the defending king towards the edge of the board.
+const PUSH_TO_EDGES: [i32; 64] = [
+    100, 90, 80, 70, 70, 80, 90, 100,
+     90, 70, 60, 50, 50, 60, 70,  90,
+     80, 60, 40, 30, 30, 40, 60,  80,
+     70, 50, 30, 20, 20, 30, 50,  70,
+     70, 50, 30, 20, 20, 30, 50,  70,
+     80, 60, 40, 30, 30, 40, 60,  80,
+     90, 70, 60, 50, 50, 60, 70,  90,
+    100, 90, 80, 70, 70, 80, 90, 100
+];
+
+// Bonus for driving the defending king towards the A1 or H8 corners.
+const PUSH_TO_CORNERS: [i32; 64] = [
+    200, 190, 180, 170, 160, 150, 140, 130,
+    190, 180, 170, 160, 150, 140, 130, 140,
+    180, 170, 155, 140, 140, 125, 140, 150,
+    170, 160, 140, 120, 110, 140, 150, 160,
+    160, 150, 140, 110, 120, 140, 160, 170,
+    150, 140, 125, 140, 140, 155, 170, 180,
+    140, 130, 140, 150, 160, 170, 180, 190,
+    130, 140, 150, 160, 170, 180, 190, 200
+];
+
+// Bonus for keeping two pieces close to, or away from, each other, indexed by distance.
+const PUSH_CLOSE: [i32; 8] = [0, 0, 100, 80, 60, 40, 20, 10];
+const PUSH_AWAY: [i32; 8] = [0, 5, 20, 40, 60, 80, 90, 100];
+
+/// Evaluates a position of a known endgame, given the strong side.
+pub type EvalFn = fn(&Board, Player) -> Value;
+
+/// Returns the scale factor of a known endgame for the strong side, or `SCALE_FACTOR_NONE`
+/// if the position can't be judged.
+pub type ScaleFn = fn(&Board, Player) -> u8;
+
+/// A specialized evaluation function, along with the side holding the extra material.
+#[derive(Copy, Clone)]
+pub struct EndgameEval {
+    strong_side: Player,
+    func: EvalFn,
+}
+
+impl EndgameEval {
+    pub fn new(strong_side: Player, func: EvalFn) -> Self {
+        EndgameEval {strong_side, func}
+    }
+
+    pub fn strong_side(&self) -> Player {
+        self.strong_side
+    }
+
+    /// Evaluates the board, from the perspective of the side to move.
+    #[inline]
+    pub fn evaluate(&self, board: &Board) -> Value {
+        (self.func)(board, self.strong_side)
+    }
+}
+
+/// A specialized scaling function, along with the side holding the extra material.
+#[derive(Copy, Clone)]
+pub struct EndgameScale {
+    strong_side: Player,
+    func: ScaleFn,
+}
+
+impl EndgameScale {
+    pub fn new(strong_side: Player, func: ScaleFn) -> Self {
+        EndgameScale {strong_side, func}
+    }
+
+    pub fn strong_side(&self) -> Player {
+        self.strong_side
+    }
+
+    /// Returns the scale factor of the strong side, or `SCALE_FACTOR_NONE`.
+    #[inline]
+    pub fn scale(&self, board: &Board) -> u8 {
+        (self.func)(board, self.strong_side)
+    }
+}
+
+/// Registry of the specialized endgames, keyed by material key.
+pub struct Endgames {
+    evals: Vec<(u64, EndgameEval)>,
+    scales: Vec<(u64, EndgameScale)>,
+}
+
+impl Endgames {
+    fn new() -> Self {
+        let mut endgames = Endgames {
+            evals: Vec::new(),
+            scales: Vec::new(),
+        };
+
+        endgames.add_eval("KPK", eval_kpk);
+        endgames.add_eval("KNNK", eval_knnk);
+        endgames.add_eval("KBNK", eval_kbnk);
+        endgames.add_eval("KRKP", eval_krkp);
+        endgames.add_eval("KRKB", eval_krkb);
+        endgames.add_eval("KRKN", eval_krkn);
+        endgames.add_eval("KQKP", eval_kqkp);
+        endgames.add_eval("KQKR", eval_kqkr);
+
+        endgames.add_scale("KNPK", scale_knpk);
+        endgames.add_scale("KRPKR", scale_krpkr);
+        endgames.add_scale("KBPKB", scale_kbpkb);
+        endgames.add_scale("KBPKN", scale_kbpkn);
+        endgames
+    }
+
+    fn add_eval(&mut self, code: &str, func: EvalFn) {
+        for &player in ALL_PLAYERS.iter() {
+            self.evals.push((material_key(code, player), EndgameEval::new(player, func)));
+        }
+    }
+
+    fn add_scale(&mut self, code: &str, func: ScaleFn) {
+        for &player in ALL_PLAYERS.iter() {
+            self.scales.push((material_key(code, player), EndgameScale::new(player, func)));
+        }
+    }
+
+    /// Returns the evaluation function of a material configuration, if one exists.
+    pub fn probe_eval(&self, key: u64) -> Option<EndgameEval> {
+        self.evals.iter().find(|e| e.0 == key).map(|e| e.1)
+    }
+
+    /// Returns the scaling function of a material configuration, if one exists.
+    pub fn probe_scale(&self, key: u64) -> Option<EndgameScale> {
+        self.scales.iter().find(|e| e.0 == key).map(|e| e.1)
+    }
+}
+
+static ENDGAMES_INIT: Once = ONCE_INIT;
+static mut ENDGAMES: *const Endgames = ptr::null();
+
+/// Returns the registry of endgames, creating it upon first use.
+pub fn endgames() -> &'static Endgames {
+    unsafe {
+        ENDGAMES_INIT.call_once(|| {
+            ENDGAMES = Box::into_raw(Box::new(Endgames::new()));
+        });
+        &*ENDGAMES
+    }
+}
+
+/// Generic mate evaluation for a king and pieces against a lone king.
+pub fn eval_kxk(board: &Board, strong_side: Player) -> Value {
+    let weak_side = !strong_side;
+
+    // Stalemate can only be detected when the lone king is to move.
+    if board.turn() == weak_side && board.generate_moves().is_empty() {
+        return DRAW;
+    }
+
+    let winner_ksq = board.king_sq(strong_side);
+    let loser_ksq = board.king_sq(weak_side);
+
+    let mut result = board.non_pawn_material(strong_side)
+        + board.count_piece(strong_side, PieceType::P) as Value * PAWN_EG
+        + PUSH_TO_EDGES[loser_ksq.0 as usize]
+        + PUSH_CLOSE[winner_ksq.distance(loser_ksq) as usize];
+
+    let bishops = board.piece_bb(strong_side, PieceType::B);
+    if board.piece_two_bb_both_players(PieceType::Q, PieceType::R).is_not_empty()
+        || (bishops.is_not_empty() && board.count_piece(strong_side, PieceType::N) > 0)
+        || (bishops & BitBoard::DARK_SQUARES).is_not_empty()
+            && (bishops & !BitBoard::DARK_SQUARES).is_not_empty() {
+        result = (result + KNOWN_WIN).min(MATE_IN_MAX_PLY - 1);
+    }
+
+    relative(board, strong_side, result)
+}
+
+// KP vs K. The result is looked up in the KPK bitbase.
+fn eval_kpk(board: &Board, strong_side: Player) -> Value {
+    let psq = board.piece_bb(strong_side, PieceType::P).to_sq();
+    if !probe_kpk(strong_side, board.king_sq(strong_side), psq, board.king_sq(!strong_side), board.turn()) {
+        return DRAW;
+    }
+
+    let result = KNOWN_WIN + PAWN_EG + strong_side.relative_rank_of_sq(psq) as Value;
+    relative(board, strong_side, result)
+}
+
+// K and two knights vs K. Mate can't be forced.
+fn eval_knnk(_board: &Board, _strong_side: Player) -> Value {
+    DRAW
+}
+
+// KBN vs K. Mate is forced by driving the king into a corner of the bishop's color.
+fn eval_kbnk(board: &Board, strong_side: Player) -> Value {
+    let mut winner_ksq = board.king_sq(strong_side);
+    let mut loser_ksq = board.king_sq(!strong_side);
+    let bishop_sq = board.piece_bb(strong_side, PieceType::B).to_sq();
+
+    // PUSH_TO_CORNERS drives towards A1 or H8. With a bishop that can't reach those squares,
+    // the kings are flipped to drive towards A8 or H1 instead.
+    if bishop_sq.opposite_colors(SQ::A1) {
+        winner_ksq = winner_ksq.flip();
+        loser_ksq = loser_ksq.flip();
+    }
+
+    let result = KNOWN_WIN
+        + PUSH_CLOSE[winner_ksq.distance(loser_ksq) as usize]
+        + PUSH_TO_CORNERS[loser_ksq.0 as usize];
+
+    relative(board, strong_side, result)
+}
+
+// KR vs KP. Won if the attacking king is in front of the pawn or the defending king is
+// too far away. Otherwise, the evaluation depends on the kings' distances to the pawn.
+fn eval_krkp(board: &Board, strong_side: Player) -> Value {
+    let wksq = strong_side.relative_square(board.king_sq(strong_side));
+    let bksq = strong_side.relative_square(board.king_sq(!strong_side));
+    let rsq = strong_side.relative_square(board.piece_bb(strong_side, PieceType::R).to_sq());
+    let psq = strong_side.relative_square(board.piece_bb(!strong_side, PieceType::P).to_sq());
+    let queening_sq = SQ(psq.file_idx_of_sq());
+    let strong_to_move = board.turn() == strong_side;
+
+    let result = if wksq.file_idx_of_sq() == psq.file_idx_of_sq() && wksq.0 < psq.0 {
+        ROOK_EG - wksq.distance(psq) as Value
+    } else if bksq.distance(psq) >= 3 + !strong_to_move as u8 && bksq.distance(rsq) >= 3 {
+        ROOK_EG - wksq.distance(psq) as Value
+    } else if bksq.rank_idx_of_sq() <= 2
+        && bksq.distance(psq) == 1
+        && wksq.rank_idx_of_sq() >= 3
+        && wksq.distance(psq) > 2 + strong_to_move as u8 {
+        80 - 8 * wksq.distance(psq) as Value
+    } else {
+        let stop_sq = SQ(psq.0 - 8);
+        200 - 8 * (wksq.distance(stop_sq) as Value
+                   - bksq.distance(stop_sq) as Value
+                   - psq.distance(queening_sq) as Value)
+    };
+
+    relative(board, strong_side, result)
+}
+
+// KR vs KB. Generally drawn, but the defending king is pushed towards the edge.
+fn eval_krkb(board: &Board, strong_side: Player) -> Value {
+    let result = PUSH_TO_EDGES[board.king_sq(!strong_side).0 as usize];
+    relative(board, strong_side, result)
+}
+
+// KR vs KN. Drawish, but the chances increase with the defending king near the edge
+// and the knight far from its king.
+fn eval_krkn(board: &Board, strong_side: Player) -> Value {
+    let bksq = board.king_sq(!strong_side);
+    let bnsq = board.piece_bb(!strong_side, PieceType::N).to_sq();
+    let result = PUSH_TO_EDGES[bksq.0 as usize] + PUSH_AWAY[bksq.distance(bnsq) as usize];
+    relative(board, strong_side, result)
+}
+
+// KQ vs KP. Won, unless the pawn is a rook or bishop pawn on its seventh rank,
+// supported by its king.
+fn eval_kqkp(board: &Board, strong_side: Player) -> Value {
+    let weak_side = !strong_side;
+    let winner_ksq = board.king_sq(strong_side);
+    let loser_ksq = board.king_sq(weak_side);
+    let psq = board.piece_bb(weak_side, PieceType::P).to_sq();
+
+    let mut result = PUSH_CLOSE[winner_ksq.distance(loser_ksq) as usize];
+    let file = psq.file_idx_of_sq();
+    if weak_side.relative_rank_of_sq(psq) as u8 != 6
+        || loser_ksq.distance(psq) != 1
+        || !(file == 0 || file == 2 || file == 5 || file == 7) {
+        result += QUEEN_EG - PAWN_EG;
+    }
+
+    relative(board, strong_side, result)
+}
+
+// KQ vs KR. Always won, by driving the defending king to the edge with the attacking king.
+fn eval_kqkr(board: &Board, strong_side: Player) -> Value {
+    let winner_ksq = board.king_sq(strong_side);
+    let loser_ksq = board.king_sq(!strong_side);
+    let result = QUEEN_EG - ROOK_EG
+        + PUSH_TO_EDGES[loser_ksq.0 as usize]
+        + PUSH_CLOSE[winner_ksq.distance(loser_ksq) as usize];
+    relative(board, strong_side, result)
+}
+
+// KNP vs K. A rook pawn on the seventh rank is drawn with the defending king in the corner.
+fn scale_knpk(board: &Board, strong_side: Player) -> u8 {
+    let psq = normalize(board, strong_side, board.piece_bb(strong_side, PieceType::P).to_sq());
+    let bksq = normalize(board, strong_side, board.king_sq(!strong_side));
+
+    if psq == SQ::A7 && bksq.distance(SQ::A8) <= 1 {
+        return SCALE_FACTOR_DRAW;
+    }
+    SCALE_FACTOR_NONE
+}
+
+// KRP vs KR. Recognizes the standard drawing defences, such as the Philidor position.
+fn scale_krpkr(board: &Board, strong_side: Player) -> u8 {
+    let weak_side = !strong_side;
+    let wksq = normalize(board, strong_side, board.king_sq(strong_side));
+    let bksq = normalize(board, strong_side, board.king_sq(weak_side));
+    let wrsq = normalize(board, strong_side, board.piece_bb(strong_side, PieceType::R).to_sq());
+    let wpsq = normalize(board, strong_side, board.piece_bb(strong_side, PieceType::P).to_sq());
+    let brsq = normalize(board, strong_side, board.piece_bb(weak_side, PieceType::R).to_sq());
+
+    let r = wpsq.rank_idx_of_sq();
+    let queening_sq = SQ(56 + wpsq.file_idx_of_sq());
+    let tempo = (board.turn() == strong_side) as u8;
+
+    // Third rank defence: the defending king guards the queening square, and the rook
+    // keeps the attacking king from advancing.
+    if r <= 4
+        && bksq.distance(queening_sq) <= 1
+        && wksq.0 <= SQ::H5.0
+        && (brsq.rank_idx_of_sq() == 5 || (r <= 2 && wrsq.rank_idx_of_sq() != 5)) {
+        return SCALE_FACTOR_DRAW;
+    }
+
+    // Once the pawn has reached the sixth rank, the defending rook checks from behind.
+    if r == 5
+        && bksq.distance(queening_sq) <= 1
+        && wksq.rank_idx_of_sq() + tempo <= 5
+        && (brsq.rank_idx_of_sq() == 0
+            || (tempo == 0 && brsq.file_idx_of_sq().max(wpsq.file_idx_of_sq())
+                              - brsq.file_idx_of_sq().min(wpsq.file_idx_of_sq()) >= 3)) {
+        return SCALE_FACTOR_DRAW;
+    }
+
+    if r >= 5
+        && bksq == queening_sq
+        && brsq.rank_idx_of_sq() == 0
+        && (tempo == 0 || wksq.distance(wpsq) >= 2) {
+        return SCALE_FACTOR_DRAW;
+    }
+
+    // The defending king blocks the pawn, and the attacking king is too far away.
+    if r <= 4
+        && bksq.0 == wpsq.0 + 8
+        && wksq.distance(wpsq) >= 2 + tempo
+        && wksq.distance(brsq) >= 2 + tempo {
+        return SCALE_FACTOR_DRAW;
+    }
+
+    SCALE_FACTOR_NONE
+}
+
+// KBP vs KB. Drawn when the defending king blocks the pawn on a square the bishop can't
+// attack, or with opposite colored bishops when the pawn can be stopped.
+fn scale_kbpkb(board: &Board, strong_side: Player) -> u8 {
+    let weak_side = !strong_side;
+    let psq = board.piece_bb(strong_side, PieceType::P).to_sq();
+    let strong_bsq = board.piece_bb(strong_side, PieceType::B).to_sq();
+    let weak_bsq = board.piece_bb(weak_side, PieceType::B).to_sq();
+    let weak_ksq = board.king_sq(weak_side);
+
+    if king_blocks_pawn(strong_side, psq, weak_ksq, strong_bsq) {
+        return SCALE_FACTOR_DRAW;
+    }
+
+    if strong_bsq.opposite_colors(weak_bsq) {
+        if strong_side.relative_rank_of_sq(psq) as u8 <= 4 {
+            return SCALE_FACTOR_DRAW;
+        }
+
+        let path = forward_file_bb(strong_side, psq);
+        if (path & weak_ksq.to_bb()).is_not_empty() {
+            return SCALE_FACTOR_DRAW;
+        }
+
+        if (board.attacks_from(PieceType::B, weak_bsq, weak_side) & path).is_not_empty()
+            && weak_bsq.distance(psq) >= 3 {
+            return SCALE_FACTOR_DRAW;
+        }
+    }
+    SCALE_FACTOR_NONE
+}
+
+// KBP vs KN. Drawn when the defending king blocks the pawn on a square the bishop can't attack.
+fn scale_kbpkn(board: &Board, strong_side: Player) -> u8 {
+    let psq = board.piece_bb(strong_side, PieceType::P).to_sq();
+    let strong_bsq = board.piece_bb(strong_side, PieceType::B).to_sq();
+    let weak_ksq = board.king_sq(!strong_side);
+
+    if king_blocks_pawn(strong_side, psq, weak_ksq, strong_bsq) {
+        return SCALE_FACTOR_DRAW;
+    }
+    SCALE_FACTOR_NONE
+}
+
+/// Generic scaling for a pawn against a pawn. If the position is a draw once the weak side's
+/// pawn is removed, it's probably at least a draw with the pawn too.
+pub fn scale_kpkp(board: &Board, strong_side: Player) -> u8 {
+    let psq = board.piece_bb(strong_side, PieceType::P).to_sq();
+
+    // With the pawn on the fifth rank or further, it's too dangerous to assume a draw.
+    if strong_side.relative_rank_of_sq(psq) as u8 >= 4 && psq.file_idx_of_sq() != 0
+        && psq.file_idx_of_sq() != 7 {
+        return SCALE_FACTOR_NONE;
+    }
+
+    if probe_kpk(strong_side, board.king_sq(strong_side), psq, board.king_sq(!strong_side), board.turn()) {
+        SCALE_FACTOR_NONE
+    } else {
+        SCALE_FACTOR_DRAW
+    }
+}
+
+/// Generic scaling for a bishop and pawns against a bare king. If all the pawns are on a
+/// rook file with a queening square of the wrong color, and the defending king reaches
+/// that square, the position is drawn.
+pub fn scale_kbpsk(board: &Board, strong_side: Player) -> u8 {
+    let pawns = board.piece_bb(strong_side, PieceType::P);
+    let pawns_file = pawns.lsb().to_sq().file_idx_of_sq();
+
+    if (pawns_file == 0 || pawns_file == 7) && (pawns & !SQ(pawns_file).file_bb()).is_empty() {
+        let bishop_sq = board.piece_bb(strong_side, PieceType::B).to_sq();
+        let queening_sq = strong_side.relative_square(SQ(56 + pawns_file));
+        let ksq = board.king_sq(!strong_side);
+
+        if queening_sq.opposite_colors(bishop_sq) && queening_sq.distance(ksq) <= 1 {
+            return SCALE_FACTOR_DRAW;
+        }
+    }
+    SCALE_FACTOR_NONE
+}
+
+/// Generic scaling for pawns against a bare king. If all the pawns are on a single rook
+/// file, and the defending king is in front of them, the position is drawn.
+pub fn scale_kpsk(board: &Board, strong_side: Player) -> u8 {
+    let ksq = board.king_sq(!strong_side);
+    let pawns = board.piece_bb(strong_side, PieceType::P);
+    let pawns_file = pawns.lsb().to_sq().file_idx_of_sq();
+
+    if (pawns & !forward_rank_bb(!strong_side, ksq.rank())).is_empty()
+        && (pawns_file == 0 || pawns_file == 7)
+        && (pawns & !SQ(pawns_file).file_bb()).is_empty()
+        && (ksq.file_idx_of_sq() as i8 - pawns_file as i8).abs() <= 1 {
+        return SCALE_FACTOR_DRAW;
+    }
+    SCALE_FACTOR_NONE
+}
+
+// Determines if the defending king stands in front of the pawn, and can't be driven
+// away by the bishop.
+fn king_blocks_pawn(strong_side: Player, psq: SQ, weak_ksq: SQ, strong_bsq: SQ) -> bool {
+    weak_ksq.file_idx_of_sq() == psq.file_idx_of_sq()
+        && (strong_side.relative_rank_of_sq(psq) as u8) < strong_side.relative_rank_of_sq(weak_ksq) as u8
+        && (weak_ksq.opposite_colors(strong_bsq)
+            || strong_side.relative_rank_of_sq(weak_ksq) as u8 <= 5)
+}
+
+// Maps a square as if the strong side was white, and its pawn was on files A-D.
+fn normalize(board: &Board, strong_side: Player, sq: SQ) -> SQ {
+    let mut sq = strong_side.relative_square(sq);
+    let pawns = board.piece_bb(strong_side, PieceType::P);
+    if pawns.is_not_empty() && pawns.lsb().to_sq().file_idx_of_sq() >= 4 {
+        sq = SQ(sq.0 ^ 7);
+    }
+    sq
+}
+
+// Converts a value from the strong side's perspective to the side to move's.
+fn relative(board: &Board, strong_side: Player, value: Value) -> Value {
+    if board.turn() == strong_side {
+        value
+    } else {
+        -value
+    }
+}
+
+// Creates the material key of an endgame code, such as "KBPKN", where the pieces of the
+// strong side are listed first.
+fn material_key(code: &str, strong_side: Player) -> u64 {
+    let weak_start = code[1..].find('K').unwrap() + 1;
+    let (strong, weak) = code.split_at(weak_start);
+    let (white, black) = match strong_side {
+        Player::White => (strong.to_string(), weak.to_string()),
+        Player::Black => (weak.to_string(), strong.to_string()),
+    };
+    let fen = format!("8/{}/8/8/8/8/{}/8 w - - 0 1",
+                      fen_rank(&black.to_lowercase()), fen_rank(&white));
+    Board::from_fen(&fen).unwrap().material_key()
+}
+
+fn fen_rank(pieces: &str) -> String {
+    assert!(pieces.len() <= 7);
+    format!("{}{}", pieces, 8 - pieces.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tables::material::{Material, SCALE_FACTOR_NORMAL};
+
+    fn eval(fen: &str) -> Value {
+        let board = Board::from_fen(fen).unwrap();
+        endgames().probe_eval(board.material_key())
+            .expect("no evaluation function")
+            .evaluate(&board)
+    }
+
+    fn scale(fen: &str) -> u8 {
+        let board = Board::from_fen(fen).unwrap();
+        let sf = endgames().probe_scale(board.material_key())
+            .expect("no scaling function");
+        sf.scale(&board)
+    }
+
+    #[test]
+    fn registry_keys() {
+        let board = Board::from_fen("8/8/8/3k4/8/8/2N5/4K1B1 w - - 0 1").unwrap();
+        let sf = endgames().probe_eval(board.material_key()).unwrap();
+        assert_eq!(sf.strong_side(), Player::White);
+
+        let board = Board::from_fen("8/2k1n3/8/8/8/1R6/8/6K1 b - - 0 1").unwrap();
+        assert!(endgames().probe_eval(board.material_key()).is_some());
+
+        let board = Board::from_fen("1q2k3/8/8/8/8/8/8/3RK3 w - - 0 1").unwrap();
+        assert_eq!(endgames().probe_eval(board.material_key()).unwrap().strong_side(), Player::Black);
+        assert!(endgames().probe_eval(Board::start_pos().material_key()).is_none());
+    }
+
+    #[test]
+    fn kpk() {
+        // Attacking king on a key square.
+        assert!(eval("3k4/8/3K4/8/3P4/8/8/8 w - - 0 1") > KNOWN_WIN);
+        assert!(eval("3k4/8/3K4/8/3P4/8/8/8 b - - 0 1") < -KNOWN_WIN);
+        // Defending king in front of the pawn, with the opposition.
+        assert_eq!(eval("8/8/3k4/8/3P4/3K4/8/8 w - - 0 1"), DRAW);
+        assert_eq!(eval("8/3k4/8/3K4/3P4/8/8/8 w - - 0 1"), DRAW);
+        assert!(eval("8/3k4/8/3K4/3P4/8/8/8 b - - 0 1") < -KNOWN_WIN);
+        // Rook pawn with the defending king in the corner.
+        assert_eq!(eval("k7/8/8/8/8/8/P7/1K6 w - - 0 1"), DRAW);
+        assert_eq!(eval("1k6/8/8/8/8/8/7p/6K1 b - - 0 1"), DRAW);
+        // The defending king is outside of the square of the pawn.
+        assert!(eval("8/8/8/8/P7/8/7k/K7 b - - 0 1") < -KNOWN_WIN);
+        assert!(eval("K7/7k/8/8/p7/8/8/8 b - - 0 1") > KNOWN_WIN);
+        // The undefended pawn is captured.
+        assert_eq!(eval("8/8/8/8/8/3k4/3P4/7K b - - 0 1"), DRAW);
+    }
+
+    #[test]
+    fn kbnk() {
+        // Dark squared bishop, so the king must be driven to A1 or H8.
+        let right_corner = eval("7k/8/5K2/8/8/8/8/2B1N3 w - - 0 1");
+        let wrong_corner = eval("k7/8/2K5/8/8/8/8/2B1N3 w - - 0 1");
+        assert!(right_corner > wrong_corner);
+        assert!(wrong_corner > KNOWN_WIN);
+
+        // Light squared bishop, so the king must be driven to A8 or H1.
+        let right_corner = eval("k7/8/2K5/8/8/8/8/3BN3 w - - 0 1");
+        let wrong_corner = eval("7k/8/5K2/8/8/8/8/3BN3 w - - 0 1");
+        assert!(right_corner > wrong_corner);
+        assert!(eval("k7/8/2K5/8/8/8/8/3BN3 b - - 0 1") < -KNOWN_WIN);
+    }
+
+    #[test]
+    fn knnk() {
+        assert_eq!(eval("8/8/3k4/8/8/8/2NN4/4K3 w - - 0 1"), DRAW);
+    }
+
+    #[test]
+    fn krkp() {
+        // The attacking king stands in front of the pawn.
+        assert!(eval("8/8/8/k7/1p6/8/1K6/7R w - - 0 1") > ROOK_EG - 10);
+        // The pawn is far advanced, supported by its king, and the attacking king is far away.
+        let drawish = eval("R6K/8/8/8/8/3k4/2p5/8 w - - 0 1");
+        assert!(drawish.abs() < PAWN_EG);
+    }
+
+    #[test]
+    fn krkb_krkn() {
+        let edge = eval("3k4/8/3K4/8/8/8/5b2/7R w - - 0 1");
+        let center = eval("8/8/3K4/8/3k4/8/5b2/7R w - - 0 1");
+        assert!(edge > center && center > 0);
+
+        let knight_far = eval("k7/8/1K6/8/8/8/8/6nR w - - 0 1");
+        let knight_near = eval("k7/1n6/1K6/8/8/8/8/7R w - - 0 1");
+        assert!(knight_far > knight_near);
+        assert!(eval("k7/8/1K6/8/8/8/8/6nR b - - 0 1") < 0);
+    }
+
+    #[test]
+    fn kqkp() {
+        // Bishop pawn on the seventh, supported by its king.
+        let drawish = eval("8/7Q/8/8/8/8/1kp5/5K2 w - - 0 1");
+        assert!(drawish < PAWN_EG);
+        // Center pawn, which can't be saved.
+        let won = eval("8/7Q/8/8/8/8/2kp4/5K2 w - - 0 1");
+        assert!(won > QUEEN_EG - PAWN_EG);
+    }
+
+    #[test]
+    fn kqkr() {
+        let v = eval("3k4/8/3K4/8/8/8/5r2/7Q w - - 0 1");
+        assert!(v > QUEEN_EG - ROOK_EG);
+        assert_eq!(eval("3k4/8/3K4/8/8/8/5r2/7Q b - - 0 1"), -v);
+    }
+
+    #[test]
+    fn kxk() {
+        let board = Board::from_fen("8/8/8/3k4/8/8/8/Q3K3 w - - 0 1").unwrap();
+        assert!(eval_kxk(&board, Player::White) > KNOWN_WIN);
+        // Stalemate.
+        let board = Board::from_fen("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(eval_kxk(&board, Player::White), DRAW);
+    }
+
+    #[test]
+    fn krpkr() {
+        // Philidor's third rank defence.
+        assert_eq!(scale("4k3/8/r7/4K3/4P3/8/8/7R w - - 0 1"), SCALE_FACTOR_DRAW);
+        // Defending king cut off from the pawn.
+        assert_eq!(scale("8/8/r7/4K3/4P3/6k1/8/7R w - - 0 1"), SCALE_FACTOR_NONE);
+    }
+
+    #[test]
+    fn minor_piece_scaling() {
+        assert_eq!(scale("k7/P7/8/8/8/8/2N5/4K3 w - - 0 1"), SCALE_FACTOR_DRAW);
+        assert_eq!(scale("8/P7/8/8/8/5k2/2N5/4K3 w - - 0 1"), SCALE_FACTOR_NONE);
+
+        // Opposite colored bishops, and a pawn which hasn't advanced far.
+        assert_eq!(scale("8/3k4/2b5/8/3P4/8/5B2/4K3 w - - 0 1"), SCALE_FACTOR_DRAW);
+        // The defending king blocks the pawn on a square the bishop can't attack.
+        assert_eq!(scale("8/8/3k4/8/3P4/8/8/2B1K1n1 w - - 0 1"), SCALE_FACTOR_DRAW);
+        assert_eq!(scale("8/8/8/3P4/8/1k6/8/2B1K1n1 w - - 0 1"), SCALE_FACTOR_NONE);
+    }
+
+    #[test]
+    fn generic_scaling() {
+        let wrong_bishop = Board::from_fen("7k/8/8/8/8/7P/4B3/4K3 w - - 0 1").unwrap();
+        assert_eq!(scale_kbpsk(&wrong_bishop, Player::White), SCALE_FACTOR_DRAW);
+        let right_bishop = Board::from_fen("7k/8/8/8/8/7P/3B4/4K3 w - - 0 1").unwrap();
+        assert_eq!(scale_kbpsk(&right_bishop, Player::White), SCALE_FACTOR_NONE);
+
+        let rook_pawns = Board::from_fen("k7/8/8/8/P7/P7/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(scale_kpsk(&rook_pawns, Player::White), SCALE_FACTOR_DRAW);
+        let split_pawns = Board::from_fen("k7/8/8/8/P7/7P/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(scale_kpsk(&split_pawns, Player::White), SCALE_FACTOR_NONE);
+
+        let blocked = Board::from_fen("8/8/8/8/3p4/3k4/3P4/3K4 w - - 0 1").unwrap();
+        assert_eq!(scale_kpkp(&blocked, Player::White), SCALE_FACTOR_DRAW);
+        let won = Board::from_fen("8/p7/3K4/8/3P4/8/8/3k4 w - - 0 1").unwrap();
+        assert_eq!(scale_kpkp(&won, Player::White), SCALE_FACTOR_NONE);
+    }
+
+    #[test]
+    fn material_entry_functions() {
+        let mut material = Material::new();
+
+        let board = Board::from_fen("8/8/3k4/8/8/8/2NN4/4K3 w - - 0 1").unwrap();
+        assert_eq!(material.probe(&board).specialized_eval(&board), Some(DRAW));
+
+        let board = Board::from_fen("4k3/8/r7/4K3/4P3/8/8/7R w - - 0 1").unwrap();
+        let entry = material.probe(&board);
+        assert!(entry.specialized_eval(&board).is_none());
+        assert_eq!(entry.scale_factor(&board, Player::White), SCALE_FACTOR_DRAW);
+        assert_eq!(entry.scale_factor(&board, Player::Black), SCALE_FACTOR_NORMAL);
+
+        let board = Board::from_fen("3k4/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(material.probe(&board).specialized_eval(&board).unwrap() > KNOWN_WIN);
+    }
+}
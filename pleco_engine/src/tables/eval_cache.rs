@@ -0,0 +1,96 @@
+//! A small table caching full static evaluations, so positions reached again through a
+//! transposition or a re-search aren't evaluated twice.
+//!
+//! An entry is retrieved from the `zobrist` key of a `Board`, mixed with anything else the
+//! evaluation depends on, such as contempt. Only full evaluations are stored, never those cut
+//! short by lazy evaluation.
+
+use pleco::core::score::*;
+use pleco::tools::{PreFetchable,prefetch_write};
+
+use super::{TableBase,TableBaseConst};
+
+/// A cached evaluation.
+pub struct EvalEntry {
+    key: u64,
+    value: Value,
+}
+
+impl TableBaseConst for EvalEntry {
+    const ENTRY_COUNT: usize = 8192;
+}
+
+/// Table of cached evaluations.
+pub struct EvalCache {
+    table: TableBase<EvalEntry>,
+}
+
+unsafe impl Send for EvalCache {}
+
+impl EvalCache {
+    /// Creates a new, empty `EvalCache`.
+    pub fn new() -> Self {
+        EvalCache {
+            table: TableBase::new().unwrap()
+        }
+    }
+
+    /// Returns the cached evaluation of a key, if any.
+    #[inline(always)]
+    pub fn probe(&mut self, key: u64) -> Option<Value> {
+        let entry = self.table.get_mut(key);
+        if entry.key == key && key != 0 {
+            Some(entry.value)
+        } else {
+            None
+        }
+    }
+
+    /// Caches the evaluation of a key, replacing whatever was in its slot.
+    #[inline(always)]
+    pub fn store(&mut self, key: u64, value: Value) {
+        let entry = self.table.get_mut(key);
+        entry.key = key;
+        entry.value = value;
+    }
+
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+}
+
+impl PreFetchable for EvalCache {
+    /// Pre-fetches a particular key. This means bringing it into the cache for faster eventual
+    /// access.
+    #[inline(always)]
+    fn prefetch(&self, key: u64) {
+        unsafe {
+            let ptr = self.table.get_ptr(key);
+            prefetch_write(ptr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_and_store() {
+        let mut cache = EvalCache::new();
+        let key: u64 = 0x1234_5678_9ABC_DEF0;
+        assert_eq!(cache.probe(key), None);
+        cache.store(key, -57);
+        assert_eq!(cache.probe(key), Some(-57));
+
+        // A key sharing the same slot replaces the entry.
+        let other = key ^ (1 << 40);
+        assert_eq!(cache.probe(other), None);
+        cache.store(other, 12);
+        assert_eq!(cache.probe(other), Some(12));
+        assert_eq!(cache.probe(key), None);
+
+        cache.clear();
+        assert_eq!(cache.probe(other), None);
+    }
+}
@@ -1,12 +1,15 @@
 //! Table to map from position -> material value;
 
 use pleco::{Player, Board, PieceType};
+use pleco::core::ALL_PLAYERS;
 use pleco::core::masks::{PLAYER_CNT,PIECE_TYPE_CNT};
 use pleco::core::score::*;
 use pleco::core::mono_traits::*;
 use pleco::tools::{PreFetchable, prefetch_write};
 
 use super::{TableBase,TableBaseConst};
+use tune;
+use super::endgame::{self,EndgameEval,EndgameScale};
 
 pub const PHASE_END_GAME: u16 = 0;
 pub const PHASE_MID_GAME: u16 = 128;
@@ -17,33 +20,22 @@ pub const SCALE_FACTOR_NORMAL: u8  = 64;
 pub const SCALE_FACTOR_MAX: u8    = 128;
 pub const SCALE_FACTOR_NONE: u8   = 255;
 
-// Polynomial material imbalance parameters
-const QUADRATIC_OURS: [[i32; PIECE_TYPE_CNT - 2]; PIECE_TYPE_CNT - 2] = [
-    [1667,    0,   0,     0,     0,   0], // Bishop pair
-    [  40,    0,   0,     0,     0,   0], // Pawn
-    [  32,  255,  -3,     0,     0,   0], // Knight      OUR PIECES
-    [   0,  104,   4,     0,     0,   0], // Bishop
-    [ -26,   -2,  47,   105,  -149,   0], // Rook
-    [-189,   24, 117,   133,  -134, -10]  // Queen
-]; // pair pawn knight bishop rook queen
-   //            OUR PIECES
-
-
-const QUADRATIC_THEIRS: [[i32; PIECE_TYPE_CNT - 2]; PIECE_TYPE_CNT - 2] = [
-    [   0,    0,   0,     0,    0,    0 ], // Bishop pair
-    [  36,    0,   0,     0,    0,    0 ], // Pawn
-    [   9,   63,   0,     0,    0,    0 ], // Knight      OUR PIECES
-    [  59,   65,  42,     0,    0,    0 ], // Bishop
-    [  46,   39,  24,   -24,    0,    0 ], // Rook
-    [  97,  100, -42,   137,  268,    0 ]  // Queen
-]; // pair pawn knight bishop rook queen
-   //           THEIR PIECES
+/// Returns the index of a pair of piece types in the material imbalance tables,
+/// `tune::QUADRATIC_OURS` and `tune::QUADRATIC_THEIRS`. Only the lower triangle of each
+/// table is stored, so `pt2` must be at most `pt1`.
+#[inline(always)]
+pub fn imbalance_index(pt1: usize, pt2: usize) -> usize {
+    debug_assert!(pt2 <= pt1);
+    pt1 * (pt1 + 1) / 2 + pt2
+}
 
 pub struct MaterialEntry {
     key: u64,
     pub value: Value,
     pub factor: [u8; PLAYER_CNT],
-    pub phase: u16
+    pub phase: u16,
+    eval_func: Option<EndgameEval>,
+    scaling_func: [Option<EndgameScale>; PLAYER_CNT],
 }
 
 
@@ -53,8 +45,23 @@ impl MaterialEntry {
         Score(self.value, self.value)
     }
 
+    /// Returns the evaluation of a known endgame from the side to move's perspective, if
+    /// the material configuration has a specialized evaluation function.
     #[inline(always)]
-    pub fn scale_factor(&self, player: Player) -> u8 {
+    pub fn specialized_eval(&self, board: &Board) -> Option<Value> {
+        self.eval_func.map(|f| f.evaluate(board))
+    }
+
+    /// Returns the scale factor of a player. A scaling function for the material configuration
+    /// takes precedence over the generic factor.
+    #[inline(always)]
+    pub fn scale_factor(&self, board: &Board, player: Player) -> u8 {
+        if let Some(func) = self.scaling_func[player as usize] {
+            let sf = func.scale(board);
+            if sf != SCALE_FACTOR_NONE {
+                return sf;
+            }
+        }
         self.factor[player as usize]
     }
 }
@@ -100,6 +107,13 @@ impl Material {
         self.table.clear();
     }
 
+    /// Forces the entry of `board` to be evaluated again on its next probe, such as after
+    /// the parameters it depends on have changed.
+    pub fn invalidate(&mut self, board: &Board) {
+        let key: u64 = board.material_key();
+        self.table.get_mut(key).key = !key;
+    }
+
     pub fn probe(&mut self, board: &Board) -> &mut MaterialEntry {
         let key: u64 = board.material_key();
         let entry: &mut MaterialEntry = self.table.get_mut(key);
@@ -108,7 +122,10 @@ impl Material {
         }
 
         entry.key = key;
+        entry.value = 0;
         entry.factor = [SCALE_FACTOR_NORMAL; PLAYER_CNT];
+        entry.eval_func = None;
+        entry.scaling_func = [None; PLAYER_CNT];
 
         let npm_w: Value = board.non_pawn_material(Player::White);
         let npm_b: Value = board.non_pawn_material(Player::Black);
@@ -116,6 +133,53 @@ impl Material {
 
         entry.phase = (((npm - END_GAME_LIMIT) * PHASE_MID_GAME as i32) / (MID_GAME_LIMIT - END_GAME_LIMIT)) as u16;
 
+        // Look for a specialized evaluation function, first by the exact material
+        // configuration, and then for a lone king against enough material to mate.
+        let endgames = endgame::endgames();
+        entry.eval_func = endgames.probe_eval(key);
+        if entry.eval_func.is_some() {
+            return entry;
+        }
+
+        for &player in ALL_PLAYERS.iter() {
+            if board.count_pieces_player(!player) == 1
+                && board.non_pawn_material(player) >= ROOK_MG {
+                entry.eval_func = Some(EndgameEval::new(player, endgame::eval_kxk));
+                return entry;
+            }
+        }
+
+        // Otherwise, look for a scaling function for the strong side.
+        if let Some(func) = endgames.probe_scale(key) {
+            entry.scaling_func[func.strong_side() as usize] = Some(func);
+            return entry;
+        }
+
+        // Generic scaling functions, which apply to more than one material configuration.
+        for &player in ALL_PLAYERS.iter() {
+            if board.non_pawn_material(player) == BISHOP_MG
+                && board.count_piece(player, PieceType::B) == 1
+                && board.count_piece(player, PieceType::P) >= 1
+                && board.count_pieces_player(!player) == 1 {
+                entry.scaling_func[player as usize] = Some(EndgameScale::new(player, endgame::scale_kbpsk));
+            }
+        }
+
+        if npm_w + npm_b == 0 {
+            if board.count_piece(Player::Black, PieceType::P) == 0
+                && board.count_piece(Player::White, PieceType::P) >= 2 {
+                entry.scaling_func[Player::White as usize] = Some(EndgameScale::new(Player::White, endgame::scale_kpsk));
+            } else if board.count_piece(Player::White, PieceType::P) == 0
+                && board.count_piece(Player::Black, PieceType::P) >= 2 {
+                entry.scaling_func[Player::Black as usize] = Some(EndgameScale::new(Player::Black, endgame::scale_kpsk));
+            } else if board.count_piece(Player::White, PieceType::P) == 1
+                && board.count_piece(Player::Black, PieceType::P) == 1 {
+                for &player in ALL_PLAYERS.iter() {
+                    entry.scaling_func[player as usize] = Some(EndgameScale::new(player, endgame::scale_kpkp));
+                }
+            }
+        }
+
         let w_pawn_count: u8 =   board.count_piece(Player::White, PieceType::P);
         let w_knight_count: u8 = board.count_piece(Player::White, PieceType::N);
         let w_bishop_count: u8 = board.count_piece(Player::White, PieceType::B);
@@ -176,9 +240,10 @@ fn imbalance<P: PlayerTrait>(piece_counts: &[[u8; PIECE_TYPE_CNT - 2]; PLAYER_CN
 
         let mut v: i32 = 0;
 
-        for pt2 in 0..6 {
-            v +=  QUADRATIC_OURS[pt1][pt2] * piece_counts[P::player() as usize][pt2] as i32
-                + QUADRATIC_THEIRS[pt1][pt2] * piece_counts[P::opp_player() as usize][pt2] as i32;
+        for pt2 in 0..=pt1 {
+            let idx = imbalance_index(pt1, pt2);
+            v +=  tune::QUADRATIC_OURS.get(idx) * piece_counts[P::player() as usize][pt2] as i32
+                + tune::QUADRATIC_THEIRS.get(idx) * piece_counts[P::opp_player() as usize][pt2] as i32;
         }
 
         bonus += piece_counts[P::player() as usize][pt1] as i32 * v;
@@ -1,17 +1,21 @@
 
 pub mod pawn_table;
 pub mod material;
+pub mod eval_cache;
+pub mod endgame;
 pub mod counter_move;
 pub mod continuation;
 pub mod capture_piece_history;
 pub mod butterfly;
 
 use std::ptr::NonNull;
-use std::alloc::{Layout, Global, handle_alloc_error, dealloc, alloc_zeroed};
+use std::alloc::{Layout, Global, handle_alloc_error};
 use std::mem;
 use std::ptr;
 use std::ops::*;
 
+use pleco::tools::huge_pages;
+
 pub mod prelude {
     // easier exporting :)
     pub use super::counter_move::CounterMoveHistory;
@@ -139,15 +143,13 @@ impl<T: Sized + TableBaseConst> TableBase<T> {
     // allocates space.
     unsafe fn alloc() -> NonNull<T> {
         let layout = Layout::array::<T>(T::ENTRY_COUNT).unwrap();
-        let ptr = alloc_zeroed(layout);
-        let new_ptr = ptr.cast();
-        NonNull::new(new_ptr as *mut T).unwrap()
+        huge_pages::alloc_zeroed(layout.size(), layout.align()).cast()
     }
 
     /// de-allocates the current table.
     unsafe fn de_alloc(&mut self) {
-        let ptr: NonNull<u8> = mem::transmute(self.table);
-        dealloc(ptr.as_ptr(),Layout::array::<T>(T::ENTRY_COUNT).unwrap());
+        let layout = Layout::array::<T>(T::ENTRY_COUNT).unwrap();
+        huge_pages::dealloc(self.table.cast(), layout.size(), layout.align());
     }
 }
 
@@ -16,17 +16,11 @@ use pleco::helper::prelude::*;
 use pleco::tools::{PreFetchable,prefetch_write};
 
 use super::{TableBase,TableBaseConst};
+use tune;
 
 
 
-// isolated pawn penalty
-const ISOLATED: Score = Score(13, 18);
-
-// backwards pawn penalty
-const BACKWARDS: Score = Score(24, 12);
-
-// doubled pawn penalty
-const DOUBLED: Score = Score(18, 28);
+// The isolated, backwards and doubled pawn penalties are tunable, and found in the `tune` module.
 
 // Lever bonus by rank
 const LEVER: [Score; RANK_CNT] = [
@@ -150,6 +144,13 @@ impl PawnTable {
         self.table.clear();
     }
 
+    /// Forces the entry of `board` to be evaluated again on its next probe, such as after
+    /// the parameters it depends on have changed.
+    pub fn invalidate(&mut self, board: &Board) {
+        let key: u64 = board.pawn_key();
+        self.get(key).key = !key;
+    }
+
     /// Retrieves the entry of a specified key. If the `Entry` doesn't a matching key,
     /// the `Entry` will be evaluated for its pawn structure.
     pub fn probe(&mut self, board: &Board) -> &mut PawnEntry {
@@ -473,15 +474,15 @@ impl PawnEntry {
                         [P::player().relative_rank_of_sq(s) as usize]
                 };
             } else if neighbours.is_empty() {
-                score -= ISOLATED;
+                score -= tune::ISOLATED.get();
                 self.weak_unopposed[P::player() as usize] += (!opposed) as i16;
             } else if backward {
-                score -= BACKWARDS;
+                score -= tune::BACKWARDS.get();
                 self.weak_unopposed[P::player() as usize] += (!opposed) as i16;
             }
 
             if doubled.is_not_empty() && supported.is_empty() {
-                score -= DOUBLED;
+                score -= tune::DOUBLED.get();
             }
         }
         score
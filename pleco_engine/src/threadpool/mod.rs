@@ -1,27 +1,33 @@
 //! Contains the ThreadPool and the individual Threads.
 
 use std::alloc::{Layout, Global, handle_alloc_error, dealloc, alloc_zeroed};
-use std::sync::atomic::{AtomicBool,Ordering};
+use std::sync::atomic::{AtomicBool,AtomicUsize,Ordering};
 use std::thread::{JoinHandle,self};
 use std::sync::{Once, ONCE_INIT};
 use std::ptr::NonNull;
 use std::{ptr,mem};
 use std::cell::UnsafeCell;
+use std::time::Duration;
 
 use pleco::MoveList;
 use pleco::tools::pleco_arc::Arc;
+use pleco::tools::tt::TranspositionTable;
 use pleco::board::*;
 use pleco::core::piece_move::BitMove;
 
 use sync::LockLatch;
 use time::uci_timer::*;
+use time::time_management::TimeSettings;
 use search::Searcher;
+use search::evaluator::{EvaluatorFactory,HandCrafted};
 
 use consts::*;
 
 const KILOBYTE: usize = 1000;
 const THREAD_STACK_SIZE: usize = 18000 * KILOBYTE;
 const POOL_SIZE: usize = mem::size_of::<ThreadPool>();
+// Number of parts of the transposition table cleared by each thread.
+const TT_CLEAR_PARTS_PER_THREAD: usize = 8;
 
 // An object that is the same size as a thread pool.
 type DummyThreadPool = [u8; POOL_SIZE];
@@ -89,6 +95,56 @@ struct SearcherPtr {
 unsafe impl Sync for SearcherPtr {}
 unsafe impl Send for SearcherPtr {}
 
+/// Clearing of the transposition table, split into parts which are claimed by
+/// each thread in turn.
+pub struct TTClearTask {
+    parts: AtomicUsize,
+    next: AtomicUsize,
+    released: AtomicBool,
+}
+
+impl TTClearTask {
+    fn new() -> Self {
+        TTClearTask {
+            parts: AtomicUsize::new(0),
+            next: AtomicUsize::new(0),
+            released: AtomicBool::new(true),
+        }
+    }
+
+    // Resets the task to clear the table in 'parts' parts. Must only be called while no
+    // thread is running the task.
+    fn start(&self, parts: usize) {
+        self.next.store(0, Ordering::SeqCst);
+        self.parts.store(parts, Ordering::SeqCst);
+        self.released.store(false, Ordering::SeqCst);
+    }
+
+    /// Clears parts of `tt` until none are left to claim, then blocks until the pool
+    /// releases the threads.
+    ///
+    /// Threads are held until the pool has locked their start conditions, so none of them
+    /// go around their idle loop and start again.
+    pub fn run(&self, tt: &TranspositionTable) {
+        let parts = self.parts.load(Ordering::SeqCst);
+        loop {
+            let part = self.next.fetch_add(1, Ordering::SeqCst);
+            if part >= parts {
+                break;
+            }
+            unsafe { tt.clear_part(part, parts); }
+        }
+        while !self.released.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    // Lets the threads return from 'run'.
+    fn release(&self) {
+        self.released.store(true, Ordering::SeqCst);
+    }
+}
+
 /// The thread-pool for the chess engine.
 pub struct ThreadPool {
     /// Access to each thread's Structure
@@ -100,7 +156,11 @@ pub struct ThreadPool {
     /// Condition for all non-main threads
     pub thread_cond: Arc<LockLatch>,
     /// Stop condition, if true the threads should halt.
-    pub stop: AtomicBool
+    pub stop: AtomicBool,
+    /// Clearing of the transposition table, shared between the threads.
+    pub tt_clear: TTClearTask,
+    /// Creates the evaluator of each thread.
+    evaluator: EvaluatorFactory
 }
 
 // Okay, this all looks like madness, but there is some reason to it all.
@@ -121,7 +181,9 @@ impl ThreadPool {
             handles: Vec::new(),
             main_cond: Arc::new(LockLatch::new()),
             thread_cond: Arc::new(LockLatch::new()),
-            stop: AtomicBool::new(true)
+            stop: AtomicBool::new(true),
+            tt_clear: TTClearTask::new(),
+            evaluator: HandCrafted::boxed
         };
         // Lock both the cond variables
         pool.main_cond.lock();
@@ -162,7 +224,7 @@ impl ThreadPool {
         unsafe {
             let result = alloc_zeroed(layout);
             let new_ptr: *mut Searcher = result.cast() as *mut Searcher;
-            ptr::write(new_ptr, Searcher::new(len, cond));
+            ptr::write(new_ptr, Searcher::with_evaluator(len, cond, (self.evaluator)()));
             self.threads.push(UnsafeCell::new(new_ptr));
             SearcherPtr {ptr: UnsafeCell::new(new_ptr)}
         }
@@ -204,6 +266,27 @@ impl ThreadPool {
         }
     }
 
+    /// Sets the evaluator used by each thread, replacing the evaluators of existing threads.
+    ///
+    /// # Safety
+    ///
+    /// Completely unsafe to use when the pool is searching.
+    pub fn set_evaluator(&mut self, factory: EvaluatorFactory) {
+        self.wait_for_finish();
+        self.evaluator = factory;
+        unsafe {
+            self.threads.iter()
+                .map(|s| &mut **s.get())
+                .for_each(|s: &mut Searcher| { s.evaluator = factory(); });
+        }
+    }
+
+    /// Returns the factory creating the evaluator of each thread.
+    #[inline(always)]
+    pub fn evaluator(&self) -> EvaluatorFactory {
+        self.evaluator
+    }
+
     /// Kills and de-allocates all the threads that are running. This function will also
     /// block on waiting for the search to finish.
     pub fn kill_all(&mut self) {
@@ -281,15 +364,45 @@ impl ThreadPool {
             .for_each(|t| t.clear());
     }
 
+    /// Clears the transposition table, splitting the work between every thread. Blocks
+    /// until the table is cleared.
+    ///
+    /// # Safety
+    ///
+    /// Completely unsafe to use when the pool is searching.
+    pub fn clear_tt(&mut self) {
+        self.wait_for_finish();
+        self.tt_clear.start(self.size() * TT_CLEAR_PARTS_PER_THREAD);
+        self.set_clearing_tt(true);
+
+        self.main_cond.set();
+        self.thread_cond.set();
+        self.wait_for_start();
+        self.main_cond.lock();
+        self.thread_cond.lock();
+
+        self.tt_clear.release();
+        self.wait_for_finish();
+        self.set_clearing_tt(false);
+    }
+
+    fn set_clearing_tt(&mut self, clearing: bool) {
+        self.threads.iter_mut()
+            .map(|thread_ptr| unsafe { &mut **(*thread_ptr).get() })
+            .for_each(|t| t.clearing_tt = clearing);
+    }
+
     /// Starts a UCI search. The result will be printed to stdout if the stdout setting
     /// is true.
     pub fn uci_search(&mut self, board: &Board, limits: &Limits) {
 
         // Start the timer!
+        let settings = TimeSettings::from_options();
         if let Some(uci_timer) = limits.use_time_management() {
-            timer().init(limits.start, &uci_timer, board.turn(), board.moves_played());
+            timer().init(limits.start.clone(), &uci_timer, board.turn(), board.moves_played(),
+                         &settings);
         } else {
-            timer().start_timer(limits.start);
+            timer().start_timer(limits.start.clone(), settings.nodes_time);
         }
 
         let root_moves: MoveList = board.generate_moves();
@@ -339,3 +452,32 @@ impl Drop for ThreadPool {
         self.kill_all();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pleco::tools::tt::NodeBound;
+
+    #[test]
+    fn tt_clear_task_clears_every_part() {
+        let table = Arc::new(TranspositionTable::new_num_clusters(1024));
+        let keys: Vec<u64> = (0..64u64).map(|i| 0xABCD_0000_0000_0000 | (i * 0x1_0001)).collect();
+        for key in keys.iter() {
+            let (_found, entry) = table.probe(*key);
+            entry.place(*key, BitMove::null(), 1, 1, 1, NodeBound::Exact, table.time_age());
+        }
+
+        let task = Arc::new(TTClearTask::new());
+        task.start(4 * TT_CLEAR_PARTS_PER_THREAD);
+        // Released up front, so each thread returns as soon as every part is claimed.
+        task.release();
+        let handles: Vec<JoinHandle<()>> = (0..4).map(|_| {
+            let (task, table) = (task.clone(), table.clone());
+            thread::spawn(move || task.run(&table))
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(keys.iter().all(|k| table.read(*k).is_none()));
+    }
+}
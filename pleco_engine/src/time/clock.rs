@@ -0,0 +1,99 @@
+//! Sources of the current time, allowing time management to be tested without waiting on the
+//! wall clock.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64,Ordering};
+use std::time::Instant;
+
+/// A source of the current time, in milliseconds since an arbitrary fixed point.
+pub trait Clock: Send + Sync {
+    /// Returns the current time in milliseconds.
+    fn now(&self) -> i64;
+}
+
+/// The wall clock.
+pub struct RealClock {
+    origin: Instant
+}
+
+impl RealClock {
+    /// Creates a `RealClock`, counting from the time of its creation.
+    pub fn new() -> Self {
+        RealClock { origin: Instant::now() }
+    }
+}
+
+impl Clock for RealClock {
+    fn now(&self) -> i64 {
+        self.origin.elapsed().as_millis() as i64
+    }
+}
+
+/// A clock that only moves forward when told to.
+pub struct MockClock {
+    now: AtomicI64
+}
+
+impl MockClock {
+    /// Creates a `MockClock` starting at zero milliseconds.
+    pub fn new() -> Self {
+        MockClock { now: AtomicI64::new(0) }
+    }
+
+    /// Moves the clock forward by `msec` milliseconds, returning the new time.
+    pub fn advance(&self, msec: i64) -> i64 {
+        self.now.fetch_add(msec, Ordering::SeqCst) + msec
+    }
+
+    /// Sets the clock to `msec` milliseconds.
+    pub fn set(&self, msec: i64) {
+        self.now.store(msec, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> i64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+/// A point in time of a `Clock`, from which elapsed time is measured.
+#[derive(Clone)]
+pub struct Timestamp {
+    clock: Arc<Clock>,
+    time: i64
+}
+
+impl Timestamp {
+    /// Returns the current time of `clock`.
+    pub fn now(clock: Arc<Clock>) -> Self {
+        let time = clock.now();
+        Timestamp { clock, time }
+    }
+
+    /// Returns the current time of the wall clock.
+    pub fn real() -> Self {
+        Timestamp::now(Arc::new(RealClock::new()))
+    }
+
+    /// Returns the milliseconds elapsed since this time.
+    pub fn elapsed(&self) -> i64 {
+        self.clock.now() - self.time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mock_elapsed() {
+        let clock = Arc::new(MockClock::new());
+        clock.set(500);
+        let start = Timestamp::now(clock.clone());
+        assert_eq!(start.elapsed(), 0);
+        assert_eq!(clock.advance(250), 750);
+        assert_eq!(start.elapsed(), 250);
+        assert_eq!(start.clone().elapsed(), 250);
+    }
+}
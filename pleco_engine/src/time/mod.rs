@@ -1,4 +1,5 @@
 //! Objects for managing time.
 
+pub mod clock;
 pub mod time_management;
 pub mod uci_timer;
\ No newline at end of file
@@ -1,25 +1,122 @@
 //! Time Management calculations for the searcher.
 
-use chrono;
-
 use pleco::Player;
 use super::uci_timer::UCITimer;
+use super::clock::Timestamp;
+use consts::{MOVE_OVERHEAD,SLOW_MOVER,MIN_THINKING_TIME,MOVE_HORIZON,NODES_TIME};
+use consts::{DEFAULT_MOVE_OVERHEAD,DEFAULT_SLOW_MOVER,DEFAULT_MIN_THINKING_TIME,DEFAULT_MOVE_HORIZON};
+use threadpool::threadpool;
 
 use std::cell::UnsafeCell;
-use std::time::Instant;
+use std::sync::atomic::Ordering;
 use std::f64;
 
 
-const MOVE_HORIZON: i64 = 50;
 const MAX_RATIO: f64 = 6.32;
 const STEAL_RATIO: f64 = 0.34;
 
-// TODO: These should be made into UCIOptions
-const MIN_THINKING_TIME: i64 = 20;
-const MOVE_OVERHEAD: i64 = 100;
+/// The settings used for allocating time, as set through the UCI options.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimeSettings {
+    /// Time in milliseconds lost to communication on each move.
+    pub move_overhead: i64,
+    /// Percentage of the time usually allocated to be used. Lower values means less importance
+    /// is placed on the current move.
+    pub slow_mover: i64,
+    /// Minimum time in milliseconds to search each move for.
+    pub min_thinking_time: i64,
+    /// Number of moves the remaining time is planned over, when the number of moves to the
+    /// next time control isn't known.
+    pub move_horizon: i64,
+    /// If non-zero, the number of nodes searched standing in for each millisecond.
+    pub nodes_time: i64,
+}
+
+impl Default for TimeSettings {
+    fn default() -> Self {
+        TimeSettings {
+            move_overhead: DEFAULT_MOVE_OVERHEAD as i64,
+            slow_mover: DEFAULT_SLOW_MOVER as i64,
+            min_thinking_time: DEFAULT_MIN_THINKING_TIME as i64,
+            move_horizon: DEFAULT_MOVE_HORIZON as i64,
+            nodes_time: 0,
+        }
+    }
+}
+
+impl TimeSettings {
+    /// Returns the settings currently set through the UCI options.
+    pub fn from_options() -> Self {
+        TimeSettings {
+            move_overhead: MOVE_OVERHEAD.load(Ordering::Relaxed) as i64,
+            slow_mover: SLOW_MOVER.load(Ordering::Relaxed) as i64,
+            min_thinking_time: MIN_THINKING_TIME.load(Ordering::Relaxed) as i64,
+            move_horizon: MOVE_HORIZON.load(Ordering::Relaxed) as i64,
+            nodes_time: NODES_TIME.load(Ordering::Relaxed) as i64,
+        }
+    }
+}
+
+/// The state of the search after an iteration, deciding how much of the ideal time to use.
+#[derive(Copy, Clone, Debug)]
+pub struct SearchStability {
+    /// Number of legal moves at the root.
+    pub root_moves: usize,
+    /// Depth of the last completed iteration.
+    pub depth_completed: i16,
+    /// Depth of the iteration the current best move first became the best.
+    pub last_best_move_depth: i16,
+    /// Decaying count of the changes of the best move.
+    pub best_move_changes: f64,
+    /// Whether the last iteration failed low at the root.
+    pub failed_low: bool,
+    /// Score of the last iteration.
+    pub best_value: i32,
+    /// Score of the search for the previous move.
+    pub previous_score: i32,
+    /// Time reduction of the search for the previous move.
+    pub previous_time_reduction: f64,
+}
+
+/// Why a search is stopped after an iteration.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StopReason {
+    /// There's only one legal move to play.
+    SingleMove,
+    /// The best move has stayed the same long enough for its time to be cut short.
+    StableBestMove,
+    /// The time allocated to the move has run out.
+    OutOfTime,
+}
 
-// Lower values means places less importance on the current move
-const SLOW_MOVER: i64 = 22;
+/// The time allocated to a move, scaled by the stability of the search.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimeScaling {
+    /// Extends time when the score is falling compared to the previous move, or failed low.
+    pub falling_eval: f64,
+    /// Reduces time as the best move stays the same over iterations.
+    pub time_reduction: f64,
+    /// Extends time as the best move changes.
+    pub instability: f64,
+    /// Milliseconds after which no further iteration is started.
+    pub target: i64,
+    /// Set if the search should stop.
+    pub stop: Option<StopReason>,
+}
+
+impl TimeScaling {
+    /// Describes the decision, for an `info string`.
+    pub fn describe(&self, elapsed: i64) -> String {
+        let decision = match self.stop {
+            None => "continue",
+            Some(StopReason::SingleMove) => "stop, single legal move",
+            Some(StopReason::StableBestMove) => "stop, stable best move",
+            Some(StopReason::OutOfTime) => "stop, out of time",
+        };
+        format!("time elapsed {} target {} falling eval {:.2} instability {:.2} reduction {:.2}: {}",
+                elapsed, self.target, self.falling_eval, self.instability, self.time_reduction, decision)
+    }
+}
 
 #[derive(PartialEq)]
 enum TimeCalc {
@@ -49,7 +146,8 @@ impl TimeCalc {
 pub struct TimeManager {
     ideal_time: UnsafeCell<i64>,
     maximum_time: UnsafeCell<i64>,
-    start: UnsafeCell<Instant>
+    nodes_time: UnsafeCell<i64>,
+    start: UnsafeCell<Timestamp>
 }
 
 unsafe impl Sync for TimeManager {}
@@ -59,26 +157,46 @@ impl TimeManager {
         TimeManager {
             ideal_time: UnsafeCell::new(0),
             maximum_time: UnsafeCell::new(0),
-            start: UnsafeCell::new(Instant::now())
+            nodes_time: UnsafeCell::new(0),
+            start: UnsafeCell::new(Timestamp::real())
         }
     }
 
-    pub fn start_timer(&self, start: Instant) {
+    /// Starts timing a search without a clock, such as a search for a fixed time. As with
+    /// `init`, a non-zero `nodes_time` measures time in nodes searched.
+    pub fn start_timer(&self, start: Timestamp, nodes_time: i64) {
         unsafe {
             let self_start = self.start.get();
+            let self_nodes_time = self.nodes_time.get();
             *self_start = start;
+            *self_nodes_time = nodes_time;
         }
     }
 
-    pub fn init(&self, start: Instant, timer: &UCITimer, turn: Player, ply: u16) {
-        let moves_to_go: i64 = timer.moves_to_go as i64;
+    /// Allocates time for a search from the clock of the player to move.
+    ///
+    /// A delay, whether simple or Bronstein, is free thinking time on top of the allocation, as
+    /// the clock isn't charged for it. With an hourglass, the opponent's thinking time is
+    /// expected to be gained back as an increment.
+    ///
+    /// If `settings.nodes_time` is non-zero, time is measured in nodes searched rather than
+    /// by the wall clock from then on, with that many nodes to each millisecond.
+    pub fn init(&self, start: Timestamp, timer: &UCITimer, turn: Player, ply: u16, settings: &TimeSettings) {
+        let moves_to_go: i64 = if timer.hourglass { 0 } else { timer.moves_to_go as i64 };
         let my_time: i64 = (timer.time_msec[turn as usize]) as i64;
-        let my_inc: i64 = (timer.inc_msec[turn as usize]) as i64;
+        let my_delay: i64 = (timer.delay_msec[turn as usize]).max(0);
+        let mut my_inc: i64 = (timer.inc_msec[turn as usize]) as i64;
+        if timer.hourglass {
+            my_inc += timer.time_msec[(!turn) as usize].max(0) / settings.move_horizon;
+        }
+        let min_thinking_time: i64 = settings.min_thinking_time;
+        let slow_mover: i64 = settings.slow_mover;
 
-        let mut ideal_time = (timer.time_msec[turn as usize]).max(MIN_THINKING_TIME);
+        let mut ideal_time = (timer.time_msec[turn as usize]).max(min_thinking_time);
         let mut max_time = ideal_time;
 
-        let max_mtg: i64 = if moves_to_go == 0 {MOVE_HORIZON} else {moves_to_go.min(MOVE_HORIZON)};
+        let move_horizon: i64 = settings.move_horizon;
+        let max_mtg: i64 = if moves_to_go == 0 {move_horizon} else {moves_to_go.min(move_horizon)};
 
         // We calculate optimum time usage for different hypothetical "moves to go"-values
         // and choose the minimum of calculated search time values. Usually the greatest
@@ -86,38 +204,51 @@ impl TimeManager {
         for hyp_mtg in 1..=max_mtg {
             let mut hyp_my_time: i64 = my_time
                                 + my_inc * (hyp_mtg - 1)
-                                - MOVE_OVERHEAD * (2 + hyp_mtg.min(40));
+                                - settings.move_overhead * (2 + hyp_mtg.min(40));
             hyp_my_time = hyp_my_time.max(0);
 
-            let t1: i64 = MIN_THINKING_TIME + TimeManager::remaining(hyp_my_time, hyp_mtg, ply as i64, SLOW_MOVER, TimeCalc::Ideal);
-            let t2: i64 = MIN_THINKING_TIME + TimeManager::remaining(hyp_my_time, hyp_mtg, ply as i64, SLOW_MOVER - 5, TimeCalc::Max);
+            let t1: i64 = min_thinking_time + TimeManager::remaining(hyp_my_time, hyp_mtg, ply as i64, slow_mover, TimeCalc::Ideal);
+            let t2: i64 = min_thinking_time + TimeManager::remaining(hyp_my_time, hyp_mtg, ply as i64, slow_mover - 5, TimeCalc::Max);
 
             ideal_time = t1.min(ideal_time);
             max_time = t2.min(max_time);
         }
 
+        ideal_time += my_delay;
+        max_time += my_delay;
+
         unsafe {
             let self_start = self.start.get();
             let self_ideal = self.ideal_time.get();
             let self_max = self.maximum_time.get();
+            let self_nodes_time = self.nodes_time.get();
             *self_start = start;
             *self_ideal = ideal_time;
             *self_max = max_time;
+            *self_nodes_time = settings.nodes_time;
         }
     }
 
-    pub fn start(&self) -> Instant {
+    pub fn start(&self) -> &Timestamp {
         unsafe {
-            *self.start.get()
+            &*self.start.get()
         }
     }
 
+    /// Returns the milliseconds elapsed since the start of the search. When measuring time in
+    /// nodes, this is the number of nodes searched over the nodes per millisecond instead.
     pub fn elapsed(&self) -> i64 {
-        let start = self.start();
-        chrono::Duration::from_std(start.elapsed())
-            .unwrap()
-            .num_milliseconds()
+        let nodes_time = self.nodes_time();
+        if nodes_time > 0 {
+            return threadpool().nodes() as i64 / nodes_time;
+        }
+        self.start().elapsed()
+    }
 
+    /// Returns if the maximum time for the move has elapsed.
+    #[inline]
+    pub fn out_of_time(&self) -> bool {
+        self.elapsed() >= self.maximum_time()
     }
 
     fn move_importance(ply: i64) -> f64 {
@@ -148,6 +279,47 @@ impl TimeManager {
         (my_time as f64 * ratio1.min(ratio2)) as i64
     }
 
+    /// Scales the ideal time by the stability of the search, and decides whether to stop
+    /// after an iteration, `elapsed` milliseconds into the search.
+    pub fn scale(&self, stability: &SearchStability, elapsed: i64) -> TimeScaling {
+        let score_diff: i64 = stability.best_value as i64 - stability.previous_score as i64;
+
+        // Use more time if the score falls compared to the previous move, or failed low.
+        let falling_eval: f64 = (232).max((787).min(
+              306
+            + 119 * stability.failed_low as i64
+            -   6 * score_diff)) as f64 / 600.0;
+
+        // If the best move is stable over several iterations, reduce time accordingly.
+        let mut time_reduction: f64 = 1.0;
+        for i in 3..6 {
+            if stability.last_best_move_depth * i < stability.depth_completed {
+                time_reduction *= 1.42;
+            }
+        }
+
+        // Use part of the gained time from a previous stable move for the current move.
+        let instability: f64 = (1.0 + stability.best_move_changes)
+            * stability.previous_time_reduction.powf(0.40);
+
+        let unreduced: f64 = self.ideal_time() as f64 * falling_eval * instability;
+        let target: i64 = (unreduced / time_reduction) as i64;
+
+        let stop = if stability.root_moves == 1 {
+            Some(StopReason::SingleMove)
+        } else if elapsed >= target {
+            if elapsed < unreduced as i64 {
+                Some(StopReason::StableBestMove)
+            } else {
+                Some(StopReason::OutOfTime)
+            }
+        } else {
+            None
+        };
+
+        TimeScaling {falling_eval, time_reduction, instability, target, stop}
+    }
+
     #[inline(always)]
     pub fn maximum_time(&self) -> i64 {
         unsafe {
@@ -155,6 +327,15 @@ impl TimeManager {
         }
     }
 
+    /// Returns the number of nodes standing in for each millisecond, or zero if time is
+    /// measured by the wall clock.
+    #[inline(always)]
+    pub fn nodes_time(&self) -> i64 {
+        unsafe {
+            *self.nodes_time.get()
+        }
+    }
+
     #[inline(always)]
     pub fn ideal_time(&self) -> i64 {
         unsafe {
@@ -166,19 +347,229 @@ impl TimeManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
+    use time::clock::MockClock;
 
     #[test]
     fn time_man() {
         let timer =  UCITimer {
             time_msec: [120000, 0],
             inc_msec: [6000, 0],
-            moves_to_go: 20
+            moves_to_go: 20,
+            .. UCITimer::blank()
         };
         let ply: u16 = 0;
         let time_man = TimeManager::uninitialized();
-        time_man.init(Instant::now(), &timer, Player::White, ply);
+        time_man.init(Timestamp::real(), &timer, Player::White, ply, &TimeSettings::default());
         let max = time_man.maximum_time();
         let ideal = time_man.ideal_time();
-        println!("ideal: {} max: {}", ideal, max);
+        assert!(ideal > 0);
+        assert!(ideal <= max);
+        assert!(max < 120000 / 2);
+    }
+
+    #[test]
+    fn time_settings() {
+        let timer =  UCITimer {
+            time_msec: [60000, 60000],
+            inc_msec: [0, 0],
+            moves_to_go: 0,
+            .. UCITimer::blank()
+        };
+        let time_man = TimeManager::uninitialized();
+        let default = TimeSettings::default();
+        time_man.init(Timestamp::real(), &timer, Player::Black, 20, &default);
+        let (ideal, max) = (time_man.ideal_time(), time_man.maximum_time());
+        assert!(ideal <= max);
+        assert_eq!(time_man.nodes_time(), 0);
+
+        let laggy = TimeSettings { move_overhead: 1000, .. default };
+        time_man.init(Timestamp::real(), &timer, Player::Black, 20, &laggy);
+        assert!(time_man.ideal_time() < ideal);
+        assert!(time_man.maximum_time() < max);
+
+        let slow = TimeSettings { slow_mover: 50, .. default };
+        time_man.init(Timestamp::real(), &timer, Player::Black, 20, &slow);
+        assert!(time_man.ideal_time() > ideal);
+
+        let min_time = TimeSettings { min_thinking_time: 5000, .. default };
+        time_man.init(Timestamp::real(), &timer, Player::Black, 20, &min_time);
+        assert!(time_man.ideal_time() >= 5000);
+
+        let short_horizon = TimeSettings { move_horizon: 10, .. default };
+        time_man.init(Timestamp::real(), &timer, Player::Black, 20, &short_horizon);
+        assert!(time_man.ideal_time() > ideal);
+
+        let nodes = TimeSettings { nodes_time: 1000, .. default };
+        time_man.init(Timestamp::real(), &timer, Player::Black, 20, &nodes);
+        assert_eq!(time_man.nodes_time(), 1000);
+        time_man.start_timer(Timestamp::real(), 0);
+        assert_eq!(time_man.nodes_time(), 0);
+        time_man.start_timer(Timestamp::real(), 500);
+        assert_eq!(time_man.nodes_time(), 500);
+    }
+
+    fn stable(depth: i16) -> SearchStability {
+        SearchStability {
+            root_moves: 30,
+            depth_completed: depth,
+            last_best_move_depth: 1,
+            best_move_changes: 0.0,
+            failed_low: false,
+            best_value: 50,
+            previous_score: 50,
+            previous_time_reduction: 1.0,
+        }
+    }
+
+    // Returns the elapsed time at which the search stops, iterating every `step` milliseconds.
+    fn stop_time(time_man: &TimeManager, step: i64, next: &Fn(i16) -> SearchStability) -> (i64, StopReason) {
+        let clock = MockClock::new();
+        for depth in 1..100 {
+            let elapsed = clock.advance(step);
+            if let Some(reason) = time_man.scale(&next(depth), elapsed).stop {
+                return (elapsed, reason);
+            }
+        }
+        panic!("search never stopped");
+    }
+
+    #[test]
+    fn stability_scaling() {
+        let timer =  UCITimer {
+            time_msec: [60000, 60000],
+            inc_msec: [0, 0],
+            moves_to_go: 0,
+            .. UCITimer::blank()
+        };
+        let time_man = TimeManager::uninitialized();
+        time_man.init(Timestamp::real(), &timer, Player::White, 20, &TimeSettings::default());
+        let ideal = time_man.ideal_time();
+
+        // The same move as best all along stops well before the ideal time.
+        let (stable_stop, reason) = stop_time(&time_man, 10, &|d| stable(d));
+        assert_eq!(reason, StopReason::StableBestMove);
+        assert!(stable_stop < ideal);
+
+        // A changing best move extends the search past the ideal time.
+        let (unstable_stop, reason) = stop_time(&time_man, 10, &|d| SearchStability {
+            last_best_move_depth: d,
+            best_move_changes: 1.5,
+            .. stable(d)
+        });
+        assert_eq!(reason, StopReason::OutOfTime);
+        assert!(unstable_stop > ideal);
+
+        // As does a falling score.
+        let (falling_stop, _) = stop_time(&time_man, 10, &|d| SearchStability {
+            last_best_move_depth: d,
+            best_value: -50,
+            failed_low: true,
+            .. stable(d)
+        });
+        let (level_stop, _) = stop_time(&time_man, 10, &|d| SearchStability {
+            last_best_move_depth: d,
+            .. stable(d)
+        });
+        assert!(falling_stop > level_stop);
+        assert!(level_stop >= ideal * 306 / 600);
+
+        // A single legal move stops after the first iteration.
+        let single = SearchStability { root_moves: 1, .. stable(1) };
+        let scaling = time_man.scale(&single, 1);
+        assert_eq!(scaling.stop, Some(StopReason::SingleMove));
+        assert!(scaling.describe(1).ends_with("single legal move"));
+    }
+
+    // Plays out a search on a mock clock, where each iteration takes twice as long as the
+    // last, returning the elapsed time when it stops.
+    fn simulate(timer: &UCITimer, ply: u16) -> (i64, TimeManager) {
+        let clock = Arc::new(MockClock::new());
+        let time_man = TimeManager::uninitialized();
+        time_man.init(Timestamp::now(clock.clone()), timer, Player::White, ply, &TimeSettings::default());
+        let mut iteration_time: i64 = 1;
+        for depth in 1..100 {
+            for _ in 0..iteration_time {
+                clock.advance(1);
+                if time_man.out_of_time() {
+                    return (time_man.elapsed(), time_man);
+                }
+            }
+            iteration_time *= 2;
+            if time_man.scale(&stable(depth), time_man.elapsed()).stop.is_some() {
+                return (time_man.elapsed(), time_man);
+            }
+        }
+        panic!("search never stopped");
+    }
+
+    #[test]
+    fn mock_clock_controls() {
+        // 1s + 0.1s, 40 moves to go.
+        let timer = UCITimer {
+            time_msec: [1000, 1000],
+            inc_msec: [100, 100],
+            moves_to_go: 40,
+            .. UCITimer::blank()
+        };
+        let (stop, time_man) = simulate(&timer, 10);
+        assert!(stop >= 10 && stop <= 40, "stopped at {}", stop);
+        assert!(stop <= time_man.maximum_time());
+
+        // 60s + 0s, sudden death.
+        let timer = UCITimer {
+            time_msec: [60000, 60000],
+            inc_msec: [0, 0],
+            moves_to_go: 0,
+            .. UCITimer::blank()
+        };
+        let (stop, time_man) = simulate(&timer, 40);
+        assert!(stop >= 100 && stop <= 500, "stopped at {}", stop);
+        assert!(stop <= time_man.maximum_time());
+
+        // A second left with a 2s increment is never overstepped.
+        let timer = UCITimer {
+            time_msec: [1000, 1000],
+            inc_msec: [2000, 2000],
+            moves_to_go: 0,
+            .. UCITimer::blank()
+        };
+        let (stop, _) = simulate(&timer, 80);
+        assert!(stop >= 50 && stop < 1000, "stopped at {}", stop);
+    }
+
+    #[test]
+    fn time_control_types() {
+        let base = UCITimer {
+            time_msec: [30000, 30000],
+            .. UCITimer::blank()
+        };
+        let allocate = |timer: &UCITimer| {
+            let time_man = TimeManager::uninitialized();
+            time_man.init(Timestamp::real(), timer, Player::White, 30, &TimeSettings::default());
+            (time_man.ideal_time(), time_man.maximum_time())
+        };
+        let (sd_ideal, sd_max) = allocate(&base);
+
+        // (name, time control, ideal time at least, ideal time at most)
+        let cases: [(&str, UCITimer, i64, i64); 6] = [
+            ("sudden death", base.clone(), sd_ideal, sd_ideal),
+            ("fischer", UCITimer { inc_msec: [1000, 1000], .. base.clone() }, sd_ideal + 200, sd_ideal + 1000),
+            ("repeating", UCITimer { moves_to_go: 5, .. base.clone() }, sd_ideal + 1, 30000 / 5),
+            ("simple delay", UCITimer { delay_msec: [2000, 2000], .. base.clone() }, sd_ideal + 2000, sd_ideal + 2000),
+            ("opponent's delay", UCITimer { delay_msec: [0, 2000], .. base.clone() }, sd_ideal, sd_ideal),
+            ("hourglass", UCITimer { hourglass: true, moves_to_go: 5, .. base.clone() }, sd_ideal + 100, sd_ideal + 1000),
+        ];
+        for &(name, ref timer, low, high) in cases.iter() {
+            let (ideal, max) = allocate(timer);
+            assert!(ideal >= low && ideal <= high, "{}: ideal {} not in [{}, {}]", name, ideal, low, high);
+            assert!(max >= ideal, "{}: max {} below ideal {}", name, max, ideal);
+            assert!(max >= sd_max, "{}: max {} below sudden death {}", name, max, sd_max);
+        }
+
+        // The delay is free, so even without any time left it's used up.
+        let delay_only = UCITimer { time_msec: [0, 0], delay_msec: [3000, 3000], .. UCITimer::blank() };
+        let (ideal, max) = allocate(&delay_only);
+        assert!(ideal >= 3000 && max < 3000 + 100, "ideal {} max {}", ideal, max);
     }
 }
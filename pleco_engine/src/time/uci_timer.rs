@@ -1,7 +1,8 @@
 
-use chrono;
-use std::time;
+use std::sync::Arc;
+
 use pleco::core::masks::PLAYER_CNT;
+use super::clock::{Clock,Timestamp};
 
 #[derive(Clone)]
 pub enum LimitsType {
@@ -21,13 +22,22 @@ impl LimitsType {
             _ => false
         }
     }
+
+    pub fn is_infinite(&self) -> bool {
+        match *self {
+            LimitsType::Infinite => true,
+            _ => false
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct UCITimer {
     pub time_msec: [i64; PLAYER_CNT], // time each player has remaining
     pub inc_msec: [i64; PLAYER_CNT], // increments for each palyer after each turn
+    pub delay_msec: [i64; PLAYER_CNT], // simple or Bronstein delay for each player, before their clock runs
     pub moves_to_go: u32, // Moves to go until next time control sent
+    pub hourglass: bool, // time used by a player is added to their opponent's clock
 }
 
 impl UCITimer {
@@ -35,7 +45,9 @@ impl UCITimer {
         UCITimer {
             time_msec: [0;PLAYER_CNT],
             inc_msec: [0;PLAYER_CNT],
-            moves_to_go: 0
+            delay_msec: [0;PLAYER_CNT],
+            moves_to_go: 0,
+            hourglass: false
         }
     }
 
@@ -43,11 +55,15 @@ impl UCITimer {
         self.time_msec[0] == 0 &&
             self.time_msec[1] == 0 &&
             self.inc_msec[0] == 0 &&
-            self.inc_msec[1] == 0
+            self.inc_msec[1] == 0 &&
+            self.delay_msec[0] == 0 &&
+            self.delay_msec[1] == 0
     }
 
     pub fn display(&self) {
-        println!("time: [{}, {}], inc: [{}, {}], moves to go: {}", self.time_msec[0], self.time_msec[1], self.inc_msec[0], self.inc_msec[1], self.moves_to_go);
+        println!("time: [{}, {}], inc: [{}, {}], delay: [{}, {}], moves to go: {}, hourglass: {}",
+                 self.time_msec[0], self.time_msec[1], self.inc_msec[0], self.inc_msec[1],
+                 self.delay_msec[0], self.delay_msec[1], self.moves_to_go, self.hourglass);
     }
 }
 
@@ -68,7 +84,9 @@ impl PreLimits {
         if let Some(ref time) = self.time {
             println!("time_msec: W = {}, B = {}",time.time_msec[0], time.time_msec[1]);
             println!("inc_msec: W = {}, B = {}",time.inc_msec[0], time.inc_msec[1]);
+            println!("delay_msec: W = {}, B = {}",time.delay_msec[0], time.delay_msec[1]);
             println!("movestogo: {}",time.moves_to_go);
+            println!("hourglass: {}",time.hourglass);
         }
         if let Some(move_time) = self.move_time {println!("move_time: {}", move_time)}
         if let Some(nodes) = self.nodes {println!("nodes: {}", nodes)}
@@ -96,10 +114,19 @@ impl PreLimits {
     }
 
     pub fn create(self) -> Limits {
+        self.create_at(Timestamp::real())
+    }
+
+    /// Creates the `Limits` of a search starting now, measuring time with `clock`.
+    pub fn create_with_clock(self, clock: Arc<Clock>) -> Limits {
+        self.create_at(Timestamp::now(clock))
+    }
+
+    fn create_at(self, start: Timestamp) -> Limits {
         let mut limits = Limits {
             search_moves: self.search_moves.clone(),
             limits_type: LimitsType::Infinite,
-            start: time::Instant::now()
+            start
         };
 
         limits.limits_type = if self.ponder {
@@ -127,7 +154,7 @@ impl PreLimits {
 pub struct Limits {
     pub search_moves: Vec<String>,
     pub limits_type: LimitsType,
-    pub start: time::Instant
+    pub start: Timestamp
 }
 
 impl Limits {
@@ -142,14 +169,13 @@ impl Limits {
         Limits {
             search_moves: Vec::new(),
             limits_type: LimitsType::Infinite,
-            start: time::Instant::now()
+            start: Timestamp::real()
         }
     }
 
+    /// Returns the milliseconds elapsed since the start of the search.
     pub fn elapsed(&self) -> i64 {
-        chrono::Duration::from_std(self.start.elapsed())
-            .unwrap()
-            .num_milliseconds()
+        self.start.elapsed()
     }
 
     pub fn use_movetime(&self) -> Option<u64> {
@@ -0,0 +1,320 @@
+//! Registry of evaluation and search parameters which can be tuned at runtime.
+//!
+//! Each parameter has a default value compiled into the engine. With the `tune` feature
+//! enabled, every parameter is exposed as a UCI spin option, allowing for external tuners
+//! (such as SPSA) to adjust them without recompiling. Without the feature, the defaults
+//! are always used.
+//!
+//! Arrays of parameters, such as the material imbalance tables, are only tuned by the
+//! `texel` tuner and aren't exposed as UCI options.
+//!
+//! Once tuned, the current values can be dumped as Rust source with `rust_source()`, and pasted
+//! back into this file to become the new defaults.
+
+pub mod texel;
+
+use std::sync::atomic::{AtomicI32,Ordering};
+
+use pleco::core::score::*;
+
+/// A single tunable integer parameter.
+pub struct TuneParam {
+    name: &'static str,
+    default: i32,
+    min: i32,
+    max: i32,
+    step: i32,
+    value: AtomicI32,
+}
+
+impl TuneParam {
+    const fn new(name: &'static str, default: i32, min: i32, max: i32, step: i32) -> Self {
+        TuneParam {
+            name,
+            default,
+            min,
+            max,
+            step,
+            value: AtomicI32::new(default),
+        }
+    }
+
+    /// Returns the current value of the parameter.
+    #[inline(always)]
+    pub fn get(&self) -> i32 {
+        if cfg!(feature = "tune") {
+            self.value.load(Ordering::Relaxed)
+        } else {
+            self.default
+        }
+    }
+
+    /// Sets the value of the parameter. Values outside of the allowed range are clamped.
+    pub fn set(&self, value: i32) {
+        self.value.store(value.max(self.min).min(self.max), Ordering::Relaxed);
+    }
+
+    /// Returns the parameter's name, as displayed by the UCI option.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn default(&self) -> i32 {
+        self.default
+    }
+
+    pub fn min(&self) -> i32 {
+        self.min
+    }
+
+    pub fn max(&self) -> i32 {
+        self.max
+    }
+
+    /// The suggested perturbation of the parameter, for use by SPSA.
+    pub fn step(&self) -> i32 {
+        self.step
+    }
+
+    fn current(&self) -> i32 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+/// A tunable `Score`, with the middle-game and end-game values tuned separately.
+pub struct TuneScore {
+    name: &'static str,
+    pub mg: TuneParam,
+    pub eg: TuneParam,
+}
+
+impl TuneScore {
+    /// Returns the current value of the score.
+    #[inline(always)]
+    pub fn get(&self) -> Score {
+        Score(self.mg.get(), self.eg.get())
+    }
+}
+
+/// A tunable array of integers.
+pub struct TuneArray {
+    name: &'static str,
+    params: &'static [TuneParam],
+}
+
+impl TuneArray {
+    /// Returns the current value at `index`.
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> i32 {
+        self.params[index].get()
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns each element of the array as a parameter.
+    pub fn params(&self) -> &'static [TuneParam] {
+        self.params
+    }
+}
+
+macro_rules! count {
+    () => { 0usize };
+    ($head:expr $(, $tail:expr)*) => { 1usize + count!($($tail),*) };
+}
+
+macro_rules! tune_params {
+    (ints { $($i_id:ident: $i_def:expr, $i_min:expr, $i_max:expr, $i_step:expr;)* }
+     scores { $($s_id:ident: ($s_mg:expr, $s_eg:expr), $s_min:expr, $s_max:expr, $s_step:expr;)* }
+     arrays { $($a_id:ident: [$($a_def:expr),*], $a_min:expr, $a_max:expr, $a_step:expr;)* }) => {
+        $(pub static $i_id: TuneParam =
+            TuneParam::new(stringify!($i_id), $i_def, $i_min, $i_max, $i_step);)*
+
+        $(pub static $s_id: TuneScore = TuneScore {
+            name: stringify!($s_id),
+            mg: TuneParam::new(concat!(stringify!($s_id), "_MG"), $s_mg, $s_min, $s_max, $s_step),
+            eg: TuneParam::new(concat!(stringify!($s_id), "_EG"), $s_eg, $s_min, $s_max, $s_step),
+        };)*
+
+        $(pub static $a_id: TuneArray = TuneArray {
+            name: stringify!($a_id),
+            params: {
+                static PARAMS: [TuneParam; count!($($a_def),*)] =
+                    [$(TuneParam::new(stringify!($a_id), $a_def, $a_min, $a_max, $a_step)),*];
+                &PARAMS
+            },
+        };)*
+
+        static INTS: &[&TuneParam] = &[$(&$i_id),*];
+        static SCORES: &[&TuneScore] = &[$(&$s_id),*];
+        static ARRAYS: &[&TuneArray] = &[$(&$a_id),*];
+    }
+}
+
+tune_params! {
+    ints {
+        RAZOR_MARGIN_1: 590, 0, 1200, 30;
+        RAZOR_MARGIN_2: 604, 0, 1200, 30;
+        FUTILITY_MARGIN: 175, 50, 400, 10;
+        FUTILITY_MARGIN_IMPROVING: 50, 0, 150, 5;
+        CAPTURE_PRUNE_MARGIN_1: 253, 0, 800, 15;
+        CAPTURE_PRUNE_MARGIN_2: 500, 0, 1200, 25;
+        CAPTURE_PRUNE_MARGIN_3: 693, 0, 1600, 35;
+        CAPTURE_PRUNE_MARGIN_4: 996, 0, 2000, 50;
+        CAPTURE_PRUNE_MARGIN_5: 1140, 0, 2400, 55;
+        CAPTURE_PRUNE_MARGIN_6: 1339, 0, 2800, 65;
+        QUEEN_SAFE_CHECK: 780, 0, 2000, 40;
+        ROOK_SAFE_CHECK: 880, 0, 2000, 40;
+        BISHOP_SAFE_CHECK: 435, 0, 2000, 25;
+        KNIGHT_SAFE_CHECK: 790, 0, 2000, 40;
+        KING_ZONE_ATTACK_WEIGHT: 102, 0, 400, 6;
+        KING_RING_WEAK_WEIGHT: 191, 0, 600, 10;
+        KING_PINNED_CHECKS_WEIGHT: 848, 0, 2000, 40;
+        KING_QUEEN_WEIGHT: 848, 0, 2000, 40;
+    }
+    scores {
+        THREAT_BY_SAFE_PAWN: (192, 175), 0, 400, 10;
+        THREAT_BY_RANK: (16, 3), 0, 100, 2;
+        HANGING: (48, 27), 0, 200, 4;
+        SLIDER_ON_QUEEN: (42, 21), 0, 200, 4;
+        THREAT_BY_PAWN_PUSH: (38, 22), 0, 200, 4;
+        ISOLATED: (13, 18), 0, 100, 2;
+        BACKWARDS: (24, 12), 0, 100, 2;
+        DOUBLED: (18, 28), 0, 100, 2;
+    }
+    arrays {
+        // The lower triangles of the material imbalance tables, indexed by
+        // `tables::material::imbalance_index`. The rows are the bishop pair, pawn, knight,
+        // bishop, rook and queen.
+        QUADRATIC_OURS: [
+            1667,
+              40,    0,
+              32,  255,  -3,
+               0,  104,   4,    0,
+             -26,   -2,  47,  105, -149,
+            -189,   24, 117,  133, -134, -10
+        ], -2500, 2500, 8;
+        QUADRATIC_THEIRS: [
+               0,
+              36,    0,
+               9,   63,   0,
+              59,   65,  42,    0,
+              46,   39,  24,  -24,    0,
+              97,  100, -42,  137,  268,    0
+        ], -2500, 2500, 8;
+    }
+}
+
+// The integer parameters read by the evaluation, rather than the search.
+static EVAL_INTS: &[&TuneParam] = &[
+    &QUEEN_SAFE_CHECK,
+    &ROOK_SAFE_CHECK,
+    &BISHOP_SAFE_CHECK,
+    &KNIGHT_SAFE_CHECK,
+    &KING_ZONE_ATTACK_WEIGHT,
+    &KING_RING_WEAK_WEIGHT,
+    &KING_PINNED_CHECKS_WEIGHT,
+    &KING_QUEEN_WEIGHT,
+];
+
+/// Returns every tunable parameter, with each `TuneScore` split into its two components.
+pub fn params() -> Vec<&'static TuneParam> {
+    let mut params: Vec<&'static TuneParam> = INTS.to_vec();
+    for score in SCORES.iter() {
+        params.push(&score.mg);
+        params.push(&score.eg);
+    }
+    params
+}
+
+/// Returns every parameter read by the evaluation as a single vector, including the elements
+/// of each `TuneArray`.
+pub fn eval_params() -> Vec<&'static TuneParam> {
+    let mut params: Vec<&'static TuneParam> = EVAL_INTS.to_vec();
+    for score in SCORES.iter() {
+        params.push(&score.mg);
+        params.push(&score.eg);
+    }
+    for array in ARRAYS.iter() {
+        params.extend(array.params().iter());
+    }
+    params
+}
+
+/// Finds a parameter by name.
+pub fn find(name: &str) -> Option<&'static TuneParam> {
+    params().into_iter().find(|p| p.name() == name)
+}
+
+/// Returns the parameters in the format used by SPSA tuners, one per line:
+/// `name, int, value, min, max, step, learning rate`.
+pub fn spsa_params() -> String {
+    params().iter()
+        .map(|p| format!("{}, int, {}, {}, {}, {}, 0.0020\n",
+                         p.name(), p.current(), p.min(), p.max(), p.step()))
+        .collect()
+}
+
+/// Returns the current values as Rust source, suitable for replacing the parameter
+/// declarations of this module.
+pub fn rust_source() -> String {
+    let mut s = String::from("tune_params! {\n    ints {\n");
+    for p in INTS.iter() {
+        s.push_str(&format!("        {}: {}, {}, {}, {};\n",
+                            p.name(), p.current(), p.min(), p.max(), p.step()));
+    }
+    s.push_str("    }\n    scores {\n");
+    for score in SCORES.iter() {
+        s.push_str(&format!("        {}: ({}, {}), {}, {}, {};\n", score.name,
+                            score.mg.current(), score.eg.current(),
+                            score.mg.min(), score.mg.max(), score.mg.step()));
+    }
+    s.push_str("    }\n    arrays {\n");
+    for array in ARRAYS.iter() {
+        let values: Vec<String> = array.params().iter().map(|p| p.current().to_string()).collect();
+        let first = &array.params()[0];
+        s.push_str(&format!("        {}: [{}], {}, {}, {};\n", array.name(), values.join(", "),
+                            first.min(), first.max(), first.step()));
+    }
+    s.push_str("    }\n}\n");
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_lookup() {
+        let params = params();
+        assert_eq!(params.len(), INTS.len() + 2 * SCORES.len());
+        for p in params.iter() {
+            assert!(p.min() <= p.default() && p.default() <= p.max(), "{}", p.name());
+            assert!(p.step() > 0);
+            assert_eq!(find(p.name()).unwrap().name(), p.name());
+        }
+        assert_eq!(find("HANGING_EG").unwrap().default(), 27);
+        assert!(find("HANGING").is_none());
+        assert_eq!(HANGING.get(), Score(48, 27));
+    }
+
+    #[test]
+    fn dump_source() {
+        let source = rust_source();
+        assert!(source.starts_with("tune_params! {\n    ints {\n        RAZOR_MARGIN_1: 590, 0, 1200, 30;\n"));
+        assert!(source.contains("\n        HANGING: (48, 27), 0, 200, 4;\n"));
+        assert!(source.contains("\n        QUADRATIC_OURS: [1667, 40, 0, 32, 255, -3, "));
+        assert_eq!(spsa_params().lines().count(), params().len());
+    }
+
+    #[cfg(feature = "tune")]
+    #[test]
+    fn set_clamps() {
+        BACKWARDS.mg.set(30);
+        assert_eq!(BACKWARDS.get(), Score(30, 12));
+        BACKWARDS.mg.set(1000);
+        assert_eq!(BACKWARDS.mg.get(), 100);
+        BACKWARDS.mg.set(24);
+    }
+}
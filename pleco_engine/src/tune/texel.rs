@@ -0,0 +1,411 @@
+//! Texel tuning of the evaluation parameters.
+//!
+//! Positions labelled with a game result or a score are first quiesced with the engine's
+//! quiescence search. The evaluation of each quiet position is then linearized around the
+//! current parameters, measuring the derivative of the evaluation with respect to each
+//! parameter of `tune::eval_params()` by finite differences. Finally, the parameters are fit
+//! to the labels by minimizing the mean squared error between each label and the sigmoid of
+//! the linearized evaluation, using Adam.
+//!
+//! As the evaluation isn't linear in all of its parameters (such as the king danger weights),
+//! the linearization can be repeated around the tuned parameters for several rounds.
+//!
+//! Parameters can only be changed with the `tune` feature enabled. Without it, every
+//! derivative is zero and nothing is tuned.
+//!
+//! # Position files
+//!
+//! Each line holds a FEN followed by a label, optionally separated by a semicolon. The label
+//! is either a game result, given as `1-0`, `1/2-1/2` or `0-1`, or as `1.0`, `0.5` or `0.0`,
+//! or a score as an integer number of centipawns. Results may be wrapped in brackets or
+//! quotes. Both results and scores are from white's perspective.
+
+use std::f64::consts::LN_10;
+use std::fs::File;
+use std::io::{self,BufRead,BufReader};
+
+use pleco::{Board,Player};
+use pleco::core::score::*;
+use pleco::tools::pleco_arc::Arc;
+
+use search::Searcher;
+use search::eval::Evaluation;
+use sync::LockLatch;
+use tables::pawn_table::PawnTable;
+use tables::material::Material;
+
+use super::{TuneParam,eval_params};
+
+// Adam hyper-parameters.
+const BETA_1: f64 = 0.9;
+const BETA_2: f64 = 0.999;
+const EPSILON: f64 = 1e-8;
+
+/// The label of a position, from white's perspective.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Label {
+    /// The result of the game, 1.0 for a white win, 0.5 for a draw and 0.0 for a loss.
+    Result(f64),
+    /// A score, in centipawns.
+    Score(i32),
+}
+
+/// Parses a line of a position file, returning `None` if it can't be parsed.
+pub fn parse_line(line: &str) -> Option<(Board, Label)> {
+    let line = line.trim();
+    let split = line.rfind(|c: char| c == ';' || c.is_whitespace())?;
+    let label = parse_label(&line[split + 1..])?;
+    let fen = line[..split].trim().trim_end_matches(';').trim();
+    Board::from_fen(fen).ok().map(|board| (board, label))
+}
+
+fn parse_label(label: &str) -> Option<Label> {
+    let label = label.trim_matches(|c: char| c == '[' || c == ']' || c == '"' || c == '\'');
+    match label {
+        "1-0" => Some(Label::Result(1.0)),
+        "0-1" => Some(Label::Result(0.0)),
+        "1/2-1/2" => Some(Label::Result(0.5)),
+        _ if label.contains('.') => label.parse::<f64>().ok()
+            .filter(|r| *r >= 0.0 && *r <= 1.0)
+            .map(Label::Result),
+        _ => label.parse::<i32>().ok().map(Label::Score),
+    }
+}
+
+/// Loads the positions of a file, skipping any lines that can't be parsed.
+pub fn load_positions(path: &str) -> io::Result<Vec<(Board, Label)>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut positions = Vec::new();
+    for line in reader.lines() {
+        if let Some(position) = parse_line(&line?) {
+            positions.push(position);
+        }
+    }
+    Ok(positions)
+}
+
+/// Replaces each position with the quiet position at the end of its quiescence search's
+/// principal variation.
+///
+/// The `Searcher` is large, so this must be called from a thread with a large stack, after
+/// the engine's globals have been initialized.
+pub fn quiesce(positions: &mut [(Board, Label)]) {
+    let mut searcher = Searcher::new(0, Arc::new(LockLatch::new()));
+    for position in positions.iter_mut() {
+        searcher.board = position.0.shallow_clone();
+        let (_, pv) = searcher.quiesce();
+        for mov in pv {
+            position.0.apply_move(mov);
+        }
+    }
+}
+
+/// Returns the expected score of white given an evaluation in centipawns.
+#[inline]
+fn sigmoid(k: f64, cp: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * cp / 400.0))
+}
+
+// A position with its evaluation linearized around the base parameters.
+struct Entry {
+    label: Label,
+    // The evaluation in centipawns from white's perspective.
+    eval: f64,
+    // The derivative of the evaluation with respect to each parameter that affects it.
+    coeffs: Vec<(u32, f32)>,
+}
+
+/// Fits the evaluation parameters to a set of labelled positions.
+pub struct Tuner {
+    params: Vec<&'static TuneParam>,
+    // The parameter values the evaluation was linearized around.
+    base: Vec<f64>,
+    weights: Vec<f64>,
+    // Adam's moment estimates, and its step count.
+    moment: Vec<f64>,
+    velocity: Vec<f64>,
+    steps: i32,
+    entries: Vec<Entry>,
+    k: f64,
+    pawns: PawnTable,
+    material: Material,
+}
+
+impl Tuner {
+    /// Creates a `Tuner` of every evaluation parameter.
+    pub fn new() -> Self {
+        Tuner::with_params(eval_params())
+    }
+
+    /// Creates a `Tuner` of a specific set of parameters.
+    pub fn with_params(params: Vec<&'static TuneParam>) -> Self {
+        let n = params.len();
+        let base: Vec<f64> = params.iter().map(|p| p.get() as f64).collect();
+        Tuner {
+            params,
+            weights: base.clone(),
+            base,
+            moment: vec![0.0; n],
+            velocity: vec![0.0; n],
+            steps: 0,
+            entries: Vec::new(),
+            k: 1.0,
+            pawns: PawnTable::new(),
+            material: Material::new(),
+        }
+    }
+
+    /// Returns the number of positions.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the scaling constant of the sigmoid.
+    pub fn k(&self) -> f64 {
+        self.k
+    }
+
+    /// Linearizes the evaluation of each position around the current parameter values,
+    /// replacing any positions from before.
+    pub fn linearize(&mut self, positions: &[(Board, Label)]) {
+        self.base = self.params.iter().map(|p| p.get() as f64).collect();
+        self.weights = self.base.clone();
+        self.entries.clear();
+        let params = self.params.clone();
+        for &(ref board, label) in positions.iter() {
+            let eval = self.evaluate(board);
+            let mut coeffs = Vec::new();
+            for (i, param) in params.iter().enumerate() {
+                let value = param.get();
+                param.set(value + param.step());
+                let hi = param.get();
+                let eval_hi = self.evaluate(board);
+                param.set(value - param.step());
+                let lo = param.get();
+                let eval_lo = self.evaluate(board);
+                param.set(value);
+                if hi != lo && eval_hi != eval_lo {
+                    coeffs.push((i as u32, ((eval_hi - eval_lo) / (hi - lo) as f64) as f32));
+                }
+            }
+            self.entries.push(Entry { label, eval, coeffs });
+        }
+    }
+
+    // Evaluates a board in centipawns from white's perspective, with the current parameters.
+    fn evaluate(&mut self, board: &Board) -> f64 {
+        self.pawns.invalidate(board);
+        self.material.invalidate(board);
+        let mut value = Evaluation::evaluate(board, &mut self.pawns, &mut self.material);
+        if board.turn() == Player::Black {
+            value = -value;
+        }
+        value as f64 * 100.0 / PAWN_EG as f64
+    }
+
+    // Returns the linearized evaluation of an entry with the current weights.
+    #[inline]
+    fn linear_eval(&self, entry: &Entry) -> f64 {
+        entry.eval + entry.coeffs.iter()
+            .map(|&(i, c)| c as f64 * (self.weights[i as usize] - self.base[i as usize]))
+            .sum::<f64>()
+    }
+
+    #[inline]
+    fn target(&self, entry: &Entry) -> f64 {
+        match entry.label {
+            Label::Result(r) => r,
+            Label::Score(cp) => sigmoid(self.k, cp as f64),
+        }
+    }
+
+    /// Returns the mean squared error of the current weights.
+    pub fn error(&self) -> f64 {
+        self.error_with_k(self.k)
+    }
+
+    fn error_with_k(&self, k: f64) -> f64 {
+        if self.entries.is_empty() {
+            return 0.0;
+        }
+        let total: f64 = self.entries.iter()
+            .map(|e| {
+                let target = match e.label {
+                    Label::Result(r) => r,
+                    Label::Score(cp) => sigmoid(k, cp as f64),
+                };
+                (target - sigmoid(k, self.linear_eval(e))).powi(2)
+            })
+            .sum();
+        total / self.entries.len() as f64
+    }
+
+    /// Finds the scaling constant of the sigmoid minimizing the error of the positions labelled
+    /// with results. Positions labelled with scores are compared against the sigmoid of their
+    /// score using the same constant.
+    pub fn fit_k(&mut self) -> f64 {
+        if !self.entries.iter().any(|e| if let Label::Result(_) = e.label { true } else { false }) {
+            self.k = 1.0;
+            return self.k;
+        }
+        let mut k = 1.0;
+        let mut step = 0.1;
+        for _ in 0..4 {
+            let mut best = self.results_error(k);
+            loop {
+                let up = self.results_error(k + step);
+                let down = if k - step > 0.0 { self.results_error(k - step) } else { best };
+                if up < best && up <= down {
+                    k += step;
+                    best = up;
+                } else if down < best {
+                    k -= step;
+                    best = down;
+                } else {
+                    break;
+                }
+            }
+            step /= 10.0;
+        }
+        self.k = k;
+        k
+    }
+
+    fn results_error(&self, k: f64) -> f64 {
+        let mut total = 0.0;
+        let mut count = 0;
+        for e in self.entries.iter() {
+            if let Label::Result(r) = e.label {
+                total += (r - sigmoid(k, self.linear_eval(e))).powi(2);
+                count += 1;
+            }
+        }
+        total / count as f64
+    }
+
+    /// Runs `epochs` full passes of Adam over the positions with the given learning rate, in
+    /// units of each parameter. Returns the resulting error.
+    pub fn train(&mut self, epochs: usize, learning_rate: f64) -> f64 {
+        let n = self.params.len();
+        let mut grad = vec![0.0; n];
+        for _ in 0..epochs {
+            for g in grad.iter_mut() {
+                *g = 0.0;
+            }
+            for e in self.entries.iter() {
+                let s = sigmoid(self.k, self.linear_eval(e));
+                let d = -2.0 * (self.target(e) - s) * s * (1.0 - s) * self.k * LN_10 / 400.0;
+                for &(i, c) in e.coeffs.iter() {
+                    grad[i as usize] += d * c as f64;
+                }
+            }
+
+            self.steps += 1;
+            let len = self.entries.len().max(1) as f64;
+            let correction_1 = 1.0 - BETA_1.powi(self.steps);
+            let correction_2 = 1.0 - BETA_2.powi(self.steps);
+            for i in 0..n {
+                let g = grad[i] / len;
+                self.moment[i] = BETA_1 * self.moment[i] + (1.0 - BETA_1) * g;
+                self.velocity[i] = BETA_2 * self.velocity[i] + (1.0 - BETA_2) * g * g;
+                let m = self.moment[i] / correction_1;
+                let v = self.velocity[i] / correction_2;
+                let w = self.weights[i] - learning_rate * m / (v.sqrt() + EPSILON);
+                self.weights[i] = w.max(self.params[i].min() as f64).min(self.params[i].max() as f64);
+            }
+        }
+        self.error()
+    }
+
+    /// Sets each parameter to its tuned value, rounded to the nearest integer.
+    pub fn apply(&self) {
+        for (param, w) in self.params.iter().zip(self.weights.iter()) {
+            param.set(w.round() as i32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use consts::init_globals;
+    use tune;
+
+    #[test]
+    fn parse_labels() {
+        let fen = "4k3/8/8/8/8/8/8/3QK3 w - - 0 1";
+        assert_eq!(parse_line(&format!("{} [1.0]", fen)).unwrap().1, Label::Result(1.0));
+        assert_eq!(parse_line(&format!("{} \"1/2-1/2\"", fen)).unwrap().1, Label::Result(0.5));
+        assert_eq!(parse_line(&format!("{}; 0-1", fen)).unwrap().1, Label::Result(0.0));
+        assert_eq!(parse_line(&format!("{} -35", fen)).unwrap().1, Label::Score(-35));
+        assert_eq!(parse_line("4k3/8/8/8/8/8/8/3QK3 w - - 1.0").unwrap().1, Label::Result(1.0));
+        assert_eq!(parse_line(&format!("{} [1.0]", fen)).unwrap().0.fen(), fen);
+        assert!(parse_line(&format!("{} 1.5", fen)).is_none());
+        assert!(parse_line(&format!("{} win", fen)).is_none());
+        assert!(parse_line("not a fen 1-0").is_none());
+        assert!(parse_line("").is_none());
+    }
+
+    #[test]
+    fn quiesce_hanging_queen() {
+        init_globals();
+        let handle = ::std::thread::Builder::new()
+            .stack_size(16 * 1024 * 1024)
+            .spawn(|| {
+                let board = Board::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+                let mut positions = vec![(board, Label::Result(1.0))];
+                quiesce(&mut positions);
+                assert_eq!(positions[0].0.fen(), "4k3/8/8/3R4/8/8/8/4K3 b - - 0 1");
+            }).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn train_fits_weights() {
+        // A single parameter, with each position's evaluation moving by a tenth of a centipawn
+        // per unit. The labels are generated by a parameter 200 units higher than the default.
+        let param = &tune::QUADRATIC_THEIRS.params()[0];
+        let mut tuner = Tuner::with_params(vec![param]);
+        let truth = tuner.base[0] + 200.0;
+        for i in 0..200 {
+            let eval = (i as f64 - 100.0) * 4.0;
+            let coeff = if i % 2 == 0 { 0.1 } else { -0.1 };
+            let true_eval = eval + coeff * (truth - tuner.base[0]);
+            tuner.entries.push(Entry {
+                label: Label::Result(sigmoid(1.0, true_eval)),
+                eval,
+                coeffs: vec![(0, coeff as f32)],
+            });
+        }
+
+        let start = tuner.error();
+        let end = tuner.train(400, 2.0);
+        assert!(end < start / 10.0);
+        assert!((tuner.weights[0] - truth).abs() < 20.0, "{}", tuner.weights[0]);
+    }
+
+    #[test]
+    fn fit_sigmoid() {
+        let mut tuner = Tuner::with_params(Vec::new());
+        for i in 0..100 {
+            let eval = (i as f64 - 50.0) * 10.0;
+            tuner.entries.push(Entry { label: Label::Result(sigmoid(1.3, eval)), eval, coeffs: Vec::new() });
+        }
+        assert!((tuner.fit_k() - 1.3).abs() < 0.01);
+        assert!(tuner.error() < 1e-6);
+    }
+
+    #[cfg(feature = "tune")]
+    #[test]
+    fn linearize_bishop_pair() {
+        // Neither side has rooks or queens, so their rows of the table don't affect the evaluation.
+        let board = Board::from_fen("4k3/pppp4/8/8/8/8/PPPP4/2B1KB2 w - - 0 1").unwrap();
+        let params: Vec<&'static TuneParam> = tune::QUADRATIC_OURS.params().iter().collect();
+        let mut tuner = Tuner::with_params(params);
+        tuner.linearize(&[(board, Label::Result(1.0))]);
+        let coeffs = &tuner.entries[0].coeffs;
+        assert!(coeffs.iter().any(|&(i, c)| i == 0 && c > 0.0));
+        assert!(coeffs.iter().all(|&(i, _)| i < 10));
+        assert_eq!(tune::QUADRATIC_OURS.get(0), tune::QUADRATIC_OURS.params()[0].default());
+    }
+}
@@ -0,0 +1,121 @@
+//! Commands for inspecting the engine's current position, not part of the UCI protocol.
+
+use pleco::{Board,BitMove,PieceType};
+
+/// Creates a description of the board, its FEN, keys and the squares giving check.
+pub fn display_board(board: &Board) -> String {
+    let mut s = board.pretty_string();
+    s.push_str(&format!("\nFen: {}\n", board.fen()));
+    s.push_str(&display_keys(board));
+    s.push_str("Checkers:");
+    for sq in board.checkers() {
+        s.push_str(&format!(" {}", sq));
+    }
+    s.push('\n');
+    s
+}
+
+/// Creates a listing of the zobrist, pawn and material keys of a board.
+pub fn display_keys(board: &Board) -> String {
+    let mut s = String::new();
+    s.push_str(&format!("Key: {:016X}\n", board.zobrist()));
+    s.push_str(&format!("Pawn key: {:016X}\n", board.pawn_key()));
+    s.push_str(&format!("Material key: {:016X}\n", board.material_key()));
+    s
+}
+
+/// Lists every legal move of the board, both in SAN and UCI notation.
+pub fn display_moves(board: &Board) -> String {
+    let moves = board.generate_moves();
+    let mut s = format!("Legal moves: {}\n", moves.len());
+    for mov in moves.iter() {
+        s.push_str(&format!("{:8} {}\n", move_to_san(board, *mov), mov));
+    }
+    s
+}
+
+/// Converts a legal move into Standard Algebraic Notation, such as "Nbd2", "exd5" or "e8=Q+".
+pub fn move_to_san(board: &Board, mov: BitMove) -> String {
+    let mut s = String::new();
+    let src = mov.get_src();
+    let dst = mov.get_dest();
+    let piece = board.piece_at_sq(src).type_of();
+
+    if mov.is_king_castle() {
+        s.push_str("O-O");
+    } else if mov.is_queen_castle() {
+        s.push_str("O-O-O");
+    } else if piece == PieceType::P {
+        if mov.is_capture() {
+            s.push_str(&src.to_string()[..1]);
+            s.push('x');
+        }
+        s.push_str(&dst.to_string());
+        if mov.is_promo() {
+            s.push('=');
+            s.push(mov.promo_piece().char_upper());
+        }
+    } else {
+        s.push(piece.char_upper());
+
+        // Disambiguate between pieces of the same type moving to the same square.
+        let others: Vec<BitMove> = board.generate_moves().iter()
+            .filter(|m| **m != mov && m.get_dest() == dst
+                && board.piece_at_sq(m.get_src()).type_of() == piece)
+            .cloned()
+            .collect();
+        if !others.is_empty() {
+            let src_str = src.to_string();
+            if others.iter().all(|m| m.get_src().file() != src.file()) {
+                s.push_str(&src_str[..1]);
+            } else if others.iter().all(|m| m.get_src().rank() != src.rank()) {
+                s.push_str(&src_str[1..]);
+            } else {
+                s.push_str(&src_str);
+            }
+        }
+        if mov.is_capture() {
+            s.push('x');
+        }
+        s.push_str(&dst.to_string());
+    }
+
+    let mut after = board.shallow_clone();
+    after.apply_move(mov);
+    if after.checkmate() {
+        s.push('#');
+    } else if after.in_check() {
+        s.push('+');
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn san_of(board: &Board, uci: &str) -> String {
+        let mov = board.generate_moves().iter()
+            .find(|m| m.stringify() == uci)
+            .cloned()
+            .unwrap();
+        move_to_san(board, mov)
+    }
+
+    #[test]
+    fn san_moves() {
+        let board = Board::start_pos();
+        assert_eq!(san_of(&board, "e2e4"), "e4");
+        assert_eq!(san_of(&board, "g1f3"), "Nf3");
+
+        let board = Board::from_fen("r3k2r/1P6/8/3p4/4P3/5N2/8/RN2K2R w KQkq - 0 1").unwrap();
+        assert_eq!(san_of(&board, "e4d5"), "exd5");
+        assert_eq!(san_of(&board, "b7a8q"), "bxa8=Q+");
+        assert_eq!(san_of(&board, "b1d2"), "Nbd2");
+        assert_eq!(san_of(&board, "f3d4"), "Nd4");
+        assert_eq!(san_of(&board, "e1g1"), "O-O");
+
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert_eq!(san_of(&board, "a1a8"), "Ra8#");
+    }
+}
@@ -1,3 +1,4 @@
 /// uci protocol functions
 pub mod options;
 pub mod parse;
+pub mod debug;
@@ -1,17 +1,37 @@
 //! Houses any UCI compatible options, as well as the framework for parsing and applying them.
 
-use consts::{MAX_THREADS,DEFAULT_TT_SIZE};
+use consts::{MAX_THREADS,DEFAULT_TT_SIZE,DEFAULT_HASH_FILE,DEFAULT_CONTEMPT};
+use consts::{DEFAULT_MOVE_OVERHEAD,DEFAULT_SLOW_MOVER,DEFAULT_MIN_THINKING_TIME,DEFAULT_MOVE_HORIZON};
+use search::AnalysisContempt;
+use tune::{self, TuneParam};
 
 use std::option::Option;
 use std::collections::VecDeque;
 
 use num_cpus;
 
+use pleco::tools::tt::TranspositionTable;
+
 /// A List of work for the Searcher to do following the application of options
 pub enum OptionWork {
     ClearTT,
     ResizeTT(usize),
-    Threads(usize)
+    HashFile(String),
+    SaveHash,
+    LoadHash,
+    Threads(usize),
+    Contempt(i32),
+    AnalysisContempt(AnalysisContempt),
+    AnalyseMode(bool),
+    ShowWDL(bool),
+    EvalFile(String),
+    UseNNUE(bool),
+    MoveOverhead(i32),
+    SlowMover(i32),
+    MinThinkingTime(i32),
+    MoveHorizon(i32),
+    NodesTime(i32),
+    Tune(&'static TuneParam, i32)
 }
 
 impl OptionWork {
@@ -19,7 +39,22 @@ impl OptionWork {
         match *self {
             OptionWork::ClearTT => false,
             OptionWork::ResizeTT(_) => false,
-            OptionWork::Threads(_) => false
+            OptionWork::HashFile(_) => true,
+            OptionWork::SaveHash => false,
+            OptionWork::LoadHash => false,
+            OptionWork::Threads(_) => false,
+            OptionWork::Contempt(_) => true,
+            OptionWork::AnalysisContempt(_) => true,
+            OptionWork::AnalyseMode(_) => true,
+            OptionWork::ShowWDL(_) => true,
+            OptionWork::EvalFile(_) => false,
+            OptionWork::UseNNUE(_) => false,
+            OptionWork::MoveOverhead(_) => true,
+            OptionWork::SlowMover(_) => true,
+            OptionWork::MinThinkingTime(_) => true,
+            OptionWork::MoveHorizon(_) => true,
+            OptionWork::NodesTime(_) => true,
+            OptionWork::Tune(_, _) => true
         }
     }
 }
@@ -37,7 +72,26 @@ impl OptionsMap {
         let work = VecDeque::new();
         map.push(OptionsMap::clear_hash());
         map.push(OptionsMap::resize_hash());
+        map.push(OptionsMap::hash_file());
+        map.push(OptionsMap::save_hash());
+        map.push(OptionsMap::load_hash());
         map.push(OptionsMap::threads());
+        map.push(OptionsMap::contempt());
+        map.push(OptionsMap::analysis_contempt());
+        map.push(OptionsMap::analyse_mode());
+        map.push(OptionsMap::show_wdl());
+        map.push(OptionsMap::eval_file());
+        map.push(OptionsMap::use_nnue());
+        map.push(OptionsMap::move_overhead());
+        map.push(OptionsMap::slow_mover());
+        map.push(OptionsMap::min_thinking_time());
+        map.push(OptionsMap::move_horizon());
+        map.push(OptionsMap::nodes_time());
+        if cfg!(feature = "tune") {
+            for param in tune::params() {
+                map.push(Box::new(UCITune {param}));
+            }
+        }
         map.sort_by(|a, b|
             a.option_name().cmp(b.option_name()));
 
@@ -88,7 +142,38 @@ impl OptionsMap {
             option_name: "Hash",
             default: DEFAULT_TT_SIZE as i32,
             min: 1,
-            max: 8000,
+            max: TranspositionTable::MAX_SIZE_MB as i32,
+            mutator
+        })
+    }
+
+    fn hash_file() -> Box<UCIOption> {
+        let mutator: fn(&str) -> Option<OptionWork> = |x: &str| {
+            Some(OptionWork::HashFile(x.to_string()))
+        };
+        Box::new(UCIText {
+            option_name: "Hash File",
+            default: DEFAULT_HASH_FILE,
+            mutator
+        })
+    }
+
+    fn save_hash() -> Box<UCIOption> {
+        let mutator: fn() -> Option<OptionWork> = || {
+            Some(OptionWork::SaveHash)
+        };
+        Box::new(UCIButton {
+            option_name: "Save Hash",
+            mutator
+        })
+    }
+
+    fn load_hash() -> Box<UCIOption> {
+        let mutator: fn() -> Option<OptionWork> = || {
+            Some(OptionWork::LoadHash)
+        };
+        Box::new(UCIButton {
+            option_name: "Load Hash",
             mutator
         })
     }
@@ -105,6 +190,140 @@ impl OptionsMap {
             mutator
         })
     }
+
+    fn contempt() -> Box<UCIOption> {
+        let mutator: fn(i32) -> Option<OptionWork> = |x: i32| {
+            Some(OptionWork::Contempt(x))
+        };
+        Box::new(UCISpin {
+            option_name: "Contempt",
+            default: DEFAULT_CONTEMPT,
+            min: -100,
+            max: 100,
+            mutator
+        })
+    }
+
+    fn analysis_contempt() -> Box<UCIOption> {
+        let mutator: fn(&str) -> Option<OptionWork> = |x: &str| {
+            AnalysisContempt::from_name(x).map(OptionWork::AnalysisContempt)
+        };
+        Box::new(UCICombo {
+            option_name: "Analysis Contempt",
+            default: "Off",
+            values: &AnalysisContempt::NAMES,
+            mutator
+        })
+    }
+
+    fn analyse_mode() -> Box<UCIOption> {
+        let mutator: fn(bool) -> Option<OptionWork> = |x: bool| {
+            Some(OptionWork::AnalyseMode(x))
+        };
+        Box::new(UCICheck {
+            option_name: "UCI_AnalyseMode",
+            default: false,
+            mutator
+        })
+    }
+
+    fn show_wdl() -> Box<UCIOption> {
+        let mutator: fn(bool) -> Option<OptionWork> = |x: bool| {
+            Some(OptionWork::ShowWDL(x))
+        };
+        Box::new(UCICheck {
+            option_name: "UCI_ShowWDL",
+            default: false,
+            mutator
+        })
+    }
+
+    fn eval_file() -> Box<UCIOption> {
+        let mutator: fn(&str) -> Option<OptionWork> = |x: &str| {
+            Some(OptionWork::EvalFile(x.to_string()))
+        };
+        Box::new(UCIText {
+            option_name: "EvalFile",
+            default: "<empty>",
+            mutator
+        })
+    }
+
+    fn use_nnue() -> Box<UCIOption> {
+        let mutator: fn(bool) -> Option<OptionWork> = |x: bool| {
+            Some(OptionWork::UseNNUE(x))
+        };
+        Box::new(UCICheck {
+            option_name: "Use NNUE",
+            default: false,
+            mutator
+        })
+    }
+
+    fn move_overhead() -> Box<UCIOption> {
+        let mutator: fn(i32) -> Option<OptionWork> = |x: i32| {
+            Some(OptionWork::MoveOverhead(x))
+        };
+        Box::new(UCISpin {
+            option_name: "Move Overhead",
+            default: DEFAULT_MOVE_OVERHEAD,
+            min: 0,
+            max: 5000,
+            mutator
+        })
+    }
+
+    fn slow_mover() -> Box<UCIOption> {
+        let mutator: fn(i32) -> Option<OptionWork> = |x: i32| {
+            Some(OptionWork::SlowMover(x))
+        };
+        Box::new(UCISpin {
+            option_name: "Slow Mover",
+            default: DEFAULT_SLOW_MOVER,
+            min: 10,
+            max: 1000,
+            mutator
+        })
+    }
+
+    fn min_thinking_time() -> Box<UCIOption> {
+        let mutator: fn(i32) -> Option<OptionWork> = |x: i32| {
+            Some(OptionWork::MinThinkingTime(x))
+        };
+        Box::new(UCISpin {
+            option_name: "Minimum Thinking Time",
+            default: DEFAULT_MIN_THINKING_TIME,
+            min: 0,
+            max: 5000,
+            mutator
+        })
+    }
+
+    fn move_horizon() -> Box<UCIOption> {
+        let mutator: fn(i32) -> Option<OptionWork> = |x: i32| {
+            Some(OptionWork::MoveHorizon(x))
+        };
+        Box::new(UCISpin {
+            option_name: "Move Horizon",
+            default: DEFAULT_MOVE_HORIZON,
+            min: 1,
+            max: 500,
+            mutator
+        })
+    }
+
+    fn nodes_time() -> Box<UCIOption> {
+        let mutator: fn(i32) -> Option<OptionWork> = |x: i32| {
+            Some(OptionWork::NodesTime(x))
+        };
+        Box::new(UCISpin {
+            option_name: "nodestime",
+            default: 0,
+            min: 0,
+            max: 10000,
+            mutator
+        })
+    }
 }
 
 
@@ -115,7 +334,7 @@ impl OptionsMap {
 /// UCI complient options for a searcher.
 pub trait UCIOption {
 
-    // Returns the type of option. This can be one of the following: button, check, spin, text, or combo.
+    // Returns the type of option. This can be one of the following: button, check, spin, string, or combo.
     fn option_type(&self) -> &'static str;
 
     // Returns the exact name of the option.
@@ -169,6 +388,11 @@ pub struct UCICombo {
     mutator: fn(&str) -> Option<OptionWork>
 }
 
+/// A spin option of a tunable parameter.
+pub struct UCITune {
+    param: &'static TuneParam
+}
+
 pub struct UCIText {
     option_name: &'static str,
     default: &'static str,
@@ -271,9 +495,34 @@ impl UCIOption for UCICombo {
     }
 }
 
+impl UCIOption for UCITune {
+    fn option_type(&self) -> &'static str {
+        "spin"
+    }
+
+    fn option_name(&self) -> &'static str {
+        self.param.name()
+    }
+
+    fn partial_display(&self) -> Option<String> {
+        Some(String::from("default ") + &self.param.default().to_string()
+            + " min " + &self.param.min().to_string()
+            + " max " + &self.param.max().to_string())
+    }
+
+    fn mutate(&self, val: &str) -> Option<OptionWork> {
+        if let Ok(integer) = val.parse::<i32>() {
+            if integer >= self.param.min() && integer <= self.param.max() {
+                return Some(OptionWork::Tune(self.param, integer));
+            }
+        }
+        None
+    }
+}
+
 impl UCIOption for UCIText {
     fn option_type(&self) -> &'static str {
-        "text"
+        "string"
     }
 
     fn option_name(&self) -> &'static str {
@@ -8,6 +8,7 @@ fn is_keyword(arg: &str) -> bool {
     match arg {
         "searchmoves" | "ponder" |
         "wtime" |"btime" |"winc" |"binc" |
+        "wdelay" |"bdelay" |"hourglass" |
         "movestogo" |"depth" |"nodes" |"mate" |
         "movetime" | "infinite" => true,
         _ => false
@@ -21,6 +22,9 @@ fn is_keyword(arg: &str) -> bool {
 // "btime" "[msec]"
 // "winc" "[msec]"
 // "binc" "[msec]"
+// "wdelay" "[msec]"  (not UCI, delay before white's clock runs)
+// "bdelay" "[msec]"  (not UCI, delay before black's clock runs)
+// "hourglass"        (not UCI, time used is added to the opponent's clock)
 // "movestogo" "[u32]"
 // "depth" "[u16]"
 // "nodes" "[u64]"
@@ -67,6 +71,23 @@ pub fn parse_time(args: &[&str]) -> PreLimits {
                     token_idx += 1;
                 }
             },
+            "wdelay" => {
+                if let Some(wdelay_s) =  args.get(token_idx + 1) {
+                    if let Ok(wdelay) = wdelay_s.parse::<i64>() {
+                        timer.delay_msec[0] = wdelay;
+                    }
+                    token_idx += 1;
+                }
+            },
+            "bdelay" => {
+                if let Some(bdelay_s) =  args.get(token_idx + 1) {
+                    if let Ok(bdelay) = bdelay_s.parse::<i64>() {
+                        timer.delay_msec[1] = bdelay;
+                    }
+                    token_idx += 1;
+                }
+            },
+            "hourglass" => {timer.hourglass = true;},
             "movestogo" => {
                 if let Some(movestogo_s) =  args.get(token_idx + 1) {
                     if let Ok(movestogo) = movestogo_s.parse::<u32>() {
@@ -222,6 +243,34 @@ mod tests {
         assert_eq!(time.search_moves.len(), 2);
     }
 
+    #[test]
+    fn time_control_parse() {
+        // (go command, [wtime, btime], [winc, binc], [wdelay, bdelay], movestogo, hourglass)
+        let cases: [(&str, [i64; 2], [i64; 2], [i64; 2], u32, bool); 5] = [
+            ("go wtime 60000 btime 50000", [60000, 50000], [0, 0], [0, 0], 0, false),
+            ("go wtime 3000 btime 3000 winc 2000 binc 1000", [3000, 3000], [2000, 1000], [0, 0], 0, false),
+            ("go wtime 9000 btime 8000 movestogo 12", [9000, 8000], [0, 0], [0, 0], 12, false),
+            ("go wtime 30000 btime 40000 wdelay 5000 bdelay 2000", [30000, 40000], [0, 0], [5000, 2000], 0, false),
+            ("go wtime 20000 btime 25000 hourglass searchmoves e2e4", [20000, 25000], [0, 0], [0, 0], 0, true),
+        ];
+        for &(go, time, inc, delay, moves_to_go, hourglass) in cases.iter() {
+            let args: Vec<&str> = go.split_whitespace().collect();
+            let timer = parse_time(&args[1..]).time.unwrap();
+            assert_eq!(timer.time_msec, time, "{}", go);
+            assert_eq!(timer.inc_msec, inc, "{}", go);
+            assert_eq!(timer.delay_msec, delay, "{}", go);
+            assert_eq!(timer.moves_to_go, moves_to_go, "{}", go);
+            assert_eq!(timer.hourglass, hourglass, "{}", go);
+        }
+
+        // A delay alone is still a time control.
+        let args: Vec<&str> = "go wdelay 1000 bdelay 1000".split_whitespace().collect();
+        assert!(parse_time(&args[1..]).time.is_some());
+
+        let args: Vec<&str> = "go hourglass searchmoves e2e4".split_whitespace().collect();
+        assert_eq!(parse_time(&args[1..]).search_moves.len(), 1);
+    }
+
     #[test]
     fn tempboard() {
         // should be e1g1